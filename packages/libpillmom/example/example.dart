@@ -113,26 +113,31 @@ void main() async {
     print('\n--- Creating Medications ---\n');
 
     // Create medications
-    final aspirinId = await client.createMedication(
+    final aspirinId = (await client.createMedication(
       name: 'Aspirin',
       dosage: '100mg',
       description: 'Pain reliever and blood thinner',
-    );
+    )).id;
     print('Created Aspirin with ID: $aspirinId');
 
-    final vitaminDId = await client.createMedication(
+    final vitaminDId = (await client.createMedication(
       name: 'Vitamin D',
       dosage: '1000 IU',
       description: 'Vitamin D supplement',
-    );
+    )).id;
     print('Created Vitamin D with ID: $vitaminDId');
 
-    final omeprazoleId = await client.createMedication(
+    final omeprazole = await client.createMedication(
       name: 'Omeprazole',
       dosage: '20mg',
       description: 'Proton pump inhibitor for acid reflux',
     );
+    final omeprazoleId = omeprazole.id;
     print('Created Omeprazole with ID: $omeprazoleId');
+    for (final warning in omeprazole.warnings) {
+      print('  Interaction (${warning.severity.name}) with '
+          '${warning.medicationName}: ${warning.description}');
+    }
 
     print('\n--- Setting Up Reminders ---\n');
 
@@ -204,6 +209,7 @@ void main() async {
       createdAt: aspirinMed.createdAt,
       updatedAt: aspirinMed.updatedAt,
       deletedAt: aspirinMed.deletedAt,
+      version: aspirinMed.version,
       reminders: aspirinMed.reminders,
     );
 
@@ -229,6 +235,7 @@ void main() async {
         createdAt: firstReminder.createdAt,
         updatedAt: firstReminder.updatedAt,
         deletedAt: firstReminder.deletedAt,
+        version: firstReminder.version,
       );
 
       final reminderUpdateSuccess = await client.updateReminder(updatedReminder);
@@ -1,4 +1,6 @@
 library libpillmom;
 
 export 'src/pillmom_client.dart';
-export 'src/generated/models.dart' show Medication, Reminder;
\ No newline at end of file
+// Only the types present in the generated bindings are exported; the rest
+// (and `error.dart`) are added back when the bindings are regenerated.
+export 'src/generated/models.dart' show Medication, Reminder;
@@ -3,6 +3,7 @@ import 'generated/api.dart';
 import 'generated/models.dart';
 import 'generated/frb_generated.dart';
 import 'dart:io' show Platform;
+import 'dart:typed_data';
 import 'package:path/path.dart' as path;
 
 export 'generated/models.dart';
@@ -53,26 +54,56 @@ class PillMomClient {
   }
 
   // ===== New Connection Methods =====
+  //
+  // Each open method takes [OpenOptions]; without them SQLite's defaults
+  // apply. When several isolates share a file, JournalMode.wal with a busy
+  // timeout avoids "database is locked" errors. With `readOnly: true` every
+  // write throws [PillMomError_ReadOnly] without touching the database.
 
   /// Connect to an in-memory database (no persistence)
-  Future<void> openInMemory() async {
+  Future<void> openInMemory({OpenOptions? options}) async {
     await _ensureInitialized();
     _api ??= PillMomApi();
-    await _api!.openInMemory();
+    await _api!.openInMemory(options: _openOptions(options));
   }
 
   /// Connect to a local SQLite database file
-  Future<void> openLocal(String path) async {
+  Future<void> openLocal(String path, {OpenOptions? options}) async {
     await _ensureInitialized();
     _api ??= PillMomApi();
-    await _api!.openLocal(path: path);
+    await _api!.openLocal(path: path, options: _openOptions(options));
   }
 
-  /// Connect to a remote Turso database
-  Future<void> openRemote(String url, String authToken) async {
+  /// Connect to a local database file encrypted with [encryptionKey].
+  /// Throws if the key does not match the one the file was written with.
+  Future<void> openLocalEncrypted(
+    String path,
+    String encryptionKey, {
+    OpenOptions? options,
+  }) async {
     await _ensureInitialized();
     _api ??= PillMomApi();
-    await _api!.openRemote(url: url, authToken: authToken);
+    await _api!.openLocalEncrypted(
+      path: path,
+      encryptionKey: encryptionKey,
+      options: _openOptions(options),
+    );
+  }
+
+  /// Connect to a remote Turso database. To view with a read-only token,
+  /// pass options with `readOnly: true`.
+  Future<void> openRemote(
+    String url,
+    String authToken, {
+    OpenOptions? options,
+  }) async {
+    await _ensureInitialized();
+    _api ??= PillMomApi();
+    await _api!.openRemote(
+      url: url,
+      authToken: authToken,
+      options: _openOptions(options),
+    );
   }
 
   /// Connect to an embedded replica (local SQLite that syncs with remote)
@@ -82,6 +113,7 @@ class PillMomClient {
     String url,
     String authToken, {
     Duration? syncPeriod,
+    OpenOptions? options,
   }) async {
     await _ensureInitialized();
     _api ??= PillMomApi();
@@ -90,9 +122,22 @@ class PillMomClient {
       url: url,
       authToken: authToken,
       syncPeriod: syncPeriod?.inSeconds.toDouble(),
+      options: _openOptions(options),
     );
   }
 
+  /// [options], or settings that leave every SQLite default in place
+  static OpenOptions _openOptions(OpenOptions? options) {
+    return options ??
+        OpenOptions(
+          journalMode: null,
+          busyTimeoutMs: null,
+          synchronous: null,
+          cacheSizeKib: null,
+          readOnly: false,
+        );
+  }
+
   // ===== Legacy Methods (for backward compatibility) =====
 
   Future<void> initTursoDatabase(String url, String authToken) async {
@@ -110,14 +155,228 @@ class PillMomClient {
     await _api!.syncDatabase();
   }
 
+  /// Re-encrypt the open local database with [newKey]
+  Future<void> changeEncryptionKey(String newKey) async {
+    await _ensureInitialized();
+    _api ??= PillMomApi();
+    await _api!.changeEncryptionKey(newKey: newKey);
+  }
+
   Future<void> closeDatabase() async {
     await _ensureInitialized();
     _api ??= PillMomApi();
     await _api!.closeDatabase();
   }
 
+  /// Snapshot the open database to a file at [path], replacing any file
+  /// there. Safe to call while the database is in use.
+  Future<void> backupTo(String path) async {
+    await _ensureInitialized();
+    _api ??= PillMomApi();
+    await _api!.backupTo(path: path);
+  }
+
+  /// Replace the open local database with a backup made by [backupTo].
+  /// Throws, keeping the current data, if the backup is damaged, from a
+  /// newer version, or encrypted with a different key.
+  Future<void> restoreFrom(String path) async {
+    await _ensureInitialized();
+    _api ??= PillMomApi();
+    await _api!.restoreFrom(path: path);
+  }
+
+  /// Check the database for damage and broken records. With [repair],
+  /// fix what can be fixed safely; see [IntegrityIssue.repaired].
+  Future<IntegrityReport> checkIntegrity({bool repair = false}) async {
+    await _ensureInitialized();
+    _api ??= PillMomApi();
+    return await _api!.checkIntegrity(repair: repair);
+  }
+
+  // Offline outbox
+
+  /// Queue writes in a durable outbox at [path] while a remote database is
+  /// unreachable; they are replayed in order once it is back.
+  Future<void> enableOutbox(String path) async {
+    await _ensureInitialized();
+    _api ??= PillMomApi();
+    await _api!.enableOutbox(path: path);
+  }
+
+  Future<OutboxStatus> flushOutbox() async {
+    await _ensureInitialized();
+    _api ??= PillMomApi();
+    return await _api!.flushOutbox();
+  }
+
+  Future<OutboxStatus> getOutboxStatus() async {
+    await _ensureInitialized();
+    _api ??= PillMomApi();
+    return await _api!.getOutboxStatus();
+  }
+
+  Future<bool> discardOutboxItem(int seq) async {
+    await _ensureInitialized();
+    _api ??= PillMomApi();
+    return await _api!.discardOutboxItem(seq: seq);
+  }
+
+  // Export / import
+
+  /// Dump all medications and reminders as JSON for backup or transfer
+  Future<String> exportJson() async {
+    await _ensureInitialized();
+    _api ??= PillMomApi();
+    return await _api!.exportJson();
+  }
+
+  /// Load data written by [exportJson]. [ImportMode.merge] keeps existing
+  /// records; [ImportMode.replace] clears the database first.
+  Future<ImportSummary> importJson(
+    String data, {
+    ImportMode mode = ImportMode.merge,
+  }) async {
+    await _ensureInitialized();
+    _api ??= PillMomApi();
+    return await _api!.importJson(data: data, mode: mode);
+  }
+
+  /// Current medications and reminders as CSV, one row per reminder
+  Future<String> exportCsv() async {
+    await _ensureInitialized();
+    _api ??= PillMomApi();
+    return await _api!.exportCsv();
+  }
+
+  /// Create medications and reminders from CSV. With [dryRun] the rows are
+  /// only validated; otherwise they are written together if all are valid.
+  Future<CsvImportReport> importCsv(
+    String data, {
+    CsvColumnMapping? mapping,
+    bool dryRun = false,
+  }) async {
+    await _ensureInitialized();
+    _api ??= PillMomApi();
+    return await _api!.importCsv(
+      data: data,
+      // Headers written by exportCsv
+      mapping: mapping ??
+          CsvColumnMapping(
+            name: 'name',
+            dosage: 'dosage',
+            description: 'description',
+            medicationId: 'medication_id',
+            time: 'time',
+            days: 'days',
+            isActive: 'is_active',
+          ),
+      dryRun: dryRun,
+    );
+  }
+
+  /// Current medications as a FHIR R4 Bundle JSON for sharing with a provider
+  Future<String> exportFhirBundle({
+    required String patientName,
+    FhirResourceType resourceType = FhirResourceType.medicationStatement,
+  }) async {
+    await _ensureInitialized();
+    _api ??= PillMomApi();
+    return await _api!.exportFhirBundle(
+      resourceType: resourceType,
+      patientName: patientName,
+      utcOffsetMinutes: DateTime.now().timeZoneOffset.inMinutes,
+    );
+  }
+
+  /// Active reminders as an iCalendar (.ics) feed
+  Future<String> exportIcal() async {
+    await _ensureInitialized();
+    _api ??= PillMomApi();
+    return await _api!.exportIcal(
+      utcOffsetMinutes: DateTime.now().timeZoneOffset.inMinutes,
+    );
+  }
+
+  /// Write the iCalendar feed to a file at [path]
+  Future<void> exportIcalToPath(String path) async {
+    await _ensureInitialized();
+    _api ??= PillMomApi();
+    await _api!.exportIcalToPath(
+      path: path,
+      utcOffsetMinutes: DateTime.now().timeZoneOffset.inMinutes,
+    );
+  }
+
+  /// Printable medication list with adherence over the last [adherenceDays]
+  /// days in the device's time zone, as a self-contained HTML page
+  Future<String> generateReportHtml({int adherenceDays = 30}) async {
+    await _ensureInitialized();
+    _api ??= PillMomApi();
+    return await _api!.generateReportHtml(
+      adherenceDays: adherenceDays,
+      utcOffsetMinutes: DateTime.now().timeZoneOffset.inMinutes,
+    );
+  }
+
+  /// The same report as an A4 PDF, paginated as needed; needs a build with
+  /// the `pdf` feature
+  Future<Uint8List> generateReportPdf({int adherenceDays = 30}) async {
+    await _ensureInitialized();
+    _api ??= PillMomApi();
+    return await _api!.generateReportPdf(
+      adherenceDays: adherenceDays,
+      utcOffsetMinutes: DateTime.now().timeZoneOffset.inMinutes,
+    );
+  }
+
+  // Profiles
+
+  /// Add a person whose medications are managed in this database
+  Future<String> createProfile(String name) async {
+    await _ensureInitialized();
+    _api ??= PillMomApi();
+    return await _api!.createProfile(name: name);
+  }
+
+  Future<List<Profile>> getProfiles() async {
+    await _ensureInitialized();
+    _api ??= PillMomApi();
+    return await _api!.getProfiles();
+  }
+
+  /// Rename a profile
+  Future<bool> updateProfile(Profile profile) async {
+    await _ensureInitialized();
+    _api ??= PillMomApi();
+    return await _api!.updateProfile(profile: profile);
+  }
+
+  /// Delete a profile with its medications; the last one cannot be deleted
+  Future<bool> deleteProfile(String id) async {
+    await _ensureInitialized();
+    _api ??= PillMomApi();
+    return await _api!.deleteProfile(id: id);
+  }
+
+  /// Switch the profile that lists, reminders, history and reports cover and
+  /// that new medications go to; null selects the default (oldest) profile
+  Future<void> setActiveProfile(String? id) async {
+    await _ensureInitialized();
+    _api ??= PillMomApi();
+    await _api!.setActiveProfile(id: id);
+  }
+
+  Future<Profile> getActiveProfile() async {
+    await _ensureInitialized();
+    _api ??= PillMomApi();
+    return await _api!.getActiveProfile();
+  }
+
   // Medication operations
-  Future<int> createMedication({
+
+  /// Add a medication to the active profile. The result carries warnings
+  /// about known interactions with the profile's current medications.
+  Future<CreatedMedication> createMedication({
     required String name,
     required String dosage,
     String description = '',
@@ -131,27 +390,162 @@ class PillMomClient {
     );
   }
 
+  /// Add a medication taken as needed (PRN), with optional limits that
+  /// [canTakeNow] checks
+  Future<CreatedMedication> createPrnMedication({
+    required String name,
+    required String dosage,
+    String description = '',
+    double? minHoursBetweenDoses,
+    int? maxDailyDoses,
+  }) async {
+    await _ensureInitialized();
+    _api ??= PillMomApi();
+    return await _api!.createPrnMedication(
+      name: name,
+      dosage: dosage,
+      description: description,
+      minHoursBetweenDoses: minHoursBetweenDoses,
+      maxDailyDoses: maxDailyDoses,
+    );
+  }
+
+  /// Add a medication taken for a fixed course. The course ends after
+  /// [endDate] ("YYYY-MM-DD") or once [totalDoses] have been taken from
+  /// [startDate], and its reminders are then turned off.
+  Future<CreatedMedication> createCourseMedication({
+    required String name,
+    required String dosage,
+    String description = '',
+    String? startDate,
+    String? endDate,
+    int? totalDoses,
+  }) async {
+    await _ensureInitialized();
+    _api ??= PillMomApi();
+    return await _api!.createCourseMedication(
+      name: name,
+      dosage: dosage,
+      description: description,
+      startDate: startDate,
+      endDate: endDate,
+      totalDoses: totalDoses,
+    );
+  }
+
+  /// Add a medication picked from the drug catalog, keeping its codes and
+  /// using its strength and form as the dosage
+  Future<CreatedMedication> createMedicationFromCatalog(
+    CatalogEntry entry, {
+    String description = '',
+  }) async {
+    await _ensureInitialized();
+    _api ??= PillMomApi();
+    return await _api!.createMedicationFromCatalog(
+      entry: entry,
+      description: description,
+    );
+  }
+
+  /// Load the offline drug catalog from the CSV file at [path]. Returns
+  /// the number of entries.
+  Future<int> loadDrugCatalog(String path) async {
+    await _ensureInitialized();
+    _api ??= PillMomApi();
+    return await _api!.loadDrugCatalog(path: path);
+  }
+
+  /// Catalog entries for autocompleting [query]: name prefixes, names with
+  /// a typo or two, or whole RxNorm, NDC or ATC codes
+  Future<List<CatalogEntry>> searchDrugCatalog(
+    String query, {
+    int limit = 10,
+  }) async {
+    await _ensureInitialized();
+    _api ??= PillMomApi();
+    return await _api!.searchDrugCatalog(query: query, limit: limit);
+  }
+
+  /// Interactions a medication called [name] would have with the current
+  /// list, to warn before it is saved
+  Future<List<InteractionWarning>> checkInteractions(String name) async {
+    await _ensureInitialized();
+    _api ??= PillMomApi();
+    return await _api!.checkInteractions(name: name);
+  }
+
+  /// Check interactions against [data], a JSON dataset in the bundled
+  /// format, instead of the bundled one
+  Future<int> loadInteractionDataset(String data) async {
+    await _ensureInitialized();
+    _api ??= PillMomApi();
+    return await _api!.loadInteractionDataset(data: data);
+  }
+
+  Future<void> useBundledInteractionDataset() async {
+    await _ensureInitialized();
+    _api ??= PillMomApi();
+    await _api!.useBundledInteractionDataset();
+  }
+
   Future<List<Medication>> getAllMedications() async {
     await _ensureInitialized();
     _api ??= PillMomApi();
     return await _api!.getAllMedications();
   }
 
-  Future<bool> updateMedication(Medication medication) async {
+  /// Search, filter, sort and page through medications of [profileId], or
+  /// of the active profile
+  Future<MedicationPage> queryMedications({
+    String? profileId,
+    String? search,
+    bool includeDeleted = false,
+    MedicationSortField sortBy = MedicationSortField.createdAt,
+    bool descending = false,
+    int? limit,
+    int? offset,
+  }) async {
+    await _ensureInitialized();
+    _api ??= PillMomApi();
+    return await _api!.queryMedications(
+      query: MedicationQuery(
+        profileId: profileId,
+        search: search,
+        includeDeleted: includeDeleted,
+        sortBy: sortBy,
+        descending: descending,
+        limit: limit,
+        offset: offset,
+      ),
+    );
+  }
+
+  /// Update a medication. Throws [PillMomError_MedicationConflict] with the
+  /// stored copy if it changed since [medication] was read, unless [policy]
+  /// resolves the conflict ([base] is required for field-level merges).
+  Future<bool> updateMedication(
+    Medication medication, {
+    MergePolicy policy = MergePolicy.reject,
+    Medication? base,
+  }) async {
     await _ensureInitialized();
     _api ??= PillMomApi();
-    return await _api!.updateMedication(medication: medication);
+    return await _api!.updateMedication(
+      medication: medication,
+      policy: policy,
+      base: base,
+    );
   }
 
-  Future<bool> deleteMedication(int id) async {
+  Future<bool> deleteMedication(String id) async {
     await _ensureInitialized();
     _api ??= PillMomApi();
     return await _api!.deleteMedication(id: id);
   }
 
   // Reminder operations
-  Future<int> createReminder({
-    required int medicationId,
+  Future<String> createReminder({
+    required String medicationId,
     required String time,
     required String days,
     bool isActive = true,
@@ -172,15 +566,189 @@ class PillMomClient {
     return await _api!.getActiveReminders();
   }
 
-  Future<bool> updateReminder(Reminder reminder) async {
+  /// Update a reminder; conflicts are handled as in [updateMedication]
+  Future<bool> updateReminder(
+    Reminder reminder, {
+    MergePolicy policy = MergePolicy.reject,
+    Reminder? base,
+  }) async {
     await _ensureInitialized();
     _api ??= PillMomApi();
-    return await _api!.updateReminder(reminder: reminder);
+    return await _api!.updateReminder(
+      reminder: reminder,
+      policy: policy,
+      base: base,
+    );
   }
 
-  Future<bool> deleteReminder(int id) async {
+  Future<bool> deleteReminder(String id) async {
     await _ensureInitialized();
     _api ??= PillMomApi();
     return await _api!.deleteReminder(id: id);
   }
-}
\ No newline at end of file
+
+  // Phased schedules
+
+  /// Give [medicationId] a taper or titration: [phases] that follow each
+  /// other from [startDate] ("YYYY-MM-DD"). A phase replaces the
+  /// medication's reminders on the days it covers.
+  Future<String> createSchedule({
+    required String medicationId,
+    required String startDate,
+    required List<SchedulePhase> phases,
+  }) async {
+    await _ensureInitialized();
+    _api ??= PillMomApi();
+    return await _api!.createSchedule(
+      medicationId: medicationId,
+      startDate: startDate,
+      phases: phases,
+    );
+  }
+
+  Future<PhasedSchedule?> getSchedule(String medicationId) async {
+    await _ensureInitialized();
+    _api ??= PillMomApi();
+    return await _api!.getSchedule(medicationId: medicationId);
+  }
+
+  Future<bool> updateSchedule(PhasedSchedule schedule) async {
+    await _ensureInitialized();
+    _api ??= PillMomApi();
+    return await _api!.updateSchedule(schedule: schedule);
+  }
+
+  Future<bool> deleteSchedule(String id) async {
+    await _ensureInitialized();
+    _api ??= PillMomApi();
+    return await _api!.deleteSchedule(id: id);
+  }
+
+  /// The phase of [medicationId]'s schedule in effect on [date]
+  /// ("YYYY-MM-DD"), if any
+  Future<ActivePhase?> getActivePhase({
+    required String medicationId,
+    required String date,
+  }) async {
+    await _ensureInitialized();
+    _api ??= PillMomApi();
+    return await _api!.getActivePhase(medicationId: medicationId, date: date);
+  }
+
+  /// Doses due on each of [days] days from [startDate] ("YYYY-MM-DD")
+  Future<List<AgendaItem>> getAgenda({
+    required String startDate,
+    int days = 7,
+  }) async {
+    await _ensureInitialized();
+    _api ??= PillMomApi();
+    return await _api!.getAgenda(startDate: startDate, days: days);
+  }
+
+  // Dose history
+
+  /// Record a dose of [medicationId] as taken or skipped. Pass [reminderId]
+  /// and [scheduledFor] when it answers a reminder.
+  Future<String> recordDose({
+    required String medicationId,
+    String? reminderId,
+    DoseStatus status = DoseStatus.taken,
+    DateTime? scheduledFor,
+    DateTime? loggedAt,
+  }) async {
+    await _ensureInitialized();
+    _api ??= PillMomApi();
+    return await _api!.recordDose(
+      medicationId: medicationId,
+      reminderId: reminderId,
+      status: status,
+      scheduledFor: scheduledFor,
+      loggedAt: loggedAt,
+      utcOffsetMinutes: DateTime.now().timeZoneOffset.inMinutes,
+    );
+  }
+
+  /// Whether a dose of [medicationId] may be taken now, or when the next
+  /// one may be and which limit stands in the way
+  Future<DoseAvailability> canTakeNow(String medicationId) async {
+    await _ensureInitialized();
+    _api ??= PillMomApi();
+    return await _api!.canTakeNow(medicationId: medicationId);
+  }
+
+  /// Doses logged from [from] up to (not including) [to], oldest first
+  Future<List<DoseLog>> getDoseHistory({DateTime? from, DateTime? to}) async {
+    await _ensureInitialized();
+    _api ??= PillMomApi();
+    return await _api!.getDoseHistory(from: from, to: to);
+  }
+
+  /// Record scheduled doses not logged within the grace window as missed,
+  /// and list the escalations due by [now] (default: the current time).
+  /// Each escalation keeps its id across runs, so deliver each id once.
+  Future<MissedDoseReport> evaluateMissedDoses({
+    DateTime? now,
+    MissedDoseOptions? options,
+  }) async {
+    await _ensureInitialized();
+    _api ??= PillMomApi();
+    return await _api!.evaluateMissedDoses(
+      now: now,
+      options: options ??
+          MissedDoseOptions(
+            graceMinutes: 60,
+            renotifyAfterMinutes: 15,
+            caregiverAfterMinutes: null,
+            utcOffsetMinutes: DateTime.now().timeZoneOffset.inMinutes,
+            lookbackHours: 24,
+          ),
+    );
+  }
+
+  // Notifications
+
+  /// Local notifications for the doses due in the next [horizonHours], at
+  /// most [limit] of them (iOS keeps 64 pending), in firing order. Ids are
+  /// stable, so diff against what is scheduled and rebuild after reboots,
+  /// edits and time zone changes.
+  Future<List<NotificationRequest>> buildNotificationPlan({
+    int horizonHours = 48,
+    int limit = 64,
+  }) async {
+    await _ensureInitialized();
+    _api ??= PillMomApi();
+    return await _api!.buildNotificationPlan(
+      horizonHours: horizonHours,
+      limit: limit,
+      utcOffsetMinutes: DateTime.now().timeZoneOffset.inMinutes,
+    );
+  }
+
+  // Courses
+
+  /// Turn off the reminders of courses complete on [today] ("YYYY-MM-DD").
+  /// Call once a day; returns the ids of the medications ended.
+  Future<List<String>> endFinishedCourses(String today) async {
+    await _ensureInitialized();
+    _api ??= PillMomApi();
+    return await _api!.endFinishedCourses(
+      today: today,
+      utcOffsetMinutes: DateTime.now().timeZoneOffset.inMinutes,
+    );
+  }
+
+  /// Courses ending within [withinDays] days of [today] ("YYYY-MM-DD"),
+  /// soonest first
+  Future<List<CourseProgress>> getCoursesEndingSoon({
+    required String today,
+    int withinDays = 3,
+  }) async {
+    await _ensureInitialized();
+    _api ??= PillMomApi();
+    return await _api!.getCoursesEndingSoon(
+      today: today,
+      withinDays: withinDays,
+      utcOffsetMinutes: DateTime.now().timeZoneOffset.inMinutes,
+    );
+  }
+}
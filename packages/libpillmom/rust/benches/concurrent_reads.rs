@@ -0,0 +1,126 @@
+//! Compares read throughput with a single connection against WAL mode with
+//! reader connections.
+//!
+//! Run with `cargo bench --bench concurrent_reads`. Several tasks load all
+//! medications at once, first alone and then while another task keeps
+//! writing. With one connection every call waits its turn; in WAL mode
+//! reads run side by side and do not wait for the writer. The read-only
+//! gain grows with the number of cores; on one core only the writer's
+//! effect shows.
+
+use pillmom::{JournalMode, OpenOptions, PillMomApi};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+const MEDICATIONS: usize = 500;
+const READER_TASKS: usize = 8;
+const READS_PER_TASK: usize = 25;
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let path = std::env::temp_dir().join("pillmom-concurrent-reads.db");
+    let path = path.to_string_lossy().to_string();
+
+    println!("journal   workload        reads/s  writes");
+    for mode in [JournalMode::Delete, JournalMode::Wal] {
+        remove_database(&path);
+
+        let api = PillMomApi::new();
+        api.open_local(
+            path.clone(),
+            OpenOptions {
+                journal_mode: Some(mode),
+                busy_timeout_ms: Some(5_000),
+                ..Default::default()
+            },
+        )
+        .await?;
+        seed(&api).await?;
+
+        for with_writer in [false, true] {
+            let (reads_per_second, writes) = measure(with_writer).await?;
+            println!(
+                "{:<8}  {:<14}  {:>7.0}  {:>6}",
+                format!("{:?}", mode).to_lowercase(),
+                if with_writer { "reads + writes" } else { "reads" },
+                reads_per_second,
+                writes
+            );
+        }
+
+        api.close_database().await?;
+    }
+
+    remove_database(&path);
+    Ok(())
+}
+
+/// Medications with three reminders each, written in one transaction
+async fn seed(api: &PillMomApi) -> anyhow::Result<()> {
+    let mut csv = String::from("name,dosage,description,medication_id,time,days,is_active\n");
+    for index in 0..MEDICATIONS {
+        for time in ["08:00", "14:00", "20:00"] {
+            csv.push_str(&format!("Medication {index},10mg,,m{index},{time},Daily,true\n"));
+        }
+    }
+
+    let report = api
+        .import_csv(csv, pillmom::CsvColumnMapping::default(), false)
+        .await?;
+    anyhow::ensure!(report.committed, "seeding failed: {:?}", report.errors);
+    Ok(())
+}
+
+/// Reads per second across all reader tasks, and the writes made meanwhile
+async fn measure(with_writer: bool) -> anyhow::Result<(f64, usize)> {
+    let done = Arc::new(AtomicBool::new(false));
+
+    let writer = with_writer.then(|| {
+        let done = done.clone();
+        tokio::spawn(async move {
+            let api = PillMomApi::new();
+            let mut writes = 0;
+            while !done.load(Ordering::Relaxed) {
+                api.create_medication(format!("Extra {writes}"), "5mg".into(), String::new())
+                    .await?;
+                writes += 1;
+            }
+            anyhow::Ok(writes)
+        })
+    });
+
+    let start = Instant::now();
+    let readers: Vec<_> = (0..READER_TASKS)
+        .map(|_| {
+            tokio::spawn(async {
+                let api = PillMomApi::new();
+                for _ in 0..READS_PER_TASK {
+                    let loaded = api.get_all_medications().await?.len();
+                    anyhow::ensure!(loaded >= MEDICATIONS, "read {} medications", loaded);
+                }
+                anyhow::Ok(())
+            })
+        })
+        .collect();
+
+    for reader in readers {
+        reader.await??;
+    }
+    let elapsed = start.elapsed();
+
+    done.store(true, Ordering::Relaxed);
+    let writes = match writer {
+        Some(writer) => writer.await??,
+        None => 0,
+    };
+
+    let reads = (READER_TASKS * READS_PER_TASK) as f64;
+    Ok((reads / elapsed.as_secs_f64(), writes))
+}
+
+fn remove_database(path: &str) {
+    for suffix in ["", "-wal", "-shm", "-journal"] {
+        let _ = std::fs::remove_file(format!("{path}{suffix}"));
+    }
+}
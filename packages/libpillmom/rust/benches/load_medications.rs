@@ -0,0 +1,71 @@
+//! Measures how `get_all_medications` scales with the number of rows.
+//!
+//! Run with `cargo bench --bench load_medications`. Each step doubles the
+//! data set; a linear loading path keeps the per-medication cost roughly
+//! flat, whereas the old find-per-reminder association grew with every step.
+
+use pillmom::{OpenOptions, PillMomApi};
+use std::time::{Duration, Instant};
+
+const REMINDERS_PER_MEDICATION: usize = 3;
+const SIZES: [usize; 4] = [500, 1_000, 2_000, 4_000];
+const RUNS: u32 = 5;
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let api = PillMomApi::new();
+    api.open_in_memory(OpenOptions::default()).await?;
+
+    let mut seeded = 0;
+    let mut first_per_row: Option<Duration> = None;
+
+    println!("medications  reminders  avg load    per medication");
+    for size in SIZES {
+        while seeded < size {
+            let id = api
+                .create_medication(format!("Medication {seeded}"), "10mg".into(), String::new())
+                .await?
+                .id;
+            for slot in 0..REMINDERS_PER_MEDICATION {
+                api.create_reminder(id.clone(), format!("{:02}:00", 8 + slot * 4), "Daily".into(), true)
+                    .await?;
+            }
+            // Every tenth medication is soft-deleted so the load path has
+            // orphaned reminders to skip
+            if seeded % 10 == 9 {
+                api.delete_medication(id).await?;
+            }
+            seeded += 1;
+        }
+
+        // Warm the statement cache before timing
+        api.get_all_medications().await?;
+
+        let start = Instant::now();
+        let mut loaded = 0;
+        for _ in 0..RUNS {
+            loaded = api.get_all_medications().await?.len();
+        }
+        let avg = start.elapsed() / RUNS;
+        let per_row = avg / loaded as u32;
+
+        println!(
+            "{:>11}  {:>9}  {:>8.2}ms  {:>10.2}us",
+            loaded,
+            loaded * REMINDERS_PER_MEDICATION,
+            avg.as_secs_f64() * 1e3,
+            per_row.as_secs_f64() * 1e6
+        );
+
+        let baseline = *first_per_row.get_or_insert(per_row);
+        if per_row > baseline * 4 {
+            anyhow::bail!(
+                "per-medication load cost grew from {:?} to {:?}; loading is no longer linear",
+                baseline,
+                per_row
+            );
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,185 @@
+use crate::models::{ActivePhase, AgendaItem, PhasedSchedule};
+use crate::repository;
+use crate::schedule::{self, DoseSource};
+use anyhow::Result;
+use chrono::{Duration, NaiveDate};
+
+// Day-by-day view of the doses due, combining reminders with phased
+// schedules. Dates and times are local, like reminder times.
+
+/// Most days one agenda can cover
+const MAX_AGENDA_DAYS: i64 = 366;
+
+/// Doses due on each of the `days` days from `start_date`, in date and
+/// time order
+pub async fn agenda(start_date: &str, days: i64) -> Result<Vec<AgendaItem>> {
+    let start = check_range(start_date, days)?;
+
+    let medications = repository::get_all_medications().await?;
+    let schedules = repository::get_schedules().await?;
+
+    let mut items = Vec::new();
+    for date in start.iter_days().take(days as usize) {
+        let mut day = Vec::new();
+
+        for med in medications.iter().filter(|med| med.deleted_at.is_none()) {
+            let Some(medication_id) = med.id.as_deref() else {
+                continue;
+            };
+            let phased = schedules
+                .iter()
+                .find(|schedule| schedule.medication_id == medication_id);
+
+            for due in schedule::doses_on(med, phased, date) {
+                let mut item = AgendaItem {
+                    date: schedule::format_date(date),
+                    time: due.time.format("%H:%M").to_string(),
+                    medication_id: medication_id.to_string(),
+                    medication_name: med.name.clone(),
+                    dose: med.dosage.clone(),
+                    reminder_id: None,
+                    schedule_id: None,
+                    phase_index: None,
+                };
+                match due.source {
+                    DoseSource::Reminder(reminder) => item.reminder_id = reminder.id.clone(),
+                    DoseSource::Phase {
+                        schedule,
+                        index,
+                        phase,
+                    } => {
+                        item.dose = schedule::describe_dose(phase);
+                        item.schedule_id = schedule.id.clone();
+                        item.phase_index = Some(index as i64);
+                    }
+                }
+                day.push(item);
+            }
+        }
+
+        day.sort_by(|a, b| {
+            a.time
+                .cmp(&b.time)
+                .then_with(|| {
+                    a.medication_name
+                        .to_lowercase()
+                        .cmp(&b.medication_name.to_lowercase())
+                })
+                .then_with(|| a.medication_id.cmp(&b.medication_id))
+        });
+        items.extend(day);
+    }
+
+    Ok(items)
+}
+
+/// The phase of the medication's schedule in effect on `date`, if any
+pub async fn active_phase(medication_id: &str, date: &str) -> Result<Option<ActivePhase>> {
+    let date = parse_start(date)?;
+
+    Ok(repository::get_schedule_for_medication(medication_id)
+        .await?
+        .and_then(|schedule| phase_at(&schedule, date)))
+}
+
+// ===== Helper Functions =====
+
+/// The first day of an agenda of `days` days from `start_date`
+fn check_range(start_date: &str, days: i64) -> Result<NaiveDate> {
+    let start = parse_start(start_date)?;
+    if !(1..=MAX_AGENDA_DAYS).contains(&days) {
+        return Err(anyhow::anyhow!(
+            "Agenda must cover between 1 and {} days",
+            MAX_AGENDA_DAYS
+        ));
+    }
+    Ok(start)
+}
+
+fn phase_at(schedule: &PhasedSchedule, date: NaiveDate) -> Option<ActivePhase> {
+    let (index, start, end) = schedule::phase_on(schedule, date)?;
+
+    Some(ActivePhase {
+        schedule_id: schedule.id.clone().unwrap_or_default(),
+        index: index as i64,
+        phase: schedule.phases[index].clone(),
+        start_date: schedule::format_date(start),
+        end_date: schedule::format_date(end),
+        day_of_phase: (date - start).num_days() + 1,
+    })
+}
+
+fn parse_start(date: &str) -> Result<NaiveDate> {
+    schedule::parse_date(date)
+        .filter(|date| {
+            date.checked_add_signed(Duration::days(MAX_AGENDA_DAYS))
+                .is_some()
+        })
+        .ok_or_else(|| anyhow::anyhow!("Date '{}' is not YYYY-MM-DD", date))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::SchedulePhase;
+
+    fn date(value: &str) -> NaiveDate {
+        schedule::parse_date(value).unwrap()
+    }
+
+    fn range_error(start_date: &str, days: i64) -> String {
+        check_range(start_date, days).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn range_is_limited() {
+        assert_eq!(check_range("2024-03-04", 1).unwrap(), date("2024-03-04"));
+        assert_eq!(check_range("2024-03-04", 366).unwrap(), date("2024-03-04"));
+
+        for days in [0, -1, 367] {
+            assert_eq!(
+                range_error("2024-03-04", days),
+                "Agenda must cover between 1 and 366 days"
+            );
+        }
+        assert_eq!(
+            range_error("04/03/2024", 7),
+            "Date '04/03/2024' is not YYYY-MM-DD"
+        );
+    }
+
+    #[test]
+    fn phase_at_counts_days_of_the_phase() {
+        let phase = |duration_days| SchedulePhase {
+            duration_days,
+            dose_quantity: 10.0,
+            dose_unit: "mg".to_string(),
+            times: vec!["08:00".to_string()],
+            days: "Daily".to_string(),
+            every_n_days: 1,
+        };
+        let schedule = PhasedSchedule {
+            id: Some("0190a5b0-0000-7000-8000-000000000005".to_string()),
+            start_date: "2024-03-04".to_string(),
+            phases: vec![phase(3), phase(5)],
+            ..Default::default()
+        };
+
+        let active = phase_at(&schedule, date("2024-03-09")).unwrap();
+        assert_eq!(active.schedule_id, "0190a5b0-0000-7000-8000-000000000005");
+        assert_eq!(active.index, 1);
+        assert_eq!(active.phase.duration_days, 5);
+        assert_eq!(active.start_date, "2024-03-07");
+        assert_eq!(active.end_date, "2024-03-11");
+        assert_eq!(active.day_of_phase, 3);
+
+        assert_eq!(
+            phase_at(&schedule, date("2024-03-04"))
+                .unwrap()
+                .day_of_phase,
+            1
+        );
+        assert!(phase_at(&schedule, date("2024-03-03")).is_none());
+        assert!(phase_at(&schedule, date("2024-03-12")).is_none());
+    }
+}
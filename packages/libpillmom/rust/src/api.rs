@@ -1,7 +1,28 @@
+use crate::agenda;
+use crate::catalog;
+use crate::course;
+use crate::csv;
 use crate::database;
-use crate::models::{Medication, Reminder};
+use crate::error::PillMomError;
+use crate::export;
+use crate::fhir;
+use crate::ical;
+use crate::integrity;
+use crate::interactions;
+use crate::missed;
+use crate::notifications;
+use crate::models::{
+    ActivePhase, AgendaItem, CatalogEntry, CourseProgress, CreatedMedication, CsvColumnMapping, CsvImportReport, DoseAvailability, DoseLog, DoseStatus, FhirResourceType, ImportMode,
+    ImportSummary, IntegrityReport, InteractionWarning, Medication, MedicationPage, MedicationQuery, MergePolicy, MissedDoseOptions, MissedDoseReport, NotificationRequest,
+    OpenOptions, OutboxStatus, PhasedSchedule, Profile, Reminder, SchedulePhase,
+};
+use crate::outbox::{self, Mutation};
+use crate::prn;
+use crate::report;
 use crate::repository;
+use crate::schedule;
 use anyhow::Result;
+use chrono::{DateTime, Utc};
 use flutter_rust_bridge::frb;
 
 #[frb(opaque)]
@@ -16,18 +37,37 @@ impl PillMomApi {
     // ===== Database Connection Methods =====
 
     /// Connect to an in-memory database (no persistence)
-    pub async fn open_in_memory(&self) -> Result<()> {
-        database::init_in_memory().await
+    pub async fn open_in_memory(&self, options: OpenOptions) -> Result<()> {
+        database::init_in_memory(&options).await
     }
 
     /// Connect to a local SQLite database file
-    pub async fn open_local(&self, path: String) -> Result<()> {
-        database::init_local_db(&path).await
+    pub async fn open_local(&self, path: String, options: OpenOptions) -> Result<()> {
+        database::init_local_db(&path, None, &options).await
     }
 
-    /// Connect to a remote Turso database
-    pub async fn open_remote(&self, url: String, auth_token: String) -> Result<()> {
-        database::init_remote_db(&url, &auth_token).await
+    /// Connect to a local database file encrypted with `encryption_key`.
+    /// A new file is created encrypted; an existing one must have been
+    /// written with the same key.
+    pub async fn open_local_encrypted(
+        &self,
+        path: String,
+        encryption_key: String,
+        options: OpenOptions,
+    ) -> Result<()> {
+        database::init_local_db(&path, Some(&encryption_key), &options).await
+    }
+
+    /// Connect to a remote Turso database. For viewing with a read-only
+    /// token, set `options.read_only` so writes fail with
+    /// `PillMomError::ReadOnly` instead of reaching the server.
+    pub async fn open_remote(
+        &self,
+        url: String,
+        auth_token: String,
+        options: OpenOptions,
+    ) -> Result<()> {
+        database::init_remote_db(&url, &auth_token, &options).await
     }
 
     /// Connect to an embedded replica (local SQLite that syncs with remote)
@@ -38,97 +78,632 @@ impl PillMomApi {
         url: String,
         auth_token: String,
         _sync_period: Option<f64>, // Sync period in seconds
+        options: OpenOptions,
     ) -> Result<()> {
         // TODO: Fix embedded replica crash and use sync_period
         // For now, fall back to remote connection
-        database::init_remote_db(&url, &auth_token).await
+        database::init_remote_db(&url, &auth_token, &options).await
     }
 
     // Legacy methods for backward compatibility
     pub async fn init_turso_database(&self, url: String, auth_token: String) -> Result<()> {
-        self.open_remote(url, auth_token).await
+        self.open_remote(url, auth_token, OpenOptions::default()).await
     }
 
     pub async fn init_local_database(&self, path: String) -> Result<()> {
-        self.open_local(path).await
+        self.open_local(path, OpenOptions::default()).await
     }
 
     // ===== Database Operations =====
 
     pub async fn sync_database(&self) -> Result<i64> {
+        // A read-only session only pulls; queued writes wait for a writable one
+        if !database::is_read_only() {
+            outbox::flush().await?;
+        }
         database::sync_database().await
     }
 
+    /// Re-encrypt the open local database with a new key. Also encrypts a
+    /// file that was opened without one. Fails for a file in WAL mode.
+    pub async fn change_encryption_key(&self, new_key: String) -> Result<(), PillMomError> {
+        Ok(database::rekey(&new_key).await?)
+    }
+
+    /// Close the current database so another one (or the same one) can be
+    /// opened. Also turns off the outbox; call `enable_outbox` again for the
+    /// next session.
     pub async fn close_database(&self) -> Result<()> {
         database::close_database().await
     }
 
+    /// Snapshot the open local or in-memory database to a file at `path`,
+    /// replacing any file there. Safe while the database is in use.
+    pub async fn backup_to(&self, path: String) -> Result<()> {
+        database::backup_to(&path).await
+    }
+
+    /// Replace the open local database with a backup made by `backup_to`.
+    /// The backup is checked first and the current data is kept if it
+    /// cannot be used. The database stays open afterwards.
+    pub async fn restore_from(&self, path: String) -> Result<(), PillMomError> {
+        Ok(database::restore_from(&path).await?)
+    }
+
+    /// Check the database for damage and broken records. With `repair`,
+    /// fix what can be fixed without losing usable data; the report marks
+    /// which issues were repaired.
+    pub async fn check_integrity(&self, repair: bool) -> Result<IntegrityReport, PillMomError> {
+        Ok(integrity::check_integrity(repair).await?)
+    }
+
+    // ===== Offline Outbox =====
+
+    /// Keep a durable queue at `path` for writes made while a remote
+    /// database is unreachable. Queued writes are replayed in order on the
+    /// next write, `sync_database` or `flush_outbox`.
+    pub async fn enable_outbox(&self, path: String) -> Result<()> {
+        outbox::enable(&path).await
+    }
+
+    /// Replay queued writes now
+    pub async fn flush_outbox(&self) -> Result<OutboxStatus, PillMomError> {
+        Ok(outbox::flush().await?)
+    }
+
+    pub async fn get_outbox_status(&self) -> Result<OutboxStatus> {
+        outbox::status().await
+    }
+
+    /// Remove a queued or failed write without applying it
+    pub async fn discard_outbox_item(&self, seq: i64) -> Result<bool> {
+        outbox::discard(seq).await
+    }
+
+    // ===== Export / Import =====
+
+    /// Dump all medications and reminders, soft-deleted ones included, as
+    /// JSON tagged with the schema version
+    pub async fn export_json(&self) -> Result<String> {
+        export::export_json().await
+    }
+
+    /// Load data written by `export_json`. `Merge` keeps existing records
+    /// and takes imported ones that are new or more recently updated;
+    /// `Replace` clears the database first. Records with unusable ids are
+    /// given new ones.
+    pub async fn import_json(
+        &self,
+        data: String,
+        mode: ImportMode,
+    ) -> Result<ImportSummary, PillMomError> {
+        Ok(export::import_json(&data, mode).await?)
+    }
+
+    /// Current medications and reminders as CSV, one row per reminder.
+    /// Cells starting with `=`, `+`, `-`, `@`, a tab or a carriage return
+    /// get a leading `'` so spreadsheets do not run them as formulas.
+    pub async fn export_csv(&self) -> Result<String> {
+        csv::export_csv().await
+    }
+
+    /// Create medications and reminders from CSV rows. Every row is checked
+    /// first and the report lists per-row errors; rows are written in one
+    /// transaction only if all are valid and `dry_run` is false.
+    pub async fn import_csv(
+        &self,
+        data: String,
+        mapping: CsvColumnMapping,
+        dry_run: bool,
+    ) -> Result<CsvImportReport, PillMomError> {
+        Ok(csv::import_csv(&data, &mapping, dry_run).await?)
+    }
+
+    /// Current medications as a FHIR R4 `collection` Bundle JSON, one
+    /// `resource_type` resource per medication, for sharing with a provider.
+    /// Courses over by the local date at `utc_offset_minutes` are marked
+    /// completed.
+    pub async fn export_fhir_bundle(
+        &self,
+        resource_type: FhirResourceType,
+        patient_name: String,
+        utc_offset_minutes: i64,
+    ) -> Result<String> {
+        fhir::export_bundle(resource_type, &patient_name, utc_offset_minutes).await
+    }
+
+    /// Active reminders as an iCalendar (.ics) feed: one recurring event per
+    /// reminder, with an alarm at the dose time. Each event starts on the
+    /// day its reminder was created, local to `utc_offset_minutes`.
+    pub async fn export_ical(&self, utc_offset_minutes: i64) -> Result<String> {
+        ical::export_calendar(utc_offset_minutes).await
+    }
+
+    /// Write the feed from `export_ical` to a file at `path`
+    pub async fn export_ical_to_path(&self, path: String, utc_offset_minutes: i64) -> Result<()> {
+        ical::export_calendar_to_path(&path, utc_offset_minutes).await
+    }
+
+    /// Medication list and adherence summary over the last `adherence_days`
+    /// days, as a self-contained printable HTML page. Days and times are
+    /// local to `utc_offset_minutes`.
+    pub async fn generate_report_html(
+        &self,
+        adherence_days: i64,
+        utc_offset_minutes: i64,
+    ) -> Result<String> {
+        report::html_report(adherence_days, utc_offset_minutes).await
+    }
+
+    /// The report from `generate_report_html` as an A4 PDF, over as many
+    /// pages as it needs. Needs the `pdf` feature.
+    pub async fn generate_report_pdf(
+        &self,
+        adherence_days: i64,
+        utc_offset_minutes: i64,
+    ) -> Result<Vec<u8>> {
+        report::pdf_report(adherence_days, utc_offset_minutes).await
+    }
+
+    // ===== Profiles =====
+
+    /// Add a person whose medications are managed in this database
+    pub async fn create_profile(&self, name: String) -> Result<String, PillMomError> {
+        let profile = Profile {
+            id: Some(repository::new_record_id()),
+            name,
+            ..Default::default()
+        };
+        Ok(outbox::submit(Mutation::CreateProfile { profile }).await?.id())
+    }
+
+    /// Live profiles, oldest first
+    pub async fn get_profiles(&self) -> Result<Vec<Profile>> {
+        repository::get_profiles().await
+    }
+
+    /// Rename a profile
+    pub async fn update_profile(&self, profile: Profile) -> Result<bool, PillMomError> {
+        Ok(outbox::submit(Mutation::UpdateProfile { profile }).await?.changed())
+    }
+
+    /// Delete a profile with its medications and reminders. The last
+    /// profile cannot be deleted; deleting the active one switches back to
+    /// the oldest remaining profile.
+    pub async fn delete_profile(&self, id: String) -> Result<bool, PillMomError> {
+        let deleted = outbox::submit(Mutation::DeleteProfile { id: id.clone() })
+            .await?
+            .changed();
+        if database::active_profile().as_deref() == Some(id.as_str()) {
+            database::set_active_profile(None)?;
+        }
+        Ok(deleted)
+    }
+
+    /// Choose the profile that medication lists, reminders, dose history,
+    /// reports and exports other than `export_json` cover, and that new
+    /// medications are created in. `None` selects the oldest profile, which
+    /// is also the default. Applies to this session only.
+    pub async fn set_active_profile(&self, id: Option<String>) -> Result<()> {
+        if let Some(id) = id.as_deref() {
+            repository::get_profile(Some(id)).await?;
+        }
+        database::set_active_profile(id)
+    }
+
+    pub async fn get_active_profile(&self) -> Result<Profile> {
+        repository::get_profile(None).await
+    }
+
     // ===== Medication CRUD =====
 
+    /// Add a medication to the active profile. The result warns about
+    /// known interactions with the profile's current medications; they do
+    /// not stop the medication being added.
     pub async fn create_medication(
         &self,
         name: String,
         dosage: String,
         description: String,
-    ) -> Result<i64> {
-        let med = Medication {
-            id: None,
+    ) -> Result<CreatedMedication, PillMomError> {
+        // Assign the id up front so a queued create reports the id it will keep
+        let medication = Medication {
+            id: Some(repository::new_record_id()),
+            profile_id: database::active_profile(),
+            name,
+            dosage,
+            description,
+            ..Default::default()
+        };
+        create_checked(medication).await
+    }
+
+    /// Add a medication taken as needed (PRN) to the active profile, with
+    /// optional safety limits checked by `can_take_now`. Warns about
+    /// interactions like `create_medication`.
+    pub async fn create_prn_medication(
+        &self,
+        name: String,
+        dosage: String,
+        description: String,
+        min_hours_between_doses: Option<f64>,
+        max_daily_doses: Option<i64>,
+    ) -> Result<CreatedMedication, PillMomError> {
+        let medication = Medication {
+            id: Some(repository::new_record_id()),
+            profile_id: database::active_profile(),
+            name,
+            dosage,
+            description,
+            is_prn: true,
+            min_hours_between_doses,
+            max_daily_doses,
+            ..Default::default()
+        };
+        prn::check_limits(&medication)?;
+        create_checked(medication).await
+    }
+
+    /// Add a medication taken for a fixed course, e.g. a ten-day
+    /// antibiotic, to the active profile. The course ends after
+    /// `end_date` ("YYYY-MM-DD") or once `total_doses` have been taken from
+    /// `start_date`, whichever comes first, and its reminders are then
+    /// turned off. Warns about interactions like `create_medication`.
+    pub async fn create_course_medication(
+        &self,
+        name: String,
+        dosage: String,
+        description: String,
+        start_date: Option<String>,
+        end_date: Option<String>,
+        total_doses: Option<i64>,
+    ) -> Result<CreatedMedication, PillMomError> {
+        let medication = Medication {
+            id: Some(repository::new_record_id()),
+            profile_id: database::active_profile(),
             name,
             dosage,
             description,
-            created_at: Default::default(),
-            updated_at: Default::default(),
-            deleted_at: None,
-            reminders: Vec::new(),
+            course_start_date: start_date,
+            course_end_date: end_date,
+            course_total_doses: total_doses,
+            ..Default::default()
+        };
+        course::check_course(&medication)?;
+        create_checked(medication).await
+    }
+
+    /// Add a medication picked from the drug catalog to the active
+    /// profile, with its codes and with its strength and form as the
+    /// dosage. Warns about interactions like `create_medication`.
+    pub async fn create_medication_from_catalog(
+        &self,
+        entry: CatalogEntry,
+        description: String,
+    ) -> Result<CreatedMedication, PillMomError> {
+        let medication = Medication {
+            id: Some(repository::new_record_id()),
+            profile_id: database::active_profile(),
+            ..catalog::medication_from_entry(&entry, description)?
+        };
+        create_checked(medication).await
+    }
+
+    /// Load the offline drug catalog from the CSV file at `path`: a `name`
+    /// column and optional `strength`, `form`, `rxnorm_cui`, `ndc` and
+    /// `atc_code` columns. Replaces any catalog loaded before; returns the
+    /// number of entries.
+    pub async fn load_drug_catalog(&self, path: String) -> Result<i64> {
+        catalog::load_catalog(&path)
+    }
+
+    /// Up to `limit` catalog entries for autocompleting `query`, best
+    /// first. Matches name prefixes, names with a typo or two, and whole
+    /// RxNorm, NDC or ATC codes.
+    pub async fn search_drug_catalog(&self, query: String, limit: i64) -> Result<Vec<CatalogEntry>> {
+        catalog::search(&query, limit)
+    }
+
+    /// Known interactions a medication called `name` would have with the
+    /// active profile's current medications, e.g. to warn before saving
+    pub async fn check_interactions(&self, name: String) -> Result<Vec<InteractionWarning>> {
+        let medication = Medication {
+            name,
+            ..Default::default()
         };
-        repository::create_medication(&med).await
+        interactions::check_medication(&medication).await
+    }
+
+    /// Check interactions against `data`, a JSON dataset in the format of
+    /// the bundled one, instead. Returns the number of interactions loaded.
+    pub async fn load_interaction_dataset(&self, data: String) -> Result<i64> {
+        interactions::load_dataset(&data)
+    }
+
+    /// Go back to the interaction dataset bundled with the library
+    pub async fn use_bundled_interaction_dataset(&self) -> Result<()> {
+        interactions::use_bundled_dataset();
+        Ok(())
     }
 
     pub async fn get_all_medications(&self) -> Result<Vec<Medication>> {
         repository::get_all_medications().await
     }
 
-    pub async fn update_medication(&self, medication: Medication) -> Result<bool> {
-        repository::update_medication(&medication).await
+    /// Search, filter, sort and page through medications
+    pub async fn query_medications(&self, query: MedicationQuery) -> Result<MedicationPage> {
+        repository::query_medications(&query).await
+    }
+
+    /// Update a medication read at `medication.version`. On a concurrent
+    /// edit, `policy` decides the outcome; `FieldLevelMerge` needs `base`,
+    /// the copy the edit started from.
+    pub async fn update_medication(
+        &self,
+        medication: Medication,
+        policy: MergePolicy,
+        base: Option<Medication>,
+    ) -> Result<bool, PillMomError> {
+        let mut medication = medication;
+        catalog::check_codes(&mut medication)?;
+        prn::check_limits(&medication)?;
+        course::check_course(&medication)?;
+        let mutation = Mutation::UpdateMedication {
+            medication,
+            policy,
+            base: base.map(Box::new),
+        };
+        Ok(outbox::submit(mutation).await?.changed())
     }
 
-    pub async fn delete_medication(&self, id: i64) -> Result<bool> {
-        repository::delete_medication(id).await
+    pub async fn delete_medication(&self, id: String) -> Result<bool, PillMomError> {
+        Ok(outbox::submit(Mutation::DeleteMedication { id }).await?.changed())
     }
 
     // ===== Reminder CRUD =====
 
     pub async fn create_reminder(
         &self,
-        medication_id: i64,
+        medication_id: String,
         time: String,
         days: String,
         is_active: bool,
-    ) -> Result<i64> {
+    ) -> Result<String, PillMomError> {
         let reminder = Reminder {
-            id: None,
+            id: Some(repository::new_record_id()),
             medication_id,
             time,
             days,
             is_active,
-            created_at: Default::default(),
-            updated_at: Default::default(),
-            deleted_at: None,
+            ..Default::default()
         };
-        repository::create_reminder(&reminder).await
+        Ok(outbox::submit(Mutation::CreateReminder { reminder }).await?.id())
     }
 
     pub async fn get_active_reminders(&self) -> Result<Vec<Reminder>> {
         repository::get_active_reminders().await
     }
 
-    pub async fn update_reminder(&self, reminder: Reminder) -> Result<bool> {
-        repository::update_reminder(&reminder).await
+    /// Update a reminder read at `reminder.version`; see `update_medication`
+    pub async fn update_reminder(
+        &self,
+        reminder: Reminder,
+        policy: MergePolicy,
+        base: Option<Reminder>,
+    ) -> Result<bool, PillMomError> {
+        let mutation = Mutation::UpdateReminder {
+            reminder,
+            policy,
+            base,
+        };
+        Ok(outbox::submit(mutation).await?.changed())
     }
 
-    pub async fn delete_reminder(&self, id: i64) -> Result<bool> {
-        repository::delete_reminder(id).await
+    pub async fn delete_reminder(&self, id: String) -> Result<bool, PillMomError> {
+        Ok(outbox::submit(Mutation::DeleteReminder { id }).await?.changed())
     }
+
+    // ===== Phased Schedules =====
+
+    /// Give a medication a taper or titration: phases that follow each other
+    /// from `start_date` ("YYYY-MM-DD"), each with its own dose and times.
+    /// While a phase is in effect it replaces the medication's reminders;
+    /// after the last phase the reminders apply again. A medication has at
+    /// most one schedule.
+    pub async fn create_schedule(
+        &self,
+        medication_id: String,
+        start_date: String,
+        phases: Vec<SchedulePhase>,
+    ) -> Result<String, PillMomError> {
+        let schedule = PhasedSchedule {
+            id: Some(repository::new_record_id()),
+            medication_id,
+            start_date,
+            phases,
+            ..Default::default()
+        };
+        schedule::check_schedule(&schedule)?;
+        Ok(outbox::submit(Mutation::CreateSchedule { schedule }).await?.id())
+    }
+
+    pub async fn get_schedule(&self, medication_id: String) -> Result<Option<PhasedSchedule>> {
+        repository::get_schedule_for_medication(&medication_id).await
+    }
+
+    /// Replace the start date and phases of a schedule read at
+    /// `schedule.version`. Fails with `PillMomError::ScheduleConflict`,
+    /// carrying the stored copy, if it has changed since.
+    pub async fn update_schedule(&self, schedule: PhasedSchedule) -> Result<bool, PillMomError> {
+        schedule::check_schedule(&schedule)?;
+        Ok(outbox::submit(Mutation::UpdateSchedule { schedule }).await?.changed())
+    }
+
+    pub async fn delete_schedule(&self, id: String) -> Result<bool, PillMomError> {
+        Ok(outbox::submit(Mutation::DeleteSchedule { id }).await?.changed())
+    }
+
+    /// The phase of the medication's schedule in effect on `date`
+    /// ("YYYY-MM-DD"), or `None` before it starts, after it ends, or if the
+    /// medication has no schedule
+    pub async fn get_active_phase(
+        &self,
+        medication_id: String,
+        date: String,
+    ) -> Result<Option<ActivePhase>> {
+        agenda::active_phase(&medication_id, &date).await
+    }
+
+    /// Doses due on each of the `days` days (1 to 366) from `start_date`
+    /// ("YYYY-MM-DD"), from phased schedules and reminders, in date and
+    /// time order
+    pub async fn get_agenda(&self, start_date: String, days: i64) -> Result<Vec<AgendaItem>> {
+        agenda::agenda(&start_date, days).await
+    }
+
+    // ===== Dose History =====
+
+    /// Record a dose taken or skipped. `reminder_id` and `scheduled_for`
+    /// link it to a scheduled dose; `logged_at` defaults to now. A taken
+    /// dose that completes a course turns its reminders off and deletes
+    /// its phased schedule; the course's start date is read as local to
+    /// `utc_offset_minutes`.
+    pub async fn record_dose(
+        &self,
+        medication_id: String,
+        reminder_id: Option<String>,
+        status: DoseStatus,
+        scheduled_for: Option<DateTime<Utc>>,
+        logged_at: Option<DateTime<Utc>>,
+        utc_offset_minutes: i64,
+    ) -> Result<String, PillMomError> {
+        let dose = DoseLog {
+            id: Some(repository::new_record_id()),
+            medication_id,
+            reminder_id,
+            status,
+            scheduled_for,
+            logged_at: logged_at.unwrap_or_else(Utc::now),
+        };
+        let medication_id = dose.medication_id.clone();
+        let completes = dose.status == DoseStatus::Taken;
+        let id = outbox::submit(Mutation::RecordDose { dose }).await?.id();
+
+        // The dose is recorded either way; a failed check is caught up by
+        // `end_finished_courses`
+        if completes {
+            if let Err(e) = course::end_if_doses_taken(&medication_id, utc_offset_minutes).await {
+                tracing::warn!("Course check skipped for medication {}: {}", medication_id, e);
+            }
+        }
+
+        Ok(id)
+    }
+
+    /// Whether a dose of the medication may be taken now under its
+    /// minimum interval and daily maximum, and if not, when and why not
+    pub async fn can_take_now(&self, medication_id: String) -> Result<DoseAvailability> {
+        prn::can_take_now(&medication_id).await
+    }
+
+    /// Doses logged from `from` (inclusive) to `to` (exclusive), oldest
+    /// first; leave a bound out to leave that end open
+    pub async fn get_dose_history(
+        &self,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> Result<Vec<DoseLog>> {
+        repository::get_dose_logs(from, to).await
+    }
+
+    /// Record scheduled doses not taken or skipped within
+    /// `options.grace_minutes` of their time as missed, looking back
+    /// `options.lookback_hours` from `now` (default: the current time), and
+    /// list the escalations due by then. Run it periodically, e.g. when the
+    /// app wakes; doses already recorded are not recorded again, and each
+    /// escalation keeps its id across runs so it can be delivered once.
+    pub async fn evaluate_missed_doses(
+        &self,
+        now: Option<DateTime<Utc>>,
+        options: MissedDoseOptions,
+    ) -> Result<MissedDoseReport, PillMomError> {
+        Ok(missed::evaluate(now.unwrap_or_else(Utc::now), &options).await?)
+    }
+
+    // ===== Notifications =====
+
+    /// Local notifications for the doses due in the next `horizon_hours`
+    /// (up to 744), at most `limit` of them (up to 1000, e.g. 64 for iOS),
+    /// in firing order. When there are more, each medication's next dose is
+    /// kept first and then the soonest others. Doses already logged are
+    /// left out, including ones logged without a time within the default
+    /// missed-dose grace window. Reminder times are read as local times at
+    /// `utc_offset_minutes`, so rebuild the plan when the offset changes,
+    /// after a reboot, and after edits; ids are stable across plans, and
+    /// numeric ids are unique within one.
+    pub async fn build_notification_plan(
+        &self,
+        horizon_hours: i64,
+        limit: i64,
+        utc_offset_minutes: i64,
+    ) -> Result<Vec<NotificationRequest>> {
+        notifications::plan(Utc::now(), horizon_hours, limit, utc_offset_minutes).await
+    }
+
+    // ===== Courses =====
+
+    /// Turn off the reminders and delete the phased schedules of
+    /// medications whose course is complete on `today` ("YYYY-MM-DD", local
+    /// at `utc_offset_minutes`): past its end date or with all its doses
+    /// taken. Call once a day, e.g. at startup;
+    /// courses completed by `record_dose` end straight away. Returns the
+    /// medications ended.
+    pub async fn end_finished_courses(
+        &self,
+        today: String,
+        utc_offset_minutes: i64,
+    ) -> Result<Vec<String>, PillMomError> {
+        Ok(course::end_finished_courses(&today, utc_offset_minutes).await?)
+    }
+
+    /// Courses ending within `within_days` days (0 to 366) of `today`
+    /// ("YYYY-MM-DD", local at `utc_offset_minutes`), soonest first. Courses
+    /// with a total number of doses are projected from their reminders or
+    /// phased schedule.
+    pub async fn get_courses_ending_soon(
+        &self,
+        today: String,
+        within_days: i64,
+        utc_offset_minutes: i64,
+    ) -> Result<Vec<CourseProgress>> {
+        course::courses_ending_soon(&today, within_days, utc_offset_minutes).await
+    }
+}
+
+/// Submit a new medication, warning about interactions with the active
+/// profile's current medications
+async fn create_checked(medication: Medication) -> Result<CreatedMedication, PillMomError> {
+    // Checked first so the list read is the one the medication joins
+    let warnings = interactions::check_medication(&medication).await;
+    let id = outbox::submit(Mutation::CreateMedication { medication })
+        .await?
+        .id();
+
+    Ok(match warnings {
+        Ok(warnings) => CreatedMedication {
+            id,
+            warnings,
+            interactions_checked: true,
+        },
+        Err(e) => {
+            tracing::warn!("Interaction check skipped for medication {}: {}", id, e);
+            CreatedMedication {
+                id,
+                warnings: Vec::new(),
+                interactions_checked: false,
+            }
+        }
+    })
 }
 
 // Convenience function to create the API instance
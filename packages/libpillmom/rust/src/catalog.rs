@@ -0,0 +1,420 @@
+use crate::csv;
+use crate::interactions::normalize;
+use crate::models::{CatalogEntry, Medication};
+use anyhow::Result;
+use std::sync::{Arc, RwLock};
+
+// Offline drug dictionary for autocompleting medication names. The app
+// ships or downloads a CSV file with a `name` column and optional
+// `strength`, `form`, `rxnorm_cui`, `ndc` and `atc_code` columns, e.g. one
+// exported from RxNorm. Searches match name prefixes first, then names a
+// typo or two away, and codes exactly.
+
+static CATALOG: RwLock<Option<Arc<Catalog>>> = RwLock::new(None);
+
+const COLUMNS: [&str; 6] = ["name", "strength", "form", "rxnorm_cui", "ndc", "atc_code"];
+
+/// Shortest query that is matched with typos allowed; shorter ones would
+/// match almost everything
+const MIN_FUZZY_LENGTH: usize = 4;
+
+struct Catalog {
+    /// Entries with their normalised names
+    entries: Vec<(CatalogEntry, String)>,
+}
+
+/// How an entry matched a query; earlier variants rank first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum MatchKind {
+    Code,
+    Exact,
+    Prefix,
+    WordPrefix,
+    /// Every word of the query starts a word of the name, in any order
+    AllWords,
+    /// Typos away from a prefix, by edit distance and then whether the
+    /// prefix is of a later word
+    Fuzzy(usize, bool),
+}
+
+impl Catalog {
+    fn parse(data: &str) -> Result<Self> {
+        let records = csv::parse(data)
+            .map_err(|e| anyhow::anyhow!("Drug catalog line {}: {}", e.line, e.message))?;
+        let mut records = records.into_iter();
+
+        let (_, header) = records
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("Drug catalog is empty"))?;
+        let indices = COLUMNS.map(|column| {
+            header
+                .iter()
+                .position(|h| h.trim().eq_ignore_ascii_case(column))
+        });
+        if indices[0].is_none() {
+            return Err(anyhow::anyhow!("Drug catalog header has no 'name' column"));
+        }
+
+        let mut entries = Vec::new();
+        for (line, fields) in records {
+            let [name, strength, form, rxnorm_cui, ndc, atc_code] = indices.map(|index| {
+                index
+                    .and_then(|index| fields.get(index))
+                    .map(|value| value.trim().to_string())
+                    .unwrap_or_default()
+            });
+            if name.is_empty() {
+                return Err(anyhow::anyhow!("Drug catalog line {}: name is empty", line));
+            }
+
+            let mut entry = CatalogEntry {
+                name,
+                strength,
+                form,
+                rxnorm_cui: Some(rxnorm_cui),
+                ndc: Some(ndc),
+                atc_code: Some(atc_code),
+            };
+            normalize_codes(&mut entry.rxnorm_cui, &mut entry.ndc, &mut entry.atc_code)
+                .map_err(|e| anyhow::anyhow!("Drug catalog line {}: {}", line, e))?;
+
+            let key = normalize(&entry.name);
+            entries.push((entry, key));
+        }
+
+        Ok(Self { entries })
+    }
+
+    /// Up to `limit` entries matching `query`, best first
+    fn search(&self, query: &str, limit: usize) -> Vec<CatalogEntry> {
+        let query = normalize(query);
+        if query.is_empty() {
+            return Vec::new();
+        }
+        let code = query.replace(' ', "");
+        let chars: Vec<char> = query.chars().collect();
+
+        let mut matches: Vec<(MatchKind, &CatalogEntry, &String)> = self
+            .entries
+            .iter()
+            .filter_map(|(entry, name)| {
+                let kind = match_kind(entry, name, &query, &code, &chars)?;
+                Some((kind, entry, name))
+            })
+            .collect();
+
+        matches.sort_by(|a, b| {
+            a.0.cmp(&b.0)
+                .then_with(|| a.2.len().cmp(&b.2.len()))
+                .then_with(|| a.2.cmp(b.2))
+        });
+        matches
+            .into_iter()
+            .take(limit)
+            .map(|(_, entry, _)| entry.clone())
+            .collect()
+    }
+}
+
+/// Load the catalog from the CSV file at `path`, replacing any loaded
+/// before. Returns the number of entries.
+pub fn load_catalog(path: &str) -> Result<i64> {
+    let data = std::fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("Cannot read drug catalog {}: {}", path, e))?;
+    let catalog = Catalog::parse(&data)?;
+    let count = catalog.entries.len() as i64;
+    *CATALOG.write().unwrap_or_else(|e| e.into_inner()) = Some(Arc::new(catalog));
+    Ok(count)
+}
+
+/// Up to `limit` catalog entries matching `query`, best first. `query` is
+/// the start of a name, possibly misspelt, or a whole RxNorm, NDC or ATC
+/// code.
+pub fn search(query: &str, limit: i64) -> Result<Vec<CatalogEntry>> {
+    let catalog = CATALOG
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+        .ok_or_else(|| anyhow::anyhow!("No drug catalog loaded"))?;
+    Ok(catalog.search(query, limit.max(0) as usize))
+}
+
+/// A new medication for the active profile filled in from `entry`, with
+/// the strength and form as its dosage
+pub fn medication_from_entry(entry: &CatalogEntry, description: String) -> Result<Medication> {
+    let mut medication = Medication {
+        name: entry.name.trim().to_string(),
+        dosage: [entry.strength.trim(), entry.form.trim()]
+            .into_iter()
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join(" "),
+        description,
+        rxnorm_cui: entry.rxnorm_cui.clone(),
+        ndc: entry.ndc.clone(),
+        atc_code: entry.atc_code.clone(),
+        ..Default::default()
+    };
+    if medication.name.is_empty() {
+        return Err(anyhow::anyhow!("Catalog entry has no name"));
+    }
+    check_codes(&mut medication)?;
+    Ok(medication)
+}
+
+/// Trim the medication's codes, drop blank ones and check the rest are
+/// well formed
+pub fn check_codes(medication: &mut Medication) -> Result<()> {
+    normalize_codes(
+        &mut medication.rxnorm_cui,
+        &mut medication.ndc,
+        &mut medication.atc_code,
+    )
+}
+
+// ===== Helper Functions =====
+
+fn normalize_codes(
+    rxnorm_cui: &mut Option<String>,
+    ndc: &mut Option<String>,
+    atc_code: &mut Option<String>,
+) -> Result<()> {
+    for code in [&mut *rxnorm_cui, &mut *ndc, &mut *atc_code] {
+        *code = code
+            .take()
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty());
+    }
+
+    if let Some(cui) = rxnorm_cui.as_deref() {
+        if !cui.chars().all(|c| c.is_ascii_digit()) {
+            return Err(anyhow::anyhow!("Invalid RxNorm CUI '{}'", cui));
+        }
+    }
+
+    if let Some(code) = ndc.as_deref() {
+        let digits = code.chars().filter(|c| c.is_ascii_digit()).count();
+        if !code.chars().all(|c| c.is_ascii_digit() || c == '-') || !(10..=11).contains(&digits) {
+            return Err(anyhow::anyhow!("Invalid NDC '{}'", code));
+        }
+    }
+
+    if let Some(code) = atc_code.as_mut() {
+        *code = code.to_ascii_uppercase();
+        if !is_atc_code(code) {
+            return Err(anyhow::anyhow!("Invalid ATC code '{}'", code));
+        }
+    }
+
+    Ok(())
+}
+
+/// Whether `code` is an ATC code at any of its five levels, e.g. "B",
+/// "B01", "B01A", "B01AA" or "B01AA03"
+fn is_atc_code(code: &str) -> bool {
+    // Letter or digit expected at each position
+    const PATTERN: [bool; 7] = [true, false, false, true, true, false, false];
+
+    let chars: Vec<char> = code.chars().collect();
+    [1, 3, 4, 5, 7].contains(&chars.len())
+        && chars.iter().zip(PATTERN).all(|(c, letter)| {
+            if letter {
+                c.is_ascii_uppercase()
+            } else {
+                c.is_ascii_digit()
+            }
+        })
+}
+
+fn match_kind(
+    entry: &CatalogEntry,
+    name: &str,
+    query: &str,
+    code: &str,
+    chars: &[char],
+) -> Option<MatchKind> {
+    let is_code = |value: &Option<String>| {
+        value
+            .as_deref()
+            .is_some_and(|value| value.replace('-', "").eq_ignore_ascii_case(code))
+    };
+    if is_code(&entry.rxnorm_cui) || is_code(&entry.ndc) || is_code(&entry.atc_code) {
+        return Some(MatchKind::Code);
+    }
+
+    if name == query {
+        return Some(MatchKind::Exact);
+    }
+    if name.starts_with(query) {
+        return Some(MatchKind::Prefix);
+    }
+    if name.contains(&format!(" {}", query)) {
+        return Some(MatchKind::WordPrefix);
+    }
+    if query.contains(' ')
+        && query
+            .split(' ')
+            .all(|word| name.split(' ').any(|name_word| name_word.starts_with(word)))
+    {
+        return Some(MatchKind::AllWords);
+    }
+
+    let allowed = match chars.len() {
+        n if n < MIN_FUZZY_LENGTH => return None,
+        n if n < 8 => 1,
+        _ => 2,
+    };
+    let name: Vec<char> = name.chars().collect();
+    let word_starts = (0..name.len()).filter(|&i| i == 0 || name[i - 1] == ' ');
+    // Compare against the text from each word on, cut a few characters
+    // either side of the query's length so dropped and doubled letters
+    // count as one typo
+    let (distance, later_word) = word_starts
+        .flat_map(|start| {
+            let rest = &name[start..];
+            (chars.len().saturating_sub(allowed)..=chars.len() + allowed)
+                .filter(move |&len| len <= rest.len())
+                .map(move |len| (edit_distance(chars, &rest[..len]), start > 0))
+        })
+        .min()?;
+
+    (distance <= allowed).then_some(MatchKind::Fuzzy(distance, later_word))
+}
+
+/// Optimal string alignment distance: insertions, deletions,
+/// substitutions and swaps of adjacent characters each count as one
+fn edit_distance(a: &[char], b: &[char]) -> usize {
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut before_previous = vec![0; b.len() + 1];
+    let mut current = vec![0; b.len() + 1];
+
+    for i in 1..=a.len() {
+        current[0] = i;
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            current[j] = (previous[j] + 1)
+                .min(current[j - 1] + 1)
+                .min(previous[j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                current[j] = current[j].min(before_previous[j - 2] + 1);
+            }
+        }
+        std::mem::swap(&mut before_previous, &mut previous);
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CATALOG_CSV: &str = "\
+name,strength,form,rxnorm_cui,ndc,atc_code
+Warfarin Sodium,5 mg,tablet,855332,0056-0172-70,b01aa03
+Warfarin,,,,,
+Coumadin (warfarin),5 mg,tablet,,,
+Sodium Chloride,0.9 %,solution,,,
+Aspirin,81 mg,tablet,,,B01AC06
+Metformin,500 mg,tablet,,,
+";
+
+    fn catalog() -> Catalog {
+        Catalog::parse(CATALOG_CSV).unwrap()
+    }
+
+    fn names(catalog: &Catalog, query: &str) -> Vec<String> {
+        catalog
+            .search(query, 10)
+            .into_iter()
+            .map(|entry| entry.name)
+            .collect()
+    }
+
+    fn kind(catalog: &Catalog, index: usize, query: &str) -> Option<MatchKind> {
+        let (entry, name) = &catalog.entries[index];
+        let query = normalize(query);
+        let chars: Vec<char> = query.chars().collect();
+        match_kind(entry, name, &query, &query.replace(' ', ""), &chars)
+    }
+
+    #[test]
+    fn parse_normalises_codes() {
+        let catalog = catalog();
+        let (entry, name) = &catalog.entries[0];
+        assert_eq!(name, "warfarin sodium");
+        assert_eq!(entry.atc_code.as_deref(), Some("B01AA03"));
+        assert_eq!(entry.ndc.as_deref(), Some("0056-0172-70"));
+        // Blank codes are dropped
+        assert_eq!(catalog.entries[1].0.rxnorm_cui, None);
+
+        assert!(Catalog::parse("strength\n5 mg\n").is_err());
+        assert!(Catalog::parse("name,ndc\nWarfarin,12-34\n").is_err());
+    }
+
+    #[test]
+    fn match_kinds_rank_in_order() {
+        let catalog = catalog();
+        assert_eq!(kind(&catalog, 0, "855332"), Some(MatchKind::Code));
+        assert_eq!(kind(&catalog, 1, "Warfarin"), Some(MatchKind::Exact));
+        assert_eq!(kind(&catalog, 0, "warf"), Some(MatchKind::Prefix));
+        assert_eq!(kind(&catalog, 2, "warf"), Some(MatchKind::WordPrefix));
+        assert_eq!(kind(&catalog, 0, "sod warf"), Some(MatchKind::AllWords));
+        assert_eq!(
+            kind(&catalog, 1, "warfrin"),
+            Some(MatchKind::Fuzzy(1, false))
+        );
+        assert_eq!(
+            kind(&catalog, 2, "warfrin"),
+            Some(MatchKind::Fuzzy(1, true))
+        );
+
+        assert!(MatchKind::Code < MatchKind::Exact);
+        assert!(MatchKind::Exact < MatchKind::Prefix);
+        assert!(MatchKind::Prefix < MatchKind::WordPrefix);
+        assert!(MatchKind::WordPrefix < MatchKind::AllWords);
+        assert!(MatchKind::AllWords < MatchKind::Fuzzy(0, false));
+        assert!(MatchKind::Fuzzy(1, false) < MatchKind::Fuzzy(1, true));
+        assert!(MatchKind::Fuzzy(1, true) < MatchKind::Fuzzy(2, false));
+    }
+
+    #[test]
+    fn search_ranks_best_matches_first() {
+        let catalog = catalog();
+        assert_eq!(
+            names(&catalog, "warfarin"),
+            ["Warfarin", "Warfarin Sodium", "Coumadin (warfarin)"]
+        );
+        assert_eq!(
+            names(&catalog, "sodium"),
+            ["Sodium Chloride", "Warfarin Sodium"]
+        );
+        assert_eq!(
+            names(&catalog, "warfrin"),
+            ["Warfarin", "Warfarin Sodium", "Coumadin (warfarin)"]
+        );
+        assert_eq!(catalog.search("warfarin", 1).len(), 1);
+        assert!(names(&catalog, " - ").is_empty());
+    }
+
+    #[test]
+    fn short_queries_are_not_fuzzy() {
+        let catalog = catalog();
+        // Three letters must match exactly; four allow one typo
+        assert!(names(&catalog, "wrf").is_empty());
+        assert_eq!(names(&catalog, "asp"), ["Aspirin"]);
+        assert_eq!(names(&catalog, "aspn"), ["Aspirin"]);
+        assert!(names(&catalog, "zzzz").is_empty());
+    }
+
+    #[test]
+    fn codes_match_whatever_their_form() {
+        let catalog = catalog();
+        for query in ["0056-0172-70", "0056017270", "b01aa03", "855332"] {
+            assert_eq!(names(&catalog, query), ["Warfarin Sodium"], "{}", query);
+        }
+        // Codes only match whole
+        assert!(names(&catalog, "85533").is_empty());
+    }
+}
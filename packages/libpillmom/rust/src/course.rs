@@ -0,0 +1,516 @@
+use crate::models::{CourseProgress, DoseStatus, Medication};
+use crate::outbox::{self, Mutation};
+use crate::repository;
+use crate::schedule::{self, parse_date};
+use anyhow::Result;
+use chrono::{DateTime, Duration, FixedOffset, NaiveDate};
+
+// Fixed courses of treatment, e.g. a ten-day antibiotic. A course is
+// complete once its end date has passed or its total doses have been
+// taken; its reminders are then turned off and its phased schedule, if it
+// has one, deleted. Dates are local, like reminder times, so "today" and
+// the UTC offset come from the caller.
+
+/// Furthest ahead `courses_ending_soon` looks
+const MAX_LOOKAHEAD_DAYS: i64 = 366;
+
+/// Check the medication's course makes sense
+pub fn check_course(medication: &Medication) -> Result<()> {
+    let start = course_date(&medication.course_start_date, "start")?;
+    let end = course_date(&medication.course_end_date, "end")?;
+
+    if let (Some(start), Some(end)) = (start, end) {
+        if end < start {
+            return Err(anyhow::anyhow!(
+                "Course ends ({}) before it starts ({})",
+                schedule::format_date(end),
+                schedule::format_date(start)
+            ));
+        }
+    }
+
+    if let Some(total) = medication.course_total_doses {
+        if total < 1 {
+            return Err(anyhow::anyhow!(
+                "Course total doses must be at least 1, got {}",
+                total
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Turn off the reminders and delete the phased schedules of the active
+/// profile's medications whose course is complete on `today`, counting doses from the start of the course at
+/// `utc_offset_minutes`. Returns the ids of the medications ended.
+pub async fn end_finished_courses(today: &str, utc_offset_minutes: i64) -> Result<Vec<String>> {
+    let today = parse_today(today)?;
+    let offset = schedule::utc_offset(utc_offset_minutes)?;
+    let schedules = repository::get_schedules().await?;
+
+    let mut ended = Vec::new();
+    for med in repository::get_all_medications().await? {
+        if med.deleted_at.is_some() || !has_course(&med) {
+            continue;
+        }
+        let Some(id) = med.id.clone() else {
+            continue;
+        };
+        let phased = schedules
+            .iter()
+            .any(|schedule| schedule.medication_id == id);
+        if !phased && !has_active_reminders(&med) {
+            continue;
+        }
+
+        let taken = doses_taken(&med, offset).await?;
+        if is_complete(&med, taken, today) && end_course(id.clone()).await? {
+            ended.push(id);
+        }
+    }
+
+    Ok(ended)
+}
+
+/// End the medication's course if the doses taken have reached its total,
+/// with its start date local to `utc_offset_minutes`. Called after a dose
+/// is recorded; returns whether the course ended.
+pub async fn end_if_doses_taken(medication_id: &str, utc_offset_minutes: i64) -> Result<bool> {
+    let offset = schedule::utc_offset(utc_offset_minutes)?;
+    let Some(mut med) = repository::get_medication(medication_id).await? else {
+        return Ok(false);
+    };
+    let Some(total) = med.course_total_doses else {
+        return Ok(false);
+    };
+    med.reminders = repository::get_reminders_for_medication(medication_id).await?;
+    if !has_active_reminders(&med)
+        && repository::get_schedule_for_medication(medication_id)
+            .await?
+            .is_none()
+    {
+        return Ok(false);
+    }
+
+    if doses_taken(&med, offset).await? < total {
+        return Ok(false);
+    }
+    end_course(medication_id.to_string()).await
+}
+
+/// Courses of the active profile that end within `within_days` days of
+/// `today`, soonest first. Courses already complete are left out. Doses
+/// are counted from the start of each course at `utc_offset_minutes`.
+pub async fn courses_ending_soon(
+    today: &str,
+    within_days: i64,
+    utc_offset_minutes: i64,
+) -> Result<Vec<CourseProgress>> {
+    let today = parse_today(today)?;
+    let offset = schedule::utc_offset(utc_offset_minutes)?;
+    if !(0..=MAX_LOOKAHEAD_DAYS).contains(&within_days) {
+        return Err(anyhow::anyhow!(
+            "Courses can be looked up between 0 and {} days ahead",
+            MAX_LOOKAHEAD_DAYS
+        ));
+    }
+    let last_day = today + Duration::days(within_days);
+
+    let medications = repository::get_all_medications().await?;
+    let schedules = repository::get_schedules().await?;
+
+    let mut courses = Vec::new();
+    for med in medications.iter().filter(|med| med.deleted_at.is_none()) {
+        let Some(medication_id) = med.id.as_deref() else {
+            continue;
+        };
+        if !has_course(med) {
+            continue;
+        }
+
+        let taken = doses_taken(med, offset).await?;
+        if is_complete(med, taken, today) {
+            continue;
+        }
+
+        let set_end = med.course_end_date.as_deref().and_then(parse_date);
+        let remaining = med.course_total_doses.map(|total| total - taken);
+
+        // The day the remaining doses fall due, counting today's in full
+        let phased = schedules
+            .iter()
+            .find(|schedule| schedule.medication_id == medication_id);
+        let projected_end = remaining.and_then(|remaining| {
+            let mut due = 0;
+            today
+                .iter_days()
+                .take_while(|date| *date <= last_day)
+                .find(|date| {
+                    due += schedule::doses_on(med, phased, *date).len() as i64;
+                    due >= remaining
+                })
+        });
+
+        let (end, end_projected) = match (set_end, projected_end) {
+            (Some(set), Some(projected)) if projected < set => (projected, true),
+            (Some(set), _) => (set, false),
+            (None, Some(projected)) => (projected, true),
+            (None, None) => continue,
+        };
+        if end > last_day {
+            continue;
+        }
+
+        courses.push(CourseProgress {
+            medication_id: medication_id.to_string(),
+            medication_name: med.name.clone(),
+            end_date: schedule::format_date(end),
+            end_projected,
+            days_remaining: (end - today).num_days(),
+            doses_taken: taken,
+            doses_remaining: remaining,
+        });
+    }
+
+    courses.sort_by(|a, b| {
+        a.end_date.cmp(&b.end_date).then_with(|| {
+            a.medication_name
+                .to_lowercase()
+                .cmp(&b.medication_name.to_lowercase())
+        })
+    });
+
+    Ok(courses)
+}
+
+/// Whether the medication's course is over on `today`, as
+/// `end_finished_courses` would find it. Medications without a course never
+/// are.
+pub(crate) async fn is_over(
+    med: &Medication,
+    today: NaiveDate,
+    offset: FixedOffset,
+) -> Result<bool> {
+    if !has_course(med) {
+        return Ok(false);
+    }
+    Ok(is_complete(med, doses_taken(med, offset).await?, today))
+}
+
+// ===== Helper Functions =====
+
+fn has_course(med: &Medication) -> bool {
+    med.course_end_date.is_some() || med.course_total_doses.is_some()
+}
+
+fn has_active_reminders(med: &Medication) -> bool {
+    med.reminders
+        .iter()
+        .any(|reminder| reminder.is_active && reminder.deleted_at.is_none())
+}
+
+/// Whether the course is over on `today`: past its end date, or with all
+/// its doses taken
+fn is_complete(med: &Medication, taken: i64, today: NaiveDate) -> bool {
+    let past_end = med
+        .course_end_date
+        .as_deref()
+        .and_then(parse_date)
+        .is_some_and(|end| today > end);
+    let all_taken = med.course_total_doses.is_some_and(|total| taken >= total);
+    past_end || all_taken
+}
+
+/// Doses of the medication taken since its course started (from local
+/// midnight at `offset`), or ever if it has no start date
+async fn doses_taken(med: &Medication, offset: FixedOffset) -> Result<i64> {
+    let Some(id) = med.id.as_deref() else {
+        return Ok(0);
+    };
+    let since = med
+        .course_start_date
+        .as_deref()
+        .and_then(parse_date)
+        .map(|start| schedule::local_midnight(start, offset))
+        .unwrap_or(DateTime::UNIX_EPOCH);
+
+    let mut doses = repository::get_medication_dose_logs(id, since).await?;
+    // A dose recorded while offline counts before it reaches the database
+    doses.extend(
+        outbox::queued_doses(id)
+            .await?
+            .into_iter()
+            .filter(|dose| dose.logged_at >= since),
+    );
+
+    Ok(doses
+        .iter()
+        .filter(|dose| dose.status == DoseStatus::Taken)
+        .count() as i64)
+}
+
+async fn end_course(medication_id: String) -> Result<bool> {
+    Ok(outbox::submit(Mutation::EndCourse { medication_id })
+        .await?
+        .changed())
+}
+
+fn course_date(date: &Option<String>, which: &str) -> Result<Option<NaiveDate>> {
+    match date.as_deref() {
+        None => Ok(None),
+        Some(text) => parse_date(text)
+            .map(Some)
+            .ok_or_else(|| anyhow::anyhow!("Course {} date '{}' is not YYYY-MM-DD", which, text)),
+    }
+}
+
+fn parse_today(today: &str) -> Result<NaiveDate> {
+    parse_date(today)
+        .filter(|date| {
+            date.checked_add_signed(Duration::days(MAX_LOOKAHEAD_DAYS))
+                .is_some()
+        })
+        .ok_or_else(|| anyhow::anyhow!("Date '{}' is not YYYY-MM-DD", today))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::test_session;
+    use crate::models::{DoseLog, MergePolicy, PhasedSchedule, Reminder, SchedulePhase};
+    use chrono::Utc;
+
+    fn phased(medication_id: &str) -> PhasedSchedule {
+        PhasedSchedule {
+            medication_id: medication_id.to_string(),
+            start_date: "2024-03-04".to_string(),
+            phases: vec![SchedulePhase {
+                duration_days: 10,
+                dose_quantity: 20.0,
+                dose_unit: "mg".to_string(),
+                times: vec!["08:00".to_string()],
+                days: "Daily".to_string(),
+                every_n_days: 1,
+            }],
+            ..Default::default()
+        }
+    }
+
+    async fn create_course(total_doses: i64, reminder: bool) -> String {
+        let id = repository::create_medication(&Medication {
+            name: "Prednisolone".to_string(),
+            course_total_doses: Some(total_doses),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        if reminder {
+            repository::create_reminder(&Reminder {
+                medication_id: id.clone(),
+                time: "20:00".to_string(),
+                days: "Daily".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        }
+        repository::create_schedule(&phased(&id)).await.unwrap();
+        id
+    }
+
+    async fn take(medication_id: &str) {
+        take_at(medication_id, Utc::now()).await;
+    }
+
+    async fn take_at(medication_id: &str, logged_at: DateTime<Utc>) {
+        repository::record_dose(&DoseLog {
+            id: Some(repository::new_record_id()),
+            medication_id: medication_id.to_string(),
+            reminder_id: None,
+            status: DoseStatus::Taken,
+            scheduled_for: None,
+            logged_at,
+        })
+        .await
+        .unwrap();
+    }
+
+    fn date(value: &str) -> NaiveDate {
+        parse_date(value).unwrap()
+    }
+
+    fn offset(minutes: i64) -> FixedOffset {
+        schedule::utc_offset(minutes).unwrap()
+    }
+
+    fn course(start: Option<&str>, end: Option<&str>, total_doses: Option<i64>) -> Medication {
+        Medication {
+            name: "Amoxicillin".to_string(),
+            course_start_date: start.map(str::to_string),
+            course_end_date: end.map(str::to_string),
+            course_total_doses: total_doses,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn check_course_limits() {
+        assert!(check_course(&course(None, None, None)).is_ok());
+        assert!(check_course(&course(Some("2024-03-01"), Some("2024-03-01"), None)).is_ok());
+        // An end date and a total together: whichever comes first ends it
+        assert!(check_course(&course(Some("2024-03-01"), Some("2024-03-10"), Some(20))).is_ok());
+
+        let error = |med: Medication| check_course(&med).unwrap_err().to_string();
+        assert_eq!(
+            error(course(Some("2024-03-10"), Some("2024-03-01"), None)),
+            "Course ends (2024-03-01) before it starts (2024-03-10)"
+        );
+        assert_eq!(
+            error(course(None, None, Some(0))),
+            "Course total doses must be at least 1, got 0"
+        );
+        assert_eq!(
+            error(course(None, None, Some(-3))),
+            "Course total doses must be at least 1, got -3"
+        );
+        assert_eq!(
+            error(course(Some("1 March"), None, None)),
+            "Course start date '1 March' is not YYYY-MM-DD"
+        );
+    }
+
+    #[tokio::test]
+    async fn is_over_after_end_date_or_total() {
+        let _session = test_session().await;
+
+        let no_course = course(None, None, None);
+        assert!(!is_over(&no_course, date("2030-01-01"), offset(0))
+            .await
+            .unwrap());
+
+        // The end date is the last day of the course
+        let dated = course(Some("2024-03-01"), Some("2024-03-10"), None);
+        assert!(!is_over(&dated, date("2024-03-10"), offset(0))
+            .await
+            .unwrap());
+        assert!(is_over(&dated, date("2024-03-11"), offset(0))
+            .await
+            .unwrap());
+
+        let mut counted = course(None, None, Some(2));
+        let id = repository::create_medication(&counted).await.unwrap();
+        counted.id = Some(id.clone());
+        take(&id).await;
+        assert!(!is_over(&counted, date("2024-03-04"), offset(0))
+            .await
+            .unwrap());
+        take(&id).await;
+        assert!(is_over(&counted, date("2024-03-04"), offset(0))
+            .await
+            .unwrap());
+    }
+
+    #[tokio::test]
+    async fn doses_count_from_local_start_of_course() {
+        let _session = test_session().await;
+
+        let mut med = course(Some("2024-03-05"), None, Some(10));
+        let id = repository::create_medication(&med).await.unwrap();
+        med.id = Some(id.clone());
+        // 22:00 on the 4th in New York, but already the 5th in UTC
+        take_at(&id, "2024-03-05T03:00:00Z".parse().unwrap()).await;
+        // 07:00 on the 5th in Tokyo, but still the 4th in UTC
+        take_at(&id, "2024-03-04T22:00:00Z".parse().unwrap()).await;
+
+        assert_eq!(doses_taken(&med, offset(0)).await.unwrap(), 1);
+        assert_eq!(doses_taken(&med, offset(-300)).await.unwrap(), 0);
+        assert_eq!(doses_taken(&med, offset(540)).await.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn courses_ending_soon_projects_the_end() {
+        let _session = test_session().await;
+
+        // Two doses a day, five in all, one taken: the remaining four fall
+        // due today and tomorrow
+        let id = repository::create_medication(&course(None, None, Some(5)))
+            .await
+            .unwrap();
+        for time in ["08:00", "20:00"] {
+            repository::create_reminder(&Reminder {
+                medication_id: id.clone(),
+                time: time.to_string(),
+                days: "Daily".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        }
+        take(&id).await;
+
+        let courses = courses_ending_soon("2024-03-04", 7, 0).await.unwrap();
+        assert_eq!(courses.len(), 1);
+        assert_eq!(courses[0].end_date, "2024-03-05");
+        assert!(courses[0].end_projected);
+        assert_eq!(courses[0].days_remaining, 1);
+        assert_eq!(courses[0].doses_taken, 1);
+        assert_eq!(courses[0].doses_remaining, Some(4));
+
+        // Out of range of the lookahead
+        assert!(courses_ending_soon("2024-03-04", 0, 0)
+            .await
+            .unwrap()
+            .is_empty());
+
+        // A set end date before the projection wins
+        let mut med = repository::get_medication(&id).await.unwrap().unwrap();
+        med.course_end_date = Some("2024-03-04".to_string());
+        assert!(
+            repository::update_medication(&med, MergePolicy::Reject, None)
+                .await
+                .unwrap()
+        );
+        let courses = courses_ending_soon("2024-03-04", 7, 0).await.unwrap();
+        assert_eq!(courses[0].end_date, "2024-03-04");
+        assert!(!courses[0].end_projected);
+        assert_eq!(courses[0].days_remaining, 0);
+
+        assert!(courses_ending_soon("2024-03-04", 367, 0).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn ending_a_course_ends_its_schedule() {
+        let _session = test_session().await;
+        let id = create_course(2, true).await;
+
+        take(&id).await;
+        assert!(!end_if_doses_taken(&id, 0).await.unwrap());
+        assert!(repository::get_schedule_for_medication(&id)
+            .await
+            .unwrap()
+            .is_some());
+
+        take(&id).await;
+        assert!(end_if_doses_taken(&id, 0).await.unwrap());
+        assert!(repository::get_schedule_for_medication(&id)
+            .await
+            .unwrap()
+            .is_none());
+        let reminders = repository::get_reminders_for_medication(&id).await.unwrap();
+        assert!(reminders.iter().all(|reminder| !reminder.is_active));
+
+        // Nothing left to end
+        assert!(!end_if_doses_taken(&id, 0).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn a_schedule_alone_is_ended() {
+        let _session = test_session().await;
+        let id = create_course(1, false).await;
+        take(&id).await;
+
+        assert!(end_if_doses_taken(&id, 0).await.unwrap());
+        assert!(repository::get_schedules().await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,474 @@
+use crate::database;
+use crate::models::{CsvColumnMapping, CsvImportReport, CsvRowError, Medication, Reminder};
+use crate::repository;
+use crate::schedule::{parse_days, parse_time};
+use anyhow::Result;
+use std::collections::HashMap;
+
+// Spreadsheet-friendly CSV (RFC 4180) of medications and their reminders:
+// one row per reminder, repeating the medication columns, and one row with
+// empty reminder columns for a medication without reminders.
+
+/// Leading characters that make a spreadsheet treat a cell as a formula
+const FORMULA_PREFIXES: [char; 6] = ['=', '+', '-', '@', '\t', '\r'];
+
+const EXPORT_HEADERS: [&str; 8] = [
+    "medication_id",
+    "name",
+    "dosage",
+    "description",
+    "reminder_id",
+    "time",
+    "days",
+    "is_active",
+];
+
+/// Cells that a spreadsheet would read as a formula are written with a
+/// leading `'`, which the import removes again
+pub async fn export_csv() -> Result<String> {
+    let medications = repository::get_all_medications().await?;
+
+    let mut output = String::new();
+    write_row(&mut output, &EXPORT_HEADERS);
+
+    for med in &medications {
+        let id = med.id.as_deref().unwrap_or_default();
+
+        if med.reminders.is_empty() {
+            write_row(
+                &mut output,
+                &[id, &med.name, &med.dosage, &med.description, "", "", "", ""],
+            );
+        }
+
+        for reminder in &med.reminders {
+            write_row(
+                &mut output,
+                &[
+                    id,
+                    &med.name,
+                    &med.dosage,
+                    &med.description,
+                    reminder.id.as_deref().unwrap_or_default(),
+                    &reminder.time,
+                    &reminder.days,
+                    if reminder.is_active { "true" } else { "false" },
+                ],
+            );
+        }
+    }
+
+    Ok(output)
+}
+
+/// Validate every row, then create all medications and reminders in one
+/// transaction unless there were errors or this is a dry run. Imported
+/// rows always get new ids; the id column only groups rows.
+pub async fn import_csv(
+    data: &str,
+    mapping: &CsvColumnMapping,
+    dry_run: bool,
+) -> Result<CsvImportReport> {
+    // A dry run only validates, so it is allowed on a read-only database
+    if !dry_run {
+        database::ensure_writable()?;
+    }
+
+    let mut report = CsvImportReport {
+        dry_run,
+        ..Default::default()
+    };
+
+    let records = match parse(data) {
+        Ok(records) => records,
+        Err(error) => {
+            report.errors.push(error);
+            return Ok(report);
+        }
+    };
+
+    let Some(((_, header), rows)) = records.split_first() else {
+        report.errors.push(file_error("File is empty"));
+        return Ok(report);
+    };
+
+    let columns = match Columns::resolve(header, mapping) {
+        Ok(columns) => columns,
+        Err(error) => {
+            report.errors.push(error);
+            return Ok(report);
+        }
+    };
+
+    let mut medications: Vec<Medication> = Vec::new();
+    let mut groups: HashMap<String, usize> = HashMap::new();
+
+    for (line, fields) in rows {
+        report.rows_read += 1;
+        let errors_before = report.errors.len();
+        let mut row_error = |column: Option<&str>, message: String| {
+            report.errors.push(CsvRowError {
+                line: *line,
+                column: column.map(str::to_string),
+                message,
+            });
+        };
+
+        let name = columns.name.value(fields);
+        let dosage = columns.dosage.value(fields);
+        let description = columns.description.value(fields);
+        let group_id = columns.medication_id.value(fields);
+        let time = columns.time.value(fields);
+        let days = columns.days.value(fields);
+        let is_active = columns.is_active.value(fields);
+
+        if name.is_empty() {
+            row_error(columns.name.header(), "Name is required".to_string());
+        }
+
+        let parsed_time = if time.is_empty() {
+            if !days.is_empty() {
+                row_error(columns.days.header(), "Days given without a time".to_string());
+            }
+            None
+        } else {
+            let parsed = parse_time(time);
+            if parsed.is_none() {
+                row_error(
+                    columns.time.header(),
+                    format!("Invalid time '{}'; expected HH:MM", time),
+                );
+            }
+            parsed
+        };
+
+        if !days.is_empty() && parse_days(days).is_none() {
+            row_error(
+                columns.days.header(),
+                format!("Unrecognised days '{}'", days),
+            );
+        }
+
+        let active = match parse_bool(is_active) {
+            Some(active) => active,
+            None => {
+                row_error(
+                    columns.is_active.header(),
+                    format!("Invalid value '{}'; expected true or false", is_active),
+                );
+                true
+            }
+        };
+
+        // Rows naming the same id must describe the same medication
+        let key = if group_id.is_empty() {
+            format!("{}\u{1f}{}\u{1f}{}", name, dosage, description)
+        } else {
+            format!("id\u{1f}{}", group_id)
+        };
+
+        if let Some(&index) = groups.get(&key) {
+            let med = &medications[index];
+            if med.name != name || med.dosage != dosage || med.description != description {
+                row_error(
+                    columns.medication_id.header(),
+                    format!(
+                        "Medication '{}' has a different name, dosage or description on an earlier row",
+                        group_id
+                    ),
+                );
+            }
+        }
+
+        if report.errors.len() > errors_before {
+            continue;
+        }
+
+        let index = *groups.entry(key).or_insert_with(|| {
+            medications.push(Medication {
+                name: name.to_string(),
+                dosage: dosage.to_string(),
+                description: description.to_string(),
+                ..Default::default()
+            });
+            medications.len() - 1
+        });
+
+        if let Some(time) = parsed_time {
+            medications[index].reminders.push(Reminder {
+                time: time.format("%H:%M").to_string(),
+                days: if days.is_empty() { "Daily" } else { days }.to_string(),
+                is_active: active,
+                ..Default::default()
+            });
+        }
+    }
+
+    report.medications_created = medications.len() as i64;
+    report.reminders_created = medications
+        .iter()
+        .map(|med| med.reminders.len() as i64)
+        .sum();
+
+    if report.errors.is_empty() && !dry_run {
+        repository::create_medications_with_reminders(&medications).await?;
+        report.committed = true;
+    }
+
+    Ok(report)
+}
+
+// ===== Columns =====
+
+/// A mapped column: its header and position in the file, if present
+struct Column {
+    header: Option<String>,
+    index: Option<usize>,
+}
+
+impl Column {
+    fn value<'a>(&self, fields: &'a [String]) -> &'a str {
+        let value = self
+            .index
+            .and_then(|index| fields.get(index))
+            .map(|value| value.trim())
+            .unwrap_or_default();
+
+        // Undo the formula guard added by `export_csv`
+        match value.strip_prefix('\'') {
+            Some(rest) if rest.starts_with(FORMULA_PREFIXES) => rest.trim(),
+            _ => value,
+        }
+    }
+
+    fn header(&self) -> Option<&str> {
+        self.header.as_deref()
+    }
+}
+
+struct Columns {
+    name: Column,
+    dosage: Column,
+    description: Column,
+    medication_id: Column,
+    time: Column,
+    days: Column,
+    is_active: Column,
+}
+
+impl Columns {
+    fn resolve(header: &[String], mapping: &CsvColumnMapping) -> Result<Self, CsvRowError> {
+        let find = |wanted: Option<&String>| Column {
+            header: wanted.cloned(),
+            index: wanted.and_then(|wanted| {
+                header
+                    .iter()
+                    .position(|h| h.trim().eq_ignore_ascii_case(wanted.trim()))
+            }),
+        };
+
+        let name = find(Some(&mapping.name));
+        if name.index.is_none() {
+            return Err(file_error(&format!(
+                "Header has no '{}' column for medication names",
+                mapping.name
+            )));
+        }
+
+        Ok(Self {
+            name,
+            dosage: find(mapping.dosage.as_ref()),
+            description: find(mapping.description.as_ref()),
+            medication_id: find(mapping.medication_id.as_ref()),
+            time: find(mapping.time.as_ref()),
+            days: find(mapping.days.as_ref()),
+            is_active: find(mapping.is_active.as_ref()),
+        })
+    }
+}
+
+// ===== Helper Functions =====
+
+/// Split `data` into records, each with the line it starts on. Handles
+/// quoted fields with embedded commas, quotes and newlines; blank lines
+/// are skipped.
+pub(crate) fn parse(data: &str) -> Result<Vec<(i64, Vec<String>)>, CsvRowError> {
+    let data = data.strip_prefix('\u{feff}').unwrap_or(data);
+
+    let mut records = Vec::new();
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut line = 1;
+    let mut start = 1;
+    let mut chars = data.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => in_quotes = false,
+                '\n' => {
+                    line += 1;
+                    field.push(c);
+                }
+                _ => field.push(c),
+            }
+            continue;
+        }
+
+        match c {
+            '"' if field.is_empty() => in_quotes = true,
+            ',' => fields.push(std::mem::take(&mut field)),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\r' | '\n' => {
+                fields.push(std::mem::take(&mut field));
+                if fields.iter().any(|f| !f.is_empty()) {
+                    records.push((start, std::mem::take(&mut fields)));
+                } else {
+                    fields.clear();
+                }
+                line += 1;
+                start = line;
+            }
+            _ => field.push(c),
+        }
+    }
+
+    if in_quotes {
+        return Err(CsvRowError {
+            line: start,
+            column: None,
+            message: "Quoted field is never closed".to_string(),
+        });
+    }
+
+    if !field.is_empty() || !fields.is_empty() {
+        fields.push(field);
+        records.push((start, fields));
+    }
+
+    Ok(records)
+}
+
+fn write_row(output: &mut String, fields: &[&str]) {
+    for (index, field) in fields.iter().enumerate() {
+        if index > 0 {
+            output.push(',');
+        }
+
+        // Keeps a name such as "=HYPERLINK(...)" from running when the
+        // file is opened in a spreadsheet
+        let guarded;
+        let field = if field.starts_with(FORMULA_PREFIXES) {
+            guarded = format!("'{}", field);
+            guarded.as_str()
+        } else {
+            field
+        };
+
+        if field.contains([',', '"', '\r', '\n']) || field.trim() != field {
+            output.push('"');
+            output.push_str(&field.replace('"', "\"\""));
+            output.push('"');
+        } else {
+            output.push_str(field);
+        }
+    }
+    output.push_str("\r\n");
+}
+
+/// Spreadsheet spellings of a boolean; an empty cell means active
+fn parse_bool(value: &str) -> Option<bool> {
+    match value.to_ascii_lowercase().as_str() {
+        "" | "true" | "yes" | "y" | "1" => Some(true),
+        "false" | "no" | "n" | "0" => Some(false),
+        _ => None,
+    }
+}
+
+fn file_error(message: &str) -> CsvRowError {
+    CsvRowError {
+        line: 1,
+        column: None,
+        message: message.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(fields: &[&str]) -> String {
+        let mut output = String::new();
+        write_row(&mut output, fields);
+        output
+    }
+
+    fn fields(record: &(i64, Vec<String>)) -> Vec<&str> {
+        record.1.iter().map(String::as_str).collect()
+    }
+
+    #[test]
+    fn write_row_quotes_only_when_needed() {
+        assert_eq!(row(&["Aspirin", "81mg", ""]), "Aspirin,81mg,\r\n");
+        assert_eq!(
+            row(&["a,b", "say \"hi\"", "two\nlines", " padded"]),
+            "\"a,b\",\"say \"\"hi\"\"\",\"two\nlines\",\" padded\"\r\n"
+        );
+    }
+
+    #[test]
+    fn write_row_guards_formulas() {
+        assert_eq!(
+            row(&["=HYPERLINK(\"x\")", "+1", "-2", "@SUM(A1)", "1-2"]),
+            "\"'=HYPERLINK(\"\"x\"\")\",'+1,'-2,'@SUM(A1),1-2\r\n"
+        );
+        assert_eq!(row(&["\tcmd"]), "'\tcmd\r\n");
+    }
+
+    #[test]
+    fn parse_handles_quotes_and_line_endings() {
+        let records =
+            parse("name,notes\r\n\"a,b\",\"say \"\"hi\"\"\"\nplain,\"two\nlines\"\n").unwrap();
+
+        assert_eq!(records.len(), 3);
+        assert_eq!(fields(&records[0]), ["name", "notes"]);
+        assert_eq!(fields(&records[1]), ["a,b", "say \"hi\""]);
+        assert_eq!(fields(&records[2]), ["plain", "two\nlines"]);
+    }
+
+    #[test]
+    fn parse_tracks_record_lines() {
+        let records = parse("\u{feff}h\n\n\"multi\nline\"\nlast").unwrap();
+
+        let lines: Vec<i64> = records.iter().map(|(line, _)| *line).collect();
+        // The blank line is skipped; a record spanning two lines starts on its first
+        assert_eq!(lines, [1, 3, 5]);
+        assert_eq!(fields(&records[0]), ["h"]);
+        assert_eq!(fields(&records[2]), ["last"]);
+    }
+
+    #[test]
+    fn parse_rejects_unclosed_quotes() {
+        let error = parse("name\n\"open,\n").unwrap_err();
+        assert_eq!(error.line, 2);
+    }
+
+    #[test]
+    fn exported_cells_read_back_unchanged() {
+        let original = ["=1+1", "-5mg", "a,\"b\"", "plain"];
+        let records = parse(&row(&original)).unwrap();
+        let column = |index| Column {
+            header: None,
+            index: Some(index),
+        };
+
+        for (index, expected) in original.iter().enumerate() {
+            assert_eq!(column(index).value(&records[0].1), *expected);
+        }
+    }
+}
@@ -1,13 +1,16 @@
+use crate::error::PillMomError;
+use crate::models::{JournalMode, OpenOptions, SynchronousMode};
+use crate::outbox;
 use anyhow::Result;
-use libsql::{Builder, Connection, Database};
-use once_cell::sync::OnceCell;
-use std::sync::Arc;
+use libsql::{Builder, Cipher, Connection, Database, EncryptionConfig, OpenFlags, Statement};
+use std::collections::HashMap;
+use std::ops::Deref;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
 use std::time::Duration;
 use tokio::sync::Mutex;
 
-static DATABASE: OnceCell<Arc<Mutex<Connection>>> = OnceCell::new();
-static DB_HANDLE: OnceCell<Database> = OnceCell::new();
-
 #[derive(Debug, Clone, Copy)]
 #[allow(dead_code)]
 enum DbType {
@@ -17,93 +20,158 @@ enum DbType {
     EmbeddedReplica,
 }
 
-static DB_TYPE: OnceCell<DbType> = OnceCell::new();
+/// The open database. Set by the `init_*` functions and cleared by
+/// `close_database`, so a session can be closed and another one opened.
+static SESSION: RwLock<Option<Session>> = RwLock::new(None);
+
+struct Session {
+    db_type: DbType,
+    /// File behind a local session, and the key it was opened with, so it
+    /// can be reopened after a restore
+    path: Option<String>,
+    encryption_key: Option<String>,
+    options: OpenOptions,
+    handle: Arc<Database>,
+    /// The connection every write goes through
+    conn: Arc<Mutex<DbConnection>>,
+    /// Connections that only read, so reads need not wait for writes or for
+    /// each other. Empty unless the file is in WAL mode.
+    readers: Vec<Arc<Mutex<DbConnection>>>,
+    next_reader: AtomicUsize,
+    /// Profile chosen with `set_active_profile`; `None` means the oldest
+    /// live profile
+    active_profile: Option<String>,
+}
+
+/// Reader connections opened for a local file in WAL mode
+const READER_CONNECTIONS: usize = 4;
+
+/// A connection together with the prepared statements cached on it
+pub struct DbConnection {
+    conn: Connection,
+    statements: HashMap<&'static str, Statement>,
+}
+
+impl DbConnection {
+    fn new(conn: Connection) -> Self {
+        Self {
+            conn,
+            statements: HashMap::new(),
+        }
+    }
+
+    /// Prepare `sql` once per connection and reuse it on later calls.
+    /// Only for queries whose rows are read to the end: a statement that is
+    /// left mid-step keeps its transaction open until the next reset.
+    pub async fn prepare_cached(&mut self, sql: &'static str) -> Result<&Statement> {
+        if !self.statements.contains_key(sql) {
+            let stmt = self.conn.prepare(sql).await?;
+            self.statements.insert(sql, stmt);
+        }
+
+        let stmt = &self.statements[sql];
+        // Clear any bindings or cursor left over from the previous use
+        stmt.reset();
+        Ok(stmt)
+    }
+}
+
+impl Deref for DbConnection {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        &self.conn
+    }
+}
 
 // ===== Connection Methods =====
 
 /// Initialize an in-memory database (no persistence)
-pub async fn init_in_memory() -> Result<()> {
-    DB_TYPE.set(DbType::InMemory).map_err(|_| {
-        anyhow::anyhow!("Failed to set database type")
-    })?;
+pub async fn init_in_memory(options: &OpenOptions) -> Result<()> {
+    if options.read_only {
+        return Err(anyhow::anyhow!(
+            "An in-memory database cannot be opened read-only"
+        ));
+    }
 
     // Use libsql for in-memory SQLite
     let db = Builder::new_local(":memory:").build().await?;
     let conn = db.connect()?;
 
+    configure(&conn, options, false).await?;
     create_tables(&conn).await?;
+    run_migrations(&conn).await?;
 
-    DATABASE.set(Arc::new(Mutex::new(conn))).map_err(|_| {
-        anyhow::anyhow!("Failed to set database connection")
-    })?;
+    install(DbType::InMemory, None, None, options, db, conn, Vec::new())
+}
 
-    DB_HANDLE.set(db).map_err(|_| {
-        anyhow::anyhow!("Failed to set database handle")
-    })?;
+/// Initialize a local SQLite database, encrypted with `encryption_key` if given
+pub async fn init_local_db(
+    path: &str,
+    encryption_key: Option<&str>,
+    options: &OpenOptions,
+) -> Result<()> {
+    // Use libsql for local SQLite
+    let mut builder = Builder::new_local(path);
 
-    Ok(())
-}
+    if options.read_only {
+        if !Path::new(path).exists() {
+            return Err(anyhow::anyhow!("Database file {} does not exist", path));
+        }
+        builder = builder.flags(OpenFlags::SQLITE_OPEN_READ_ONLY);
+    }
 
-/// Initialize a local SQLite database
-pub async fn init_local_db(path: &str) -> Result<()> {
-    DB_TYPE.set(DbType::Local).map_err(|_| {
-        anyhow::anyhow!("Failed to set database type")
-    })?;
+    if let Some(config) = encryption_config(encryption_key)? {
+        builder = builder.encryption_config(config);
+    }
 
-    // Use libsql for local SQLite
-    let db = Builder::new_local(path).build().await?;
+    let db = builder.build().await?;
     let conn = db.connect()?;
 
-    create_tables(&conn).await?;
-
-    DATABASE.set(Arc::new(Mutex::new(conn))).map_err(|_| {
-        anyhow::anyhow!("Failed to set database connection")
-    })?;
+    verify_key(&conn, path, encryption_key.is_some()).await?;
+    configure(&conn, options, true).await?;
 
-    DB_HANDLE.set(db).map_err(|_| {
-        anyhow::anyhow!("Failed to set database handle")
-    })?;
+    if options.read_only {
+        require_current_schema(&conn, path).await?;
+    } else {
+        create_tables(&conn).await?;
+        run_migrations(&conn).await?;
+    }
 
-    Ok(())
+    let readers = open_readers(&db, &conn, options).await?;
+    install(DbType::Local, Some(path), encryption_key, options, db, conn, readers)
 }
 
-/// Initialize a remote Turso database connection
-pub async fn init_remote_db(url: &str, auth_token: &str) -> Result<()> {
-    DB_TYPE.set(DbType::Remote).map_err(|_| {
-        anyhow::anyhow!("Failed to set database type")
-    })?;
-
+/// Initialize a remote Turso database connection. Read-only mode suits a
+/// read-only token, which cannot create tables or migrate.
+pub async fn init_remote_db(url: &str, auth_token: &str, options: &OpenOptions) -> Result<()> {
     // Use libsql for remote Turso connection
     let db = Builder::new_remote(url.to_string(), auth_token.to_string())
         .build()
         .await?;
     let conn = db.connect()?;
 
-    create_tables(&conn).await?;
-
-    DATABASE.set(Arc::new(Mutex::new(conn))).map_err(|_| {
-        anyhow::anyhow!("Failed to set database connection")
-    })?;
-
-    DB_HANDLE.set(db).map_err(|_| {
-        anyhow::anyhow!("Failed to set database handle")
-    })?;
+    if options.read_only {
+        require_current_schema(&conn, url).await?;
+    } else {
+        create_tables(&conn).await?;
+        run_migrations(&conn).await?;
+    }
 
-    Ok(())
+    install(DbType::Remote, None, None, options, db, conn, Vec::new())
 }
 
-/// Initialize an embedded replica (local SQLite that syncs with remote)
+/// Initialize an embedded replica (local SQLite that syncs with remote),
+/// encrypting the local copy with `encryption_key` if given
 #[allow(dead_code)]
 pub async fn init_embedded_replica(
     path: &str,
     url: &str,
     auth_token: &str,
     sync_period: Option<Duration>,
+    encryption_key: Option<&str>,
+    options: &OpenOptions,
 ) -> Result<()> {
-    DB_TYPE.set(DbType::EmbeddedReplica).map_err(|_| {
-        anyhow::anyhow!("Failed to set database type")
-    })?;
-
     // Use libsql embedded replica
     let mut builder = Builder::new_remote_replica(path, url.to_string(), auth_token.to_string());
 
@@ -111,30 +179,268 @@ pub async fn init_embedded_replica(
         builder = builder.sync_interval(period);
     }
 
+    if let Some(config) = encryption_config(encryption_key)? {
+        builder = builder.encryption_config(config);
+    }
+
     let db = builder.build().await?;
     let conn = db.connect()?;
 
-    create_tables(&conn).await?;
-
-    DATABASE.set(Arc::new(Mutex::new(conn))).map_err(|_| {
-        anyhow::anyhow!("Failed to set database connection")
-    })?;
+    verify_key(&conn, path, encryption_key.is_some()).await?;
+    configure(&conn, options, true).await?;
 
-    DB_HANDLE.set(db).map_err(|_| {
-        anyhow::anyhow!("Failed to set database handle")
-    })?;
+    if options.read_only {
+        require_current_schema(&conn, path).await?;
+    } else {
+        create_tables(&conn).await?;
+        run_migrations(&conn).await?;
+    }
 
-    Ok(())
+    install(
+        DbType::EmbeddedReplica,
+        Some(path),
+        encryption_key,
+        options,
+        db,
+        conn,
+        Vec::new(),
+    )
 }
 
 // Legacy support for old API
 #[allow(dead_code)]
 pub async fn init_turso_db(url: &str, auth_token: &str) -> Result<()> {
-    init_remote_db(url, auth_token).await
+    init_remote_db(url, auth_token, &OpenOptions::default()).await
 }
 
 // ===== Helper Functions =====
 
+/// Make a freshly opened database the current session. Done last, so a
+/// failed open (such as a wrong key) leaves nothing behind to close.
+fn install(
+    db_type: DbType,
+    path: Option<&str>,
+    encryption_key: Option<&str>,
+    options: &OpenOptions,
+    db: Database,
+    conn: Connection,
+    readers: Vec<Arc<Mutex<DbConnection>>>,
+) -> Result<()> {
+    let mut session = SESSION.write().unwrap_or_else(|e| e.into_inner());
+    if session.is_some() {
+        return Err(anyhow::anyhow!(
+            "A database is already open; close it before opening another"
+        ));
+    }
+
+    *session = Some(Session {
+        db_type,
+        path: path.map(str::to_string),
+        encryption_key: encryption_key.map(str::to_string),
+        options: options.clone(),
+        handle: Arc::new(db),
+        conn: Arc::new(Mutex::new(DbConnection::new(conn))),
+        readers,
+        next_reader: AtomicUsize::new(0),
+        active_profile: None,
+    });
+
+    Ok(())
+}
+
+/// Reader connections for a local file in WAL mode, where reads see the
+/// last commit without blocking the writer. In other journal modes readers
+/// and the writer lock each other out, so reads stay on the one connection.
+async fn open_readers(
+    db: &Database,
+    conn: &Connection,
+    options: &OpenOptions,
+) -> Result<Vec<Arc<Mutex<DbConnection>>>> {
+    let mode = pragma(conn, "PRAGMA journal_mode").await?;
+    if !mode.is_some_and(|mode| mode.eq_ignore_ascii_case("wal")) {
+        return Ok(Vec::new());
+    }
+
+    // The journal mode belongs to the file and is already set
+    let options = OpenOptions {
+        journal_mode: None,
+        ..options.clone()
+    };
+
+    let mut readers = Vec::with_capacity(READER_CONNECTIONS);
+    for _ in 0..READER_CONNECTIONS {
+        let reader = db.connect()?;
+        configure(&reader, &options, true).await?;
+        // Guard against a write slipping onto a reader
+        pragma(&reader, "PRAGMA query_only = ON").await?;
+        readers.push(Arc::new(Mutex::new(DbConnection::new(reader))));
+    }
+
+    Ok(readers)
+}
+
+/// Read from the current session, if there is one
+fn with_session<T>(read: impl FnOnce(&Session) -> T) -> Option<T> {
+    SESSION
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .as_ref()
+        .map(read)
+}
+
+/// Apply `options` to a new connection. `file` says whether the
+/// connection is backed by a file; in-memory databases take only the
+/// settings that mean something for them.
+async fn configure(conn: &Connection, options: &OpenOptions, file: bool) -> Result<()> {
+    if let Some(timeout) = options.busy_timeout_ms {
+        if timeout < 0 {
+            return Err(anyhow::anyhow!("Busy timeout must not be negative"));
+        }
+        conn.busy_timeout(Duration::from_millis(timeout as u64))?;
+    }
+
+    if let Some(size) = options.cache_size_kib {
+        if size <= 0 {
+            return Err(anyhow::anyhow!("Cache size must be positive"));
+        }
+        // A negative cache_size is in KiB rather than pages
+        pragma(conn, &format!("PRAGMA cache_size = -{}", size)).await?;
+    }
+
+    if !file {
+        return Ok(());
+    }
+
+    if let Some(mode) = options.journal_mode {
+        if options.read_only {
+            return Err(anyhow::anyhow!(
+                "The journal mode cannot be changed on a read-only database"
+            ));
+        }
+
+        let wanted = journal_mode_name(mode);
+        let actual = pragma(conn, &format!("PRAGMA journal_mode = {}", wanted)).await?;
+        if !actual.is_some_and(|actual| actual.eq_ignore_ascii_case(wanted)) {
+            return Err(anyhow::anyhow!(
+                "Could not switch the database to {} journal mode",
+                wanted
+            ));
+        }
+    }
+
+    if let Some(level) = options.synchronous {
+        pragma(conn, &format!("PRAGMA synchronous = {}", synchronous_name(level))).await?;
+    }
+
+    Ok(())
+}
+
+/// Run a pragma and return the first column of its first row, if any
+async fn pragma(conn: &Connection, sql: &str) -> Result<Option<String>> {
+    let mut rows = conn.query(sql, ()).await?;
+    let value = match rows.next().await? {
+        Some(row) => row.get::<Option<String>>(0).ok().flatten(),
+        None => None,
+    };
+    while rows.next().await?.is_some() {}
+    Ok(value)
+}
+
+fn journal_mode_name(mode: JournalMode) -> &'static str {
+    match mode {
+        JournalMode::Delete => "delete",
+        JournalMode::Truncate => "truncate",
+        JournalMode::Persist => "persist",
+        JournalMode::Wal => "wal",
+    }
+}
+
+fn synchronous_name(level: SynchronousMode) -> &'static str {
+    match level {
+        SynchronousMode::Off => "OFF",
+        SynchronousMode::Normal => "NORMAL",
+        SynchronousMode::Full => "FULL",
+        SynchronousMode::Extra => "EXTRA",
+    }
+}
+
+/// A read-only connection cannot create tables or migrate, so the file
+/// must already be a fully migrated pillmom database
+async fn require_current_schema(conn: &Connection, path: &str) -> Result<()> {
+    match stored_schema_version(conn).await? {
+        Some(SCHEMA_VERSION) => Ok(()),
+        Some(version) if version < SCHEMA_VERSION => Err(anyhow::anyhow!(
+            "Database at {} is at schema version {}; open it writable once to upgrade it to {}",
+            path,
+            version,
+            SCHEMA_VERSION
+        )),
+        Some(version) => Err(anyhow::anyhow!(
+            "Database schema version {} is newer than supported version {}",
+            version,
+            SCHEMA_VERSION
+        )),
+        None => Err(anyhow::anyhow!(
+            "Database at {} is not a pillmom database",
+            path
+        )),
+    }
+}
+
+/// SQLite's result code for a file that does not decode as a database
+const SQLITE_NOTADB: i32 = 26;
+
+/// Build the libsql encryption settings for `key`. Builds without the
+/// `encryption` feature refuse keys rather than write a plaintext file.
+fn encryption_config(key: Option<&str>) -> Result<Option<EncryptionConfig>> {
+    let Some(key) = key else {
+        return Ok(None);
+    };
+
+    if !cfg!(feature = "encryption") {
+        return Err(anyhow::anyhow!(
+            "This build of pillmom does not support encryption (enable the `encryption` feature)"
+        ));
+    }
+
+    if key.is_empty() {
+        return Err(anyhow::anyhow!("Encryption key must not be empty"));
+    }
+
+    Ok(Some(EncryptionConfig::new(
+        Cipher::Aes256Cbc,
+        key.to_string().into(),
+    )))
+}
+
+/// Read the schema so a wrong key fails here with a clear message instead
+/// of as "file is not a database" from the first query
+async fn verify_key(conn: &Connection, path: &str, encrypted: bool) -> Result<()> {
+    let read = async {
+        let mut rows = conn.query("SELECT COUNT(*) FROM sqlite_master", ()).await?;
+        rows.next().await?;
+        Ok::<_, libsql::Error>(())
+    };
+
+    match read.await {
+        Ok(()) => Ok(()),
+        Err(libsql::Error::SqliteFailure(code, _)) if code & 0xff == SQLITE_NOTADB => {
+            if encrypted {
+                Err(anyhow::anyhow!(
+                    "Cannot decrypt database at {}: the encryption key is wrong or the file is not encrypted",
+                    path
+                ))
+            } else {
+                Err(anyhow::anyhow!(
+                    "Cannot read database at {}: it is encrypted or not a database; open it with its encryption key",
+                    path
+                ))
+            }
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
 async fn create_tables(conn: &Connection) -> Result<()> {
     conn.execute(
         r#"
@@ -143,8 +449,8 @@ async fn create_tables(conn: &Connection) -> Result<()> {
             name TEXT NOT NULL,
             dosage TEXT,
             description TEXT,
-            created_at TEXT DEFAULT (datetime('now')),
-            updated_at TEXT DEFAULT (datetime('now')),
+            created_at TEXT DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+            updated_at TEXT DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
             deleted_at TEXT
         )
         "#,
@@ -160,8 +466,8 @@ async fn create_tables(conn: &Connection) -> Result<()> {
             time TEXT NOT NULL,
             days TEXT,
             is_active INTEGER DEFAULT 1,
-            created_at TEXT DEFAULT (datetime('now')),
-            updated_at TEXT DEFAULT (datetime('now')),
+            created_at TEXT DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+            updated_at TEXT DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
             deleted_at TEXT,
             FOREIGN KEY (medication_id) REFERENCES medications(id)
         )
@@ -170,6 +476,210 @@ async fn create_tables(conn: &Connection) -> Result<()> {
     )
     .await?;
 
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_reminders_medication_id ON reminders(medication_id)",
+        (),
+    )
+    .await?;
+
+    Ok(())
+}
+
+// ===== Migrations =====
+
+/// Schema changes applied on top of `create_tables`, oldest first. Append
+/// only: the schema version of a database is the number of entries applied.
+const MIGRATIONS: &[&str] = &[
+    // 1: normalise timestamps written by `datetime('now')` defaults and
+    // older RFC 3339 writers to `YYYY-MM-DDTHH:MM:SS.SSSZ`
+    r#"
+    UPDATE medications SET
+        created_at = COALESCE(strftime('%Y-%m-%dT%H:%M:%fZ', created_at), created_at),
+        updated_at = COALESCE(strftime('%Y-%m-%dT%H:%M:%fZ', updated_at), updated_at),
+        deleted_at = COALESCE(strftime('%Y-%m-%dT%H:%M:%fZ', deleted_at), deleted_at);
+    UPDATE reminders SET
+        created_at = COALESCE(strftime('%Y-%m-%dT%H:%M:%fZ', created_at), created_at),
+        updated_at = COALESCE(strftime('%Y-%m-%dT%H:%M:%fZ', updated_at), updated_at),
+        deleted_at = COALESCE(strftime('%Y-%m-%dT%H:%M:%fZ', deleted_at), deleted_at);
+    "#,
+    // 2: globally unique record ids so devices can create rows offline and
+    // merge them later; existing rows are backfilled with random UUIDs
+    r#"
+    ALTER TABLE medications ADD COLUMN uuid TEXT;
+    ALTER TABLE reminders ADD COLUMN uuid TEXT;
+    UPDATE medications SET uuid = lower(
+        hex(randomblob(4)) || '-' || hex(randomblob(2)) || '-4' ||
+        substr(hex(randomblob(2)), 2) || '-' ||
+        substr('89ab', 1 + (abs(random()) % 4), 1) || substr(hex(randomblob(2)), 2) || '-' ||
+        hex(randomblob(6))
+    ) WHERE uuid IS NULL;
+    UPDATE reminders SET uuid = lower(
+        hex(randomblob(4)) || '-' || hex(randomblob(2)) || '-4' ||
+        substr(hex(randomblob(2)), 2) || '-' ||
+        substr('89ab', 1 + (abs(random()) % 4), 1) || substr(hex(randomblob(2)), 2) || '-' ||
+        hex(randomblob(6))
+    ) WHERE uuid IS NULL;
+    CREATE UNIQUE INDEX IF NOT EXISTS idx_medications_uuid ON medications(uuid);
+    CREATE UNIQUE INDEX IF NOT EXISTS idx_reminders_uuid ON reminders(uuid);
+    "#,
+    // 3: row versions for optimistic concurrency between devices
+    r#"
+    ALTER TABLE medications ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
+    ALTER TABLE reminders ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
+    "#,
+    // 4: history of doses taken or skipped, for adherence
+    r#"
+    CREATE TABLE IF NOT EXISTS dose_logs (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        uuid TEXT NOT NULL UNIQUE,
+        medication_id INTEGER NOT NULL,
+        reminder_id INTEGER,
+        status TEXT NOT NULL,
+        scheduled_for TEXT,
+        logged_at TEXT NOT NULL,
+        created_at TEXT DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+        FOREIGN KEY (medication_id) REFERENCES medications(id),
+        FOREIGN KEY (reminder_id) REFERENCES reminders(id)
+    );
+    CREATE INDEX IF NOT EXISTS idx_dose_logs_medication_logged
+        ON dose_logs(medication_id, logged_at);
+    "#,
+    // 5: profiles, so one database can hold several people's medications;
+    // existing medications move into a default profile. Its id is the same
+    // in every database, so default profiles merge on import.
+    r#"
+    CREATE TABLE IF NOT EXISTS profiles (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        uuid TEXT NOT NULL UNIQUE,
+        name TEXT NOT NULL,
+        created_at TEXT DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+        updated_at TEXT DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+        deleted_at TEXT
+    );
+    INSERT INTO profiles (uuid, name)
+        VALUES ('00000000-0000-0000-0000-000000000000', 'Default');
+    ALTER TABLE medications ADD COLUMN profile_id INTEGER REFERENCES profiles(id);
+    UPDATE medications SET profile_id = (SELECT MIN(id) FROM profiles);
+    CREATE INDEX IF NOT EXISTS idx_medications_profile_id ON medications(profile_id);
+    "#,
+    // 6: coded drug identifiers, so medications can be matched without
+    // relying on the free-text name
+    r#"
+    ALTER TABLE medications ADD COLUMN rxnorm_cui TEXT;
+    ALTER TABLE medications ADD COLUMN ndc TEXT;
+    ALTER TABLE medications ADD COLUMN atc_code TEXT;
+    CREATE INDEX IF NOT EXISTS idx_medications_rxnorm_cui ON medications(rxnorm_cui);
+    "#,
+    // 7: as-needed (PRN) medications and their safety limits
+    r#"
+    ALTER TABLE medications ADD COLUMN is_prn INTEGER NOT NULL DEFAULT 0;
+    ALTER TABLE medications ADD COLUMN min_hours_between_doses REAL;
+    ALTER TABLE medications ADD COLUMN max_daily_doses INTEGER;
+    "#,
+    // 8: phased schedules (tapers and titrations), their phases in order
+    r#"
+    CREATE TABLE IF NOT EXISTS phased_schedules (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        uuid TEXT NOT NULL UNIQUE,
+        medication_id INTEGER NOT NULL,
+        start_date TEXT NOT NULL,
+        created_at TEXT DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+        updated_at TEXT DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+        deleted_at TEXT,
+        version INTEGER NOT NULL DEFAULT 1,
+        FOREIGN KEY (medication_id) REFERENCES medications(id)
+    );
+    CREATE INDEX IF NOT EXISTS idx_phased_schedules_medication_id
+        ON phased_schedules(medication_id);
+    CREATE TABLE IF NOT EXISTS schedule_phases (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        schedule_id INTEGER NOT NULL,
+        position INTEGER NOT NULL,
+        duration_days INTEGER NOT NULL,
+        dose_quantity REAL NOT NULL,
+        dose_unit TEXT NOT NULL,
+        times TEXT NOT NULL,
+        days TEXT NOT NULL,
+        every_n_days INTEGER NOT NULL DEFAULT 1,
+        FOREIGN KEY (schedule_id) REFERENCES phased_schedules(id),
+        UNIQUE (schedule_id, position)
+    );
+    "#,
+    // 9: fixed courses of treatment, ended by date or by doses taken
+    r#"
+    ALTER TABLE medications ADD COLUMN course_start_date TEXT;
+    ALTER TABLE medications ADD COLUMN course_end_date TEXT;
+    ALTER TABLE medications ADD COLUMN course_total_doses INTEGER;
+    "#,
+];
+
+/// Version a fully migrated database reports
+pub const SCHEMA_VERSION: i64 = MIGRATIONS.len() as i64;
+
+/// Read the schema version recorded by `run_migrations`
+pub async fn schema_version(conn: &Connection) -> Result<i64> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS schema_version (version INTEGER NOT NULL)",
+        (),
+    )
+    .await?;
+
+    let mut rows = conn.query("SELECT MAX(version) FROM schema_version", ()).await?;
+    match rows.next().await? {
+        Some(row) => Ok(row.get::<Option<i64>>(0)?.unwrap_or(0)),
+        None => Ok(0),
+    }
+}
+
+/// Schema version of a pillmom database without creating anything, or
+/// `None` if the file lacks the pillmom tables
+async fn stored_schema_version(conn: &Connection) -> Result<Option<i64>> {
+    let mut rows = conn
+        .query(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' \
+             AND name IN ('medications', 'reminders', 'schema_version')",
+            (),
+        )
+        .await?;
+    let tables = match rows.next().await? {
+        Some(row) => row.get::<i64>(0)?,
+        None => 0,
+    };
+
+    if tables != 3 {
+        return Ok(None);
+    }
+
+    let mut rows = conn.query("SELECT MAX(version) FROM schema_version", ()).await?;
+    match rows.next().await? {
+        Some(row) => Ok(Some(row.get::<Option<i64>>(0)?.unwrap_or(0))),
+        None => Ok(Some(0)),
+    }
+}
+
+async fn run_migrations(conn: &Connection) -> Result<()> {
+    let current = schema_version(conn).await?;
+
+    if current > SCHEMA_VERSION {
+        return Err(anyhow::anyhow!(
+            "Database schema version {} is newer than supported version {}",
+            current,
+            SCHEMA_VERSION
+        ));
+    }
+
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(current as usize) {
+        let version = index as i64 + 1;
+        // Each step commits together with its version bump so an
+        // interrupted upgrade resumes where it stopped
+        conn.execute_transactional_batch(&format!(
+            "{}\nDELETE FROM schema_version;\nINSERT INTO schema_version (version) VALUES ({});",
+            migration, version
+        ))
+        .await
+        .map_err(|e| anyhow::anyhow!("Migration to schema version {} failed: {}", version, e))?;
+    }
+
     Ok(())
 }
 
@@ -178,39 +688,399 @@ async fn create_tables(conn: &Connection) -> Result<()> {
 pub async fn sync_database() -> Result<i64> {
     // Sync is only supported for embedded replicas
     // For other connection types, data is already synced or local-only
-    match DB_TYPE.get() {
-        Some(DbType::Remote) | Some(DbType::InMemory) | Some(DbType::Local) => {
+    match with_session(|session| (session.db_type, session.handle.clone())) {
+        Some((DbType::Remote | DbType::InMemory | DbType::Local, _)) => {
             // No sync needed for these types
             Ok(0)
         }
-        Some(DbType::EmbeddedReplica) => {
-            if let Some(db) = DB_HANDLE.get() {
-                // Attempt sync for embedded replica
-                let _result = db.sync().await?;
-                Ok(0)
-            } else {
-                Err(anyhow::anyhow!("No active database connection"))
-            }
+        Some((DbType::EmbeddedReplica, db)) => {
+            // Attempt sync for embedded replica
+            let _result = db.sync().await?;
+            Ok(0)
         }
         None => Err(anyhow::anyhow!("Database not initialized")),
     }
 }
 
+/// Re-encrypt the open local database with `new_key`. An unencrypted file
+/// becomes encrypted. Not supported in WAL journal mode.
+pub async fn rekey(new_key: &str) -> Result<()> {
+    ensure_writable()?;
+
+    match with_session(|session| session.db_type) {
+        Some(DbType::Local) | Some(DbType::EmbeddedReplica) => {}
+        Some(_) => {
+            return Err(anyhow::anyhow!(
+                "Key rotation is only supported for local database files"
+            ))
+        }
+        None => return Err(anyhow::anyhow!("Database not initialized")),
+    }
+
+    // Validates the key and feature before touching the file
+    encryption_config(Some(new_key))?;
+
+    let conn = get_connection().ok_or_else(|| anyhow::anyhow!("No database connection"))?;
+    let conn = conn.lock().await;
+
+    // Rekeying rewrites the main file only, leaving frames in the WAL under
+    // the old key, and the mode cannot be switched while readers have the
+    // file open
+    let mode = pragma(&conn, "PRAGMA journal_mode").await?;
+    if mode.is_some_and(|mode| mode.eq_ignore_ascii_case("wal")) {
+        return Err(anyhow::anyhow!(
+            "Cannot change the encryption key of a database in WAL mode; \
+             reopen it with another journal mode, change the key, then reopen it in WAL mode"
+        ));
+    }
+
+    // PRAGMA arguments cannot be bound, so quote the key as a string literal.
+    // The pragma may answer with a status row, so run it as a query.
+    let rekeyed = async {
+        let sql = format!("PRAGMA rekey = '{}'", new_key.replace('\'', "''"));
+        let mut rows = conn.query(&sql, ()).await?;
+        while rows.next().await?.is_some() {}
+        Ok::<_, libsql::Error>(())
+    };
+
+    rekeyed
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to change encryption key: {}", e))?;
+
+    // Reopening after a restore must use the new key
+    if let Some(session) = SESSION.write().unwrap_or_else(|e| e.into_inner()).as_mut() {
+        session.encryption_key = Some(new_key.to_string());
+    }
+
+    Ok(())
+}
+
+/// Write a consistent snapshot of the open database to `path`, replacing
+/// any file there. Uses `VACUUM INTO`, so other callers only wait for the
+/// copy, and the file at `path` is complete or absent.
+pub async fn backup_to(path: &str) -> Result<()> {
+    let conn = match with_session(|session| (session.db_type, session.conn.clone())) {
+        Some((DbType::Remote, _)) => {
+            return Err(anyhow::anyhow!(
+                "Backups are only supported for local and in-memory databases"
+            ))
+        }
+        Some((_, conn)) => conn,
+        None => return Err(anyhow::anyhow!("Database not initialized")),
+    };
+
+    // Written beside the target and renamed, as VACUUM INTO needs a new file
+    let partial = format!("{}.partial", path);
+    remove_file_if_exists(&partial).await?;
+
+    let result = conn
+        .lock()
+        .await
+        .execute("VACUUM INTO ?1", [partial.as_str()])
+        .await;
+
+    if let Err(e) = result {
+        let _ = remove_file_if_exists(&partial).await;
+        return Err(anyhow::anyhow!("Failed to back up database to {}: {}", path, e));
+    }
+
+    tokio::fs::rename(&partial, path)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to back up database to {}: {}", path, e))
+}
+
+/// Replace the open local database with the backup at `path`. The backup
+/// is checked (readable with the current key, intact, and a schema this
+/// version can migrate) before the session is closed, and the previous
+/// file is put back if the restored one fails to open.
+pub async fn restore_from(path: &str) -> Result<()> {
+    ensure_writable()?;
+
+    let (target, key, options, profile) = match with_session(|session| {
+        (
+            session.db_type,
+            session.path.clone(),
+            session.encryption_key.clone(),
+            session.options.clone(),
+            session.active_profile.clone(),
+        )
+    }) {
+        Some((DbType::Local, Some(target), key, options, profile)) => {
+            (target, key, options, profile)
+        }
+        Some(_) => {
+            return Err(anyhow::anyhow!(
+                "Restore is only supported for local database files"
+            ))
+        }
+        None => return Err(anyhow::anyhow!("Database not initialized")),
+    };
+
+    // Validate a private copy, so the file swapped in is the file checked
+    let staged = format!("{}.restore", target);
+    remove_file_if_exists(&staged).await?;
+    tokio::fs::copy(path, &staged)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to read backup {}: {}", path, e))?;
+
+    if let Err(e) = validate_backup(&staged, key.as_deref()).await {
+        let _ = remove_file_if_exists(&staged).await;
+        return Err(anyhow::anyhow!("Cannot restore from {}: {}", path, e));
+    }
+
+    end_session().await;
+
+    // With the session closed, any failure puts the previous files back and
+    // reopens them, so the caller is never left without a database
+    let previous = format!("{}.previous", target);
+    let mut moved = Vec::new();
+    let swapped = async {
+        move_database_files(&target, &previous, &mut moved).await?;
+        tokio::fs::rename(&staged, &target).await?;
+        init_local_db(&target, key.as_deref(), &options).await
+    }
+    .await;
+
+    if let Err(e) = swapped {
+        tracing::warn!("Restore failed, putting back the previous database: {}", e);
+        let _ = remove_file_if_exists(&staged).await;
+        put_back_database_files(&previous, &target, &moved).await?;
+        init_local_db(&target, key.as_deref(), &options).await?;
+        set_active_profile(profile)?;
+        return Err(anyhow::anyhow!("Cannot restore from {}: {}", path, e));
+    }
+
+    // Stay on the same person rather than switching silently
+    set_active_profile(profile)?;
+
+    // The restore has succeeded; a leftover copy is only untidy
+    for suffix in DATABASE_FILE_SUFFIXES {
+        if let Err(e) = remove_file_if_exists(&format!("{}{}", previous, suffix)).await {
+            tracing::warn!("Failed to remove the replaced database: {}", e);
+        }
+    }
+
+    Ok(())
+}
+
+/// End the current session so another database can be opened. Waits for
+/// operations already in progress; closing with nothing open is allowed.
 pub async fn close_database() -> Result<()> {
-    // libsql connections are automatically closed when dropped
+    // The outbox belongs to the session's remote; a later session enables
+    // its own
+    outbox::disable().await;
+    end_session().await;
+
+    Ok(())
+}
+
+/// Close the session's connections, leaving the outbox alone
+async fn end_session() {
+    let session = SESSION.write().unwrap_or_else(|e| e.into_inner()).take();
+    let Some(session) = session else {
+        return;
+    };
+
+    for reader in session.readers {
+        release(reader).await;
+    }
+    release(session.conn).await;
+}
+
+/// Drop `conn` once every caller that picked it up has let go of it, which
+/// is when libsql closes it
+async fn release(mut conn: Arc<Mutex<DbConnection>>) {
+    loop {
+        match Arc::try_unwrap(conn) {
+            Ok(conn) => {
+                drop(conn);
+                return;
+            }
+            Err(shared) => {
+                conn = shared;
+                drop(conn.lock().await);
+                tokio::task::yield_now().await;
+            }
+        }
+    }
+}
+
+// ===== Backup Helpers =====
+
+/// The main file and the journals SQLite may keep beside it
+const DATABASE_FILE_SUFFIXES: [&str; 4] = ["", "-wal", "-shm", "-journal"];
+
+/// Check that the file at `path` is a pillmom database that can be opened
+/// in place of the current one
+async fn validate_backup(path: &str, encryption_key: Option<&str>) -> Result<()> {
+    let mut builder = Builder::new_local(path).flags(OpenFlags::SQLITE_OPEN_READ_ONLY);
+
+    if let Some(config) = encryption_config(encryption_key)? {
+        builder = builder.encryption_config(config);
+    }
+
+    let db = builder.build().await?;
+    let conn = db.connect()?;
+
+    // Messages name the backup, not this staged copy
+    verify_key(&conn, path, encryption_key.is_some())
+        .await
+        .map_err(|_| {
+            anyhow::anyhow!("it is not a database or is encrypted with a different key")
+        })?;
+
+    let mut problems = Vec::new();
+    let mut rows = conn.query("PRAGMA integrity_check", ()).await?;
+    while let Some(row) = rows.next().await? {
+        let message: String = row.get(0)?;
+        if message != "ok" {
+            problems.push(message);
+        }
+    }
+
+    if !problems.is_empty() {
+        return Err(anyhow::anyhow!(
+            "integrity check failed: {}",
+            problems.join("; ")
+        ));
+    }
+
+    let Some(version) = stored_schema_version(&conn).await? else {
+        return Err(anyhow::anyhow!("it is not a pillmom database"));
+    };
+
+    if version > SCHEMA_VERSION {
+        return Err(anyhow::anyhow!(
+            "its schema version {} is newer than supported version {}",
+            version,
+            SCHEMA_VERSION
+        ));
+    }
+
+    Ok(())
+}
+
+/// Move the files of the database at `from` to `to`. Each suffix whose
+/// file is no longer at `from` is added to `moved`, so a failure part way
+/// through can be undone.
+async fn move_database_files(from: &str, to: &str, moved: &mut Vec<&'static str>) -> Result<()> {
+    for suffix in DATABASE_FILE_SUFFIXES {
+        let source = format!("{}{}", from, suffix);
+        let destination = format!("{}{}", to, suffix);
+
+        remove_file_if_exists(&destination).await?;
+        if Path::new(&source).exists() {
+            tokio::fs::rename(&source, &destination).await?;
+        }
+        moved.push(suffix);
+    }
+
+    Ok(())
+}
+
+/// Undo `move_database_files(target, previous)` for the suffixes in
+/// `moved`, replacing whatever the restore left at `target`. Files that
+/// were never moved are left alone.
+async fn put_back_database_files(previous: &str, target: &str, moved: &[&str]) -> Result<()> {
+    for suffix in moved {
+        let original = format!("{}{}", previous, suffix);
+        let current = format!("{}{}", target, suffix);
+
+        remove_file_if_exists(&current).await?;
+        if Path::new(&original).exists() {
+            tokio::fs::rename(&original, &current)
+                .await
+                .map_err(|e| anyhow::anyhow!("Failed to put back {}: {}", current, e))?;
+        }
+    }
+
     Ok(())
 }
 
+async fn remove_file_if_exists(path: &str) -> Result<()> {
+    match tokio::fs::remove_file(path).await {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(anyhow::anyhow!("Failed to remove {}: {}", path, e)),
+    }
+}
+
 // ===== Getters for repository layer =====
 
-pub fn get_connection() -> Option<Arc<Mutex<Connection>>> {
-    DATABASE.get().cloned()
+pub fn get_connection() -> Option<Arc<Mutex<DbConnection>>> {
+    with_session(|session| session.conn.clone())
+}
+
+/// A connection for queries that only read. Prefers an idle reader and
+/// otherwise takes turns; without readers this is `get_connection`.
+pub fn get_reader() -> Option<Arc<Mutex<DbConnection>>> {
+    with_session(|session| {
+        if session.readers.is_empty() {
+            return session.conn.clone();
+        }
+
+        let idle = session
+            .readers
+            .iter()
+            .find(|reader| reader.try_lock().is_ok());
+
+        match idle {
+            Some(reader) => reader.clone(),
+            None => {
+                let next = session.next_reader.fetch_add(1, Ordering::Relaxed);
+                session.readers[next % session.readers.len()].clone()
+            }
+        }
+    })
+}
+
+/// Profile chosen for this session, if any
+pub fn active_profile() -> Option<String> {
+    with_session(|session| session.active_profile.clone()).flatten()
+}
+
+/// Choose the profile this session lists and creates medications in;
+/// `None` goes back to the oldest live profile
+pub fn set_active_profile(id: Option<String>) -> Result<()> {
+    let mut session = SESSION.write().unwrap_or_else(|e| e.into_inner());
+    let session = session
+        .as_mut()
+        .ok_or_else(|| anyhow::anyhow!("Database not initialized"))?;
+    session.active_profile = id;
+    Ok(())
+}
+
+/// Fail with `PillMomError::ReadOnly` if the open database was opened
+/// read-only, so writes are refused before they reach the database (or the
+/// outbox)
+pub fn ensure_writable() -> Result<()> {
+    if is_read_only() {
+        return Err(PillMomError::ReadOnly.into());
+    }
+    Ok(())
+}
+
+/// Whether the open database was opened read-only
+pub fn is_read_only() -> bool {
+    with_session(|session| session.options.read_only).unwrap_or(false)
 }
 
-#[allow(dead_code)]
 pub fn is_remote() -> bool {
     matches!(
-        DB_TYPE.get(),
+        with_session(|session| session.db_type),
         Some(DbType::Remote) | Some(DbType::EmbeddedReplica)
     )
-}
\ No newline at end of file
+}
+
+/// Start a test on a fresh in-memory database. The session is shared by
+/// the whole process, so tests that use it hold the returned guard and run
+/// one at a time.
+#[cfg(test)]
+pub(crate) async fn test_session() -> tokio::sync::MutexGuard<'static, ()> {
+    static LOCK: Mutex<()> = Mutex::const_new(());
+
+    let guard = LOCK.lock().await;
+    close_database().await.unwrap();
+    init_in_memory(&OpenOptions::default()).await.unwrap();
+    guard
+}
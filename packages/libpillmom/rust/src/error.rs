@@ -0,0 +1,69 @@
+use crate::models::{Medication, PhasedSchedule, Reminder};
+use std::fmt;
+
+/// Errors that callers are expected to handle individually. Anything else
+/// is carried as a message in `Other`.
+#[derive(Debug, Clone)]
+pub enum PillMomError {
+    /// The medication changed since the caller read it; carries the
+    /// current stored copy so the caller can rebase its edit
+    MedicationConflict { current: Box<Medication> },
+    /// The reminder changed since the caller read it
+    ReminderConflict { current: Reminder },
+    /// The phased schedule changed since the caller read it
+    ScheduleConflict { current: PhasedSchedule },
+    /// The database was opened read-only, so the write was not attempted
+    ReadOnly,
+    Other { message: String },
+}
+
+impl fmt::Display for PillMomError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PillMomError::MedicationConflict { current } => write!(
+                f,
+                "Medication {} was modified concurrently (stored version {})",
+                current.id.as_deref().unwrap_or_default(),
+                current.version
+            ),
+            PillMomError::ReminderConflict { current } => write!(
+                f,
+                "Reminder {} was modified concurrently (stored version {})",
+                current.id.as_deref().unwrap_or_default(),
+                current.version
+            ),
+            PillMomError::ScheduleConflict { current } => write!(
+                f,
+                "Schedule {} was modified concurrently (stored version {})",
+                current.id.as_deref().unwrap_or_default(),
+                current.version
+            ),
+            PillMomError::ReadOnly => {
+                f.write_str("The database is open read-only; changes are not allowed")
+            }
+            PillMomError::Other { message } => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for PillMomError {}
+
+impl From<anyhow::Error> for PillMomError {
+    fn from(error: anyhow::Error) -> Self {
+        // Keep typed errors that were passed through anyhow intact
+        match error.downcast::<PillMomError>() {
+            Ok(typed) => typed,
+            Err(other) => PillMomError::Other {
+                message: other.to_string(),
+            },
+        }
+    }
+}
+
+impl From<libsql::Error> for PillMomError {
+    fn from(error: libsql::Error) -> Self {
+        PillMomError::Other {
+            message: error.to_string(),
+        }
+    }
+}
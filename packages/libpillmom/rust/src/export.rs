@@ -0,0 +1,274 @@
+use crate::database::{self, SCHEMA_VERSION};
+use crate::models::{DoseLog, ImportMode, ImportSummary, Medication, PhasedSchedule, Profile};
+use crate::repository::{self, new_record_id};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+// Portable JSON dump of a user's data, used for backups, moving between
+// devices and reproducing support cases.
+
+/// Value of `format` in every export, so unrelated JSON is rejected early
+const EXPORT_FORMAT: &str = "pillmom-export";
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ExportDocument {
+    format: String,
+    /// Schema version of the database the export was taken from
+    schema_version: i64,
+    exported_at: DateTime<Utc>,
+    /// Absent from exports taken before profiles existed
+    #[serde(default)]
+    profiles: Vec<Profile>,
+    /// Medications with their reminders nested, soft-deleted ones included
+    medications: Vec<Medication>,
+    #[serde(default)]
+    schedules: Vec<PhasedSchedule>,
+    #[serde(default)]
+    dose_history: Vec<DoseLog>,
+}
+
+pub async fn export_json() -> Result<String> {
+    let document = ExportDocument {
+        format: EXPORT_FORMAT.to_string(),
+        schema_version: SCHEMA_VERSION,
+        exported_at: Utc::now(),
+        profiles: repository::export_profiles().await?,
+        medications: repository::export_records().await?,
+        schedules: repository::export_schedules().await?,
+        dose_history: repository::export_dose_logs().await?,
+    };
+
+    Ok(serde_json::to_string_pretty(&document)?)
+}
+
+pub async fn import_json(data: &str, mode: ImportMode) -> Result<ImportSummary> {
+    database::ensure_writable()?;
+
+    let mut document: ExportDocument = serde_json::from_str(data)
+        .map_err(|e| anyhow::anyhow!("Invalid export data: {}", e))?;
+
+    if document.format != EXPORT_FORMAT {
+        return Err(anyhow::anyhow!(
+            "Unrecognised export format '{}'",
+            document.format
+        ));
+    }
+
+    if document.schema_version > SCHEMA_VERSION {
+        return Err(anyhow::anyhow!(
+            "Export schema version {} is newer than supported version {}",
+            document.schema_version,
+            SCHEMA_VERSION
+        ));
+    }
+
+    let ids_remapped = assign_ids(
+        &mut document.profiles,
+        &mut document.medications,
+        &mut document.schedules,
+        &mut document.dose_history,
+    );
+
+    let mut summary = repository::import_records(
+        &document.profiles,
+        &document.medications,
+        &document.schedules,
+        &document.dose_history,
+        mode,
+    )
+    .await?;
+    summary.ids_remapped = ids_remapped;
+
+    Ok(summary)
+}
+
+// ===== Helper Functions =====
+
+/// Give a fresh id to every record whose id is missing, not a UUID, or
+/// already used earlier in the import, and point each reminder at the
+/// medication it is nested under. Medications follow their profile, and
+/// schedules and doses their medication (and doses their reminder), to any
+/// new id. Returns the number of ids replaced.
+fn assign_ids(
+    profiles: &mut [Profile],
+    medications: &mut [Medication],
+    schedules: &mut [PhasedSchedule],
+    doses: &mut [DoseLog],
+) -> i64 {
+    let mut seen = HashSet::new();
+    // Malformed ids that were replaced, so references to them can follow;
+    // a repeated valid id still refers to its first record
+    let mut replaced: HashMap<String, String> = HashMap::new();
+    let mut remapped = 0;
+
+    let mut keep_or_replace = |id: &mut Option<String>| {
+        let usable = id
+            .as_deref()
+            .is_some_and(|value| Uuid::parse_str(value).is_ok() && seen.insert(value.to_string()));
+
+        if !usable {
+            let fresh = new_record_id();
+            seen.insert(fresh.clone());
+            if let Some(old) = id.take() {
+                if Uuid::parse_str(&old).is_err() {
+                    replaced.entry(old).or_insert_with(|| fresh.clone());
+                }
+            }
+            *id = Some(fresh);
+            remapped += 1;
+        }
+    };
+
+    for profile in profiles.iter_mut() {
+        keep_or_replace(&mut profile.id);
+    }
+
+    for med in medications.iter_mut() {
+        keep_or_replace(&mut med.id);
+        let medication_id = med.id.clone().unwrap_or_default();
+
+        for reminder in &mut med.reminders {
+            keep_or_replace(&mut reminder.id);
+            reminder.medication_id = medication_id.clone();
+        }
+    }
+
+    for schedule in schedules.iter_mut() {
+        keep_or_replace(&mut schedule.id);
+    }
+
+    for dose in doses.iter_mut() {
+        keep_or_replace(&mut dose.id);
+    }
+
+    for med in medications.iter_mut() {
+        if let Some(id) = med.profile_id.as_ref().and_then(|id| replaced.get(id)) {
+            med.profile_id = Some(id.clone());
+        }
+    }
+
+    for schedule in schedules.iter_mut() {
+        if let Some(id) = replaced.get(&schedule.medication_id) {
+            schedule.medication_id = id.clone();
+        }
+    }
+
+    for dose in doses.iter_mut() {
+        if let Some(id) = replaced.get(&dose.medication_id) {
+            dose.medication_id = id.clone();
+        }
+        if let Some(id) = dose.reminder_id.as_ref().and_then(|id| replaced.get(id)) {
+            dose.reminder_id = Some(id.clone());
+        }
+    }
+
+    remapped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{DoseStatus, Reminder};
+
+    const PROFILE: &str = "0190a5b0-0000-7000-8000-000000000001";
+    const MEDICATION: &str = "0190a5b0-0000-7000-8000-000000000002";
+
+    fn dose(id: &str, medication_id: &str, reminder_id: Option<&str>) -> DoseLog {
+        DoseLog {
+            id: Some(id.to_string()),
+            medication_id: medication_id.to_string(),
+            reminder_id: reminder_id.map(str::to_string),
+            status: DoseStatus::Taken,
+            scheduled_for: None,
+            logged_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn valid_ids_are_kept() {
+        let mut profiles = vec![Profile {
+            id: Some(PROFILE.to_string()),
+            ..Default::default()
+        }];
+        let mut medications = vec![Medication {
+            id: Some(MEDICATION.to_string()),
+            profile_id: Some(PROFILE.to_string()),
+            ..Default::default()
+        }];
+
+        let remapped = assign_ids(&mut profiles, &mut medications, &mut [], &mut []);
+
+        assert_eq!(remapped, 0);
+        assert_eq!(profiles[0].id.as_deref(), Some(PROFILE));
+        assert_eq!(medications[0].id.as_deref(), Some(MEDICATION));
+        assert_eq!(medications[0].profile_id.as_deref(), Some(PROFILE));
+    }
+
+    #[test]
+    fn malformed_ids_are_replaced_and_followed() {
+        let mut profiles = vec![Profile {
+            id: Some("home".to_string()),
+            ..Default::default()
+        }];
+        let mut medications = vec![Medication {
+            id: Some("med-1".to_string()),
+            profile_id: Some("home".to_string()),
+            reminders: vec![Reminder {
+                id: Some("rem-1".to_string()),
+                medication_id: "something-else".to_string(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        }];
+        let mut schedules = vec![PhasedSchedule {
+            id: None,
+            medication_id: "med-1".to_string(),
+            ..Default::default()
+        }];
+        let mut doses = vec![dose("dose-1", "med-1", Some("rem-1"))];
+
+        let remapped = assign_ids(&mut profiles, &mut medications, &mut schedules, &mut doses);
+
+        assert_eq!(remapped, 5);
+        let profile_id = profiles[0].id.clone().unwrap();
+        let medication_id = medications[0].id.clone().unwrap();
+        let reminder_id = medications[0].reminders[0].id.clone().unwrap();
+        assert!(Uuid::parse_str(&profile_id).is_ok());
+        assert_eq!(
+            medications[0].profile_id.as_deref(),
+            Some(profile_id.as_str())
+        );
+        assert_eq!(medications[0].reminders[0].medication_id, medication_id);
+        assert!(schedules[0].id.is_some());
+        assert_eq!(schedules[0].medication_id, medication_id);
+        assert_eq!(doses[0].medication_id, medication_id);
+        assert_eq!(doses[0].reminder_id.as_deref(), Some(reminder_id.as_str()));
+    }
+
+    #[test]
+    fn repeated_ids_get_fresh_ones_without_moving_references() {
+        let mut medications = vec![
+            Medication {
+                id: Some(MEDICATION.to_string()),
+                ..Default::default()
+            },
+            Medication {
+                id: Some(MEDICATION.to_string()),
+                ..Default::default()
+            },
+        ];
+        let mut doses = vec![dose(MEDICATION, MEDICATION, None)];
+
+        let remapped = assign_ids(&mut [], &mut medications, &mut [], &mut doses);
+
+        assert_eq!(remapped, 2);
+        assert_eq!(medications[0].id.as_deref(), Some(MEDICATION));
+        assert_ne!(medications[1].id.as_deref(), Some(MEDICATION));
+        assert_ne!(doses[0].id.as_deref(), Some(MEDICATION));
+        // A repeated valid id still names the first record
+        assert_eq!(doses[0].medication_id, MEDICATION);
+    }
+}
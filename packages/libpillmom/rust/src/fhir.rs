@@ -0,0 +1,478 @@
+use crate::course;
+use crate::models::{FhirResourceType, Medication, Reminder};
+use crate::repository::{self, new_record_id};
+use crate::schedule::{self, format_date, parse_date, parse_days, parse_time, DaySet};
+use anyhow::Result;
+use chrono::{DateTime, SecondsFormat, Utc, Weekday};
+use serde_json::{json, Map, Value};
+use std::collections::HashSet;
+
+// HL7 FHIR R4 export of the current medication list, for sharing with a
+// provider. Each medication becomes one resource in a `collection` Bundle;
+// its active reminders become `Dosage` entries with a `Timing.repeat`.
+// A medication whose course is over is `completed` rather than `active`;
+// course dates are local, so the caller gives its UTC offset.
+
+pub async fn export_bundle(
+    resource_type: FhirResourceType,
+    patient_name: &str,
+    utc_offset_minutes: i64,
+) -> Result<String> {
+    let offset = schedule::utc_offset(utc_offset_minutes)?;
+    let medications = repository::get_all_medications().await?;
+    let now = Utc::now();
+    let today = now.with_timezone(&offset).date_naive();
+
+    let mut completed = HashSet::new();
+    for med in medications.iter().filter(|med| med.deleted_at.is_none()) {
+        if let Some(id) = med.id.as_deref() {
+            if course::is_over(med, today, offset).await? {
+                completed.insert(id);
+            }
+        }
+    }
+
+    let bundle = bundle(&medications, &completed, resource_type, patient_name, now);
+
+    Ok(serde_json::to_string_pretty(&bundle)?)
+}
+
+fn bundle(
+    medications: &[Medication],
+    completed: &HashSet<&str>,
+    resource_type: FhirResourceType,
+    patient_name: &str,
+    now: DateTime<Utc>,
+) -> Value {
+    let entries: Vec<Value> = medications
+        .iter()
+        .filter(|med| med.deleted_at.is_none())
+        .filter_map(|med| {
+            let id = med.id.as_deref()?;
+            Some(json!({
+                "fullUrl": format!("urn:uuid:{}", id),
+                "resource": resource(
+                    med,
+                    id,
+                    completed.contains(id),
+                    resource_type,
+                    patient_name,
+                    now,
+                ),
+            }))
+        })
+        .collect();
+
+    json!({
+        "resourceType": "Bundle",
+        "id": new_record_id(),
+        "type": "collection",
+        "timestamp": instant(now),
+        "entry": entries,
+    })
+}
+
+fn resource(
+    med: &Medication,
+    id: &str,
+    completed: bool,
+    resource_type: FhirResourceType,
+    patient_name: &str,
+    now: DateTime<Utc>,
+) -> Value {
+    let mut dosages: Vec<Value> = med
+        .reminders
+        .iter()
+        .filter(|reminder| reminder.is_active && reminder.deleted_at.is_none())
+        .enumerate()
+        .map(|(index, reminder)| dosage(med, reminder, index as i64 + 1))
+        .collect();
+    if med.is_prn {
+        dosages.push(as_needed_dosage(med, dosages.len() as i64 + 1));
+    }
+
+    let mut resource = Map::new();
+    resource.insert("id".into(), json!(id));
+    let status = if completed { "completed" } else { "active" };
+    resource.insert("status".into(), json!(status));
+
+    match resource_type {
+        FhirResourceType::MedicationStatement => {
+            resource.insert("resourceType".into(), json!("MedicationStatement"));
+            resource.insert("medicationCodeableConcept".into(), medication_concept(med));
+            resource.insert("subject".into(), json!({ "display": patient_name }));
+            let period =
+                course_period(med).unwrap_or_else(|| json!({ "start": instant(med.created_at) }));
+            resource.insert("effectivePeriod".into(), period);
+            resource.insert("dateAsserted".into(), json!(instant(now)));
+            if !dosages.is_empty() {
+                resource.insert("dosage".into(), Value::Array(dosages));
+            }
+        }
+        FhirResourceType::MedicationRequest => {
+            resource.insert("resourceType".into(), json!("MedicationRequest"));
+            // The entries come from the patient rather than the
+            // prescriber's system
+            resource.insert("intent".into(), json!("order"));
+            resource.insert("reportedBoolean".into(), json!(true));
+            resource.insert("medicationCodeableConcept".into(), medication_concept(med));
+            resource.insert("subject".into(), json!({ "display": patient_name }));
+            resource.insert("authoredOn".into(), json!(instant(med.created_at)));
+            if !dosages.is_empty() {
+                resource.insert("dosageInstruction".into(), Value::Array(dosages));
+            }
+        }
+    }
+
+    if !med.description.is_empty() {
+        resource.insert("note".into(), json!([{ "text": med.description }]));
+    }
+
+    Value::Object(resource)
+}
+
+/// `CodeableConcept` for the medication: the name as entered, plus a
+/// `coding` for each identifier it has
+fn medication_concept(med: &Medication) -> Value {
+    let codes = [
+        ("http://www.nlm.nih.gov/research/umls/rxnorm", &med.rxnorm_cui),
+        ("http://hl7.org/fhir/sid/ndc", &med.ndc),
+        ("http://www.whocc.no/atc", &med.atc_code),
+    ];
+    let coding: Vec<Value> = codes
+        .iter()
+        .filter_map(|(system, code)| {
+            let code = code.as_deref()?;
+            Some(json!({ "system": system, "code": code }))
+        })
+        .collect();
+
+    let mut concept = Map::new();
+    if !coding.is_empty() {
+        concept.insert("coding".into(), Value::Array(coding));
+    }
+    concept.insert("text".into(), json!(med.name));
+    Value::Object(concept)
+}
+
+/// A `Dosage` for one reminder. The timing is left out when the reminder's
+/// time or days cannot be read, rather than guessing a schedule; the text
+/// still carries what the user entered.
+fn dosage(med: &Medication, reminder: &Reminder, sequence: i64) -> Value {
+    let mut text = format!("{} at {}", med.dosage, reminder.time);
+    if !reminder.days.is_empty() {
+        text.push_str(&format!(" ({})", reminder.days));
+    }
+
+    let mut dosage = Map::new();
+    dosage.insert("sequence".into(), json!(sequence));
+    dosage.insert("text".into(), json!(text.trim_start()));
+
+    if let (Some(time), Some(days)) = (parse_time(&reminder.time), parse_days(&reminder.days)) {
+        let mut repeat = Map::new();
+        repeat.insert("frequency".into(), json!(1));
+        repeat.insert("period".into(), json!(1));
+        repeat.insert("periodUnit".into(), json!("d"));
+        if let DaySet::Only(weekdays) = days {
+            let codes: Vec<&str> = weekdays.iter().copied().map(day_code).collect();
+            repeat.insert("dayOfWeek".into(), json!(codes));
+        }
+        repeat.insert("timeOfDay".into(), json!([time.format("%H:%M:%S").to_string()]));
+        if let Some(period) = course_period(med) {
+            repeat.insert("boundsPeriod".into(), period);
+        }
+
+        dosage.insert("timing".into(), json!({ "repeat": repeat }));
+    }
+
+    Value::Object(dosage)
+}
+
+/// A `Dosage` for taking the medication as needed, with its limits
+fn as_needed_dosage(med: &Medication, sequence: i64) -> Value {
+    let mut text = format!("{} as needed", med.dosage);
+    if let Some(hours) = med.min_hours_between_doses {
+        text.push_str(&format!(", at least {} hours apart", hours));
+    }
+    if let Some(max) = med.max_daily_doses {
+        text.push_str(&format!(", at most {} per day", max));
+    }
+
+    let mut dosage = Map::new();
+    dosage.insert("sequence".into(), json!(sequence));
+    dosage.insert("text".into(), json!(text.trim_start()));
+    dosage.insert("asNeededBoolean".into(), json!(true));
+    if let Some(max) = med.max_daily_doses {
+        dosage.insert(
+            "maxDosePerPeriod".into(),
+            json!({
+                "numerator": { "value": max },
+                "denominator": {
+                    "value": 1,
+                    "unit": "day",
+                    "system": "http://unitsofmeasure.org",
+                    "code": "d",
+                },
+            }),
+        );
+    }
+
+    Value::Object(dosage)
+}
+
+/// `Period` of the medication's course, from whichever of its dates can
+/// be read
+fn course_period(med: &Medication) -> Option<Value> {
+    let mut period = Map::new();
+    for (key, date) in [
+        ("start", &med.course_start_date),
+        ("end", &med.course_end_date),
+    ] {
+        if let Some(date) = date.as_deref().and_then(parse_date) {
+            period.insert(key.into(), json!(format_date(date)));
+        }
+    }
+    (!period.is_empty()).then_some(Value::Object(period))
+}
+
+/// FHIR `days-of-week` code
+fn day_code(day: Weekday) -> &'static str {
+    match day {
+        Weekday::Mon => "mon",
+        Weekday::Tue => "tue",
+        Weekday::Wed => "wed",
+        Weekday::Thu => "thu",
+        Weekday::Fri => "fri",
+        Weekday::Sat => "sat",
+        Weekday::Sun => "sun",
+    }
+}
+
+/// FHIR `instant`/`dateTime`: seconds precision with an explicit zone
+fn instant(value: DateTime<Utc>) -> String {
+    value.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 3, 4, 9, 30, 0).unwrap()
+    }
+
+    fn medication() -> Medication {
+        Medication {
+            id: Some("0190a5b0-0000-7000-8000-000000000002".to_string()),
+            name: "Amoxicillin".to_string(),
+            dosage: "500mg".to_string(),
+            rxnorm_cui: Some("308191".to_string()),
+            course_start_date: Some("2024-03-01".to_string()),
+            course_end_date: Some("2024-03-10".to_string()),
+            created_at: Utc.with_ymd_and_hms(2024, 3, 1, 8, 0, 0).unwrap(),
+            reminders: vec![
+                Reminder {
+                    time: "08:00".to_string(),
+                    days: "Mon, Wed, Fri".to_string(),
+                    ..Default::default()
+                },
+                Reminder {
+                    time: "20:30".to_string(),
+                    days: "Daily".to_string(),
+                    ..Default::default()
+                },
+                Reminder {
+                    time: "12:00".to_string(),
+                    is_active: false,
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        }
+    }
+
+    fn first_resource(bundle: &Value) -> &Value {
+        &bundle["entry"][0]["resource"]
+    }
+
+    #[test]
+    fn bundle_is_a_collection_of_medications() {
+        let deleted = Medication {
+            id: Some("0190a5b0-0000-7000-8000-000000000003".to_string()),
+            deleted_at: Some(now()),
+            ..Default::default()
+        };
+        let bundle = bundle(
+            &[medication(), deleted],
+            &HashSet::new(),
+            FhirResourceType::MedicationStatement,
+            "Alex",
+            now(),
+        );
+
+        assert_eq!(bundle["resourceType"], "Bundle");
+        assert_eq!(bundle["type"], "collection");
+        assert_eq!(bundle["timestamp"], "2024-03-04T09:30:00Z");
+
+        let entries = bundle["entry"].as_array().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(
+            entries[0]["fullUrl"],
+            "urn:uuid:0190a5b0-0000-7000-8000-000000000002"
+        );
+    }
+
+    #[test]
+    fn medication_statement_structure() {
+        let bundle = bundle(
+            &[medication()],
+            &HashSet::new(),
+            FhirResourceType::MedicationStatement,
+            "Alex",
+            now(),
+        );
+        let resource = first_resource(&bundle);
+
+        assert_eq!(resource["resourceType"], "MedicationStatement");
+        assert_eq!(resource["id"], "0190a5b0-0000-7000-8000-000000000002");
+        assert_eq!(resource["status"], "active");
+        assert_eq!(resource["subject"]["display"], "Alex");
+        assert_eq!(resource["dateAsserted"], "2024-03-04T09:30:00Z");
+        assert_eq!(resource["medicationCodeableConcept"]["text"], "Amoxicillin");
+        assert_eq!(
+            resource["medicationCodeableConcept"]["coding"][0],
+            json!({ "system": "http://www.nlm.nih.gov/research/umls/rxnorm", "code": "308191" })
+        );
+        assert_eq!(
+            resource["effectivePeriod"],
+            json!({ "start": "2024-03-01", "end": "2024-03-10" })
+        );
+        // The inactive reminder is left out
+        assert_eq!(resource["dosage"].as_array().unwrap().len(), 2);
+        assert!(resource.get("dosageInstruction").is_none());
+    }
+
+    #[test]
+    fn finished_courses_are_completed() {
+        let completed = HashSet::from(["0190a5b0-0000-7000-8000-000000000002"]);
+        for resource_type in [
+            FhirResourceType::MedicationStatement,
+            FhirResourceType::MedicationRequest,
+        ] {
+            let bundle = bundle(&[medication()], &completed, resource_type, "Alex", now());
+            assert_eq!(first_resource(&bundle)["status"], "completed");
+        }
+
+        let bundle = bundle(
+            &[medication()],
+            &completed,
+            FhirResourceType::MedicationStatement,
+            "Alex",
+            now(),
+        );
+        assert_eq!(
+            first_resource(&bundle)["effectivePeriod"],
+            json!({ "start": "2024-03-01", "end": "2024-03-10" })
+        );
+    }
+
+    #[test]
+    fn medication_request_structure() {
+        let bundle = bundle(
+            &[medication()],
+            &HashSet::new(),
+            FhirResourceType::MedicationRequest,
+            "Alex",
+            now(),
+        );
+        let resource = first_resource(&bundle);
+
+        assert_eq!(resource["resourceType"], "MedicationRequest");
+        assert_eq!(resource["intent"], "order");
+        assert_eq!(resource["reportedBoolean"], true);
+        assert_eq!(resource["authoredOn"], "2024-03-01T08:00:00Z");
+        assert_eq!(resource["dosageInstruction"].as_array().unwrap().len(), 2);
+        assert!(resource.get("dosage").is_none());
+        assert!(resource.get("effectivePeriod").is_none());
+    }
+
+    #[test]
+    fn reminders_become_timing_repeats() {
+        let bundle = bundle(
+            &[medication()],
+            &HashSet::new(),
+            FhirResourceType::MedicationStatement,
+            "Alex",
+            now(),
+        );
+        let dosages = &first_resource(&bundle)["dosage"];
+
+        assert_eq!(dosages[0]["sequence"], 1);
+        assert_eq!(dosages[0]["text"], "500mg at 08:00 (Mon, Wed, Fri)");
+        assert_eq!(
+            dosages[0]["timing"]["repeat"],
+            json!({
+                "frequency": 1,
+                "period": 1,
+                "periodUnit": "d",
+                "dayOfWeek": ["mon", "wed", "fri"],
+                "timeOfDay": ["08:00:00"],
+                "boundsPeriod": { "start": "2024-03-01", "end": "2024-03-10" },
+            })
+        );
+
+        // Every day needs no dayOfWeek
+        let repeat = &dosages[1]["timing"]["repeat"];
+        assert!(repeat.get("dayOfWeek").is_none());
+        assert_eq!(repeat["timeOfDay"], json!(["20:30:00"]));
+    }
+
+    #[test]
+    fn unreadable_reminders_keep_their_text_without_timing() {
+        let med = Medication {
+            reminders: vec![Reminder {
+                time: "after breakfast".to_string(),
+                ..Default::default()
+            }],
+            ..medication()
+        };
+        let bundle = bundle(
+            &[med],
+            &HashSet::new(),
+            FhirResourceType::MedicationStatement,
+            "Alex",
+            now(),
+        );
+        let dosage = &first_resource(&bundle)["dosage"][0];
+
+        assert_eq!(dosage["text"], "500mg at after breakfast");
+        assert!(dosage.get("timing").is_none());
+    }
+
+    #[test]
+    fn as_needed_medications_get_their_limits() {
+        let med = Medication {
+            is_prn: true,
+            min_hours_between_doses: Some(4.0),
+            max_daily_doses: Some(3),
+            reminders: Vec::new(),
+            ..medication()
+        };
+        let bundle = bundle(
+            &[med],
+            &HashSet::new(),
+            FhirResourceType::MedicationStatement,
+            "Alex",
+            now(),
+        );
+        let dosage = &first_resource(&bundle)["dosage"][0];
+
+        assert_eq!(dosage["asNeededBoolean"], true);
+        assert_eq!(
+            dosage["text"],
+            "500mg as needed, at least 4 hours apart, at most 3 per day"
+        );
+        assert_eq!(dosage["maxDosePerPeriod"]["numerator"]["value"], 3);
+    }
+}
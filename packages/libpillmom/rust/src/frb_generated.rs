@@ -132,7 +132,7 @@ fn wire__crate__api__PillMomApi_create_medication_impl(
             let api_description = <String>::sse_decode(&mut deserializer);
             deserializer.end();
             move |context| async move {
-                transform_result_sse::<_, flutter_rust_bridge::for_generated::anyhow::Error>(
+                transform_result_sse::<_, crate::error::PillMomError>(
                     (move || async move {
                         let mut api_that_guard = None;
                         let decode_indices_ =
@@ -191,13 +191,13 @@ fn wire__crate__api__PillMomApi_create_reminder_impl(
             let api_that = <RustOpaqueMoi<
                 flutter_rust_bridge::for_generated::RustAutoOpaqueInner<PillMomApi>,
             >>::sse_decode(&mut deserializer);
-            let api_medication_id = <i64>::sse_decode(&mut deserializer);
+            let api_medication_id = <String>::sse_decode(&mut deserializer);
             let api_time = <String>::sse_decode(&mut deserializer);
             let api_days = <String>::sse_decode(&mut deserializer);
             let api_is_active = <bool>::sse_decode(&mut deserializer);
             deserializer.end();
             move |context| async move {
-                transform_result_sse::<_, flutter_rust_bridge::for_generated::anyhow::Error>(
+                transform_result_sse::<_, crate::error::PillMomError>(
                     (move || async move {
                         let mut api_that_guard = None;
                         let decode_indices_ =
@@ -257,10 +257,10 @@ fn wire__crate__api__PillMomApi_delete_medication_impl(
             let api_that = <RustOpaqueMoi<
                 flutter_rust_bridge::for_generated::RustAutoOpaqueInner<PillMomApi>,
             >>::sse_decode(&mut deserializer);
-            let api_id = <i64>::sse_decode(&mut deserializer);
+            let api_id = <String>::sse_decode(&mut deserializer);
             deserializer.end();
             move |context| async move {
-                transform_result_sse::<_, flutter_rust_bridge::for_generated::anyhow::Error>(
+                transform_result_sse::<_, crate::error::PillMomError>(
                     (move || async move {
                         let mut api_that_guard = None;
                         let decode_indices_ =
@@ -315,10 +315,10 @@ fn wire__crate__api__PillMomApi_delete_reminder_impl(
             let api_that = <RustOpaqueMoi<
                 flutter_rust_bridge::for_generated::RustAutoOpaqueInner<PillMomApi>,
             >>::sse_decode(&mut deserializer);
-            let api_id = <i64>::sse_decode(&mut deserializer);
+            let api_id = <String>::sse_decode(&mut deserializer);
             deserializer.end();
             move |context| async move {
-                transform_result_sse::<_, flutter_rust_bridge::for_generated::anyhow::Error>(
+                transform_result_sse::<_, crate::error::PillMomError>(
                     (move || async move {
                         let mut api_that_guard = None;
                         let decode_indices_ =
@@ -638,6 +638,7 @@ fn wire__crate__api__PillMomApi_open_embedded_replica_impl(
             let api_url = <String>::sse_decode(&mut deserializer);
             let api_auth_token = <String>::sse_decode(&mut deserializer);
             let api__sync_period = <Option<f64>>::sse_decode(&mut deserializer);
+            let api_options = <crate::models::OpenOptions>::sse_decode(&mut deserializer);
             deserializer.end();
             move |context| async move {
                 transform_result_sse::<_, flutter_rust_bridge::for_generated::anyhow::Error>(
@@ -665,6 +666,7 @@ fn wire__crate__api__PillMomApi_open_embedded_replica_impl(
                             api_url,
                             api_auth_token,
                             api__sync_period,
+                            api_options,
                         )
                         .await?;
                         Ok(output_ok)
@@ -700,6 +702,7 @@ fn wire__crate__api__PillMomApi_open_in_memory_impl(
             let api_that = <RustOpaqueMoi<
                 flutter_rust_bridge::for_generated::RustAutoOpaqueInner<PillMomApi>,
             >>::sse_decode(&mut deserializer);
+            let api_options = <crate::models::OpenOptions>::sse_decode(&mut deserializer);
             deserializer.end();
             move |context| async move {
                 transform_result_sse::<_, flutter_rust_bridge::for_generated::anyhow::Error>(
@@ -722,7 +725,8 @@ fn wire__crate__api__PillMomApi_open_in_memory_impl(
                         }
                         let api_that_guard = api_that_guard.unwrap();
                         let output_ok =
-                            crate::api::PillMomApi::open_in_memory(&*api_that_guard).await?;
+                            crate::api::PillMomApi::open_in_memory(&*api_that_guard, api_options)
+                                .await?;
                         Ok(output_ok)
                     })()
                     .await,
@@ -757,6 +761,7 @@ fn wire__crate__api__PillMomApi_open_local_impl(
                 flutter_rust_bridge::for_generated::RustAutoOpaqueInner<PillMomApi>,
             >>::sse_decode(&mut deserializer);
             let api_path = <String>::sse_decode(&mut deserializer);
+            let api_options = <crate::models::OpenOptions>::sse_decode(&mut deserializer);
             deserializer.end();
             move |context| async move {
                 transform_result_sse::<_, flutter_rust_bridge::for_generated::anyhow::Error>(
@@ -778,8 +783,12 @@ fn wire__crate__api__PillMomApi_open_local_impl(
                             }
                         }
                         let api_that_guard = api_that_guard.unwrap();
-                        let output_ok =
-                            crate::api::PillMomApi::open_local(&*api_that_guard, api_path).await?;
+                        let output_ok = crate::api::PillMomApi::open_local(
+                            &*api_that_guard,
+                            api_path,
+                            api_options,
+                        )
+                        .await?;
                         Ok(output_ok)
                     })()
                     .await,
@@ -815,6 +824,7 @@ fn wire__crate__api__PillMomApi_open_remote_impl(
             >>::sse_decode(&mut deserializer);
             let api_url = <String>::sse_decode(&mut deserializer);
             let api_auth_token = <String>::sse_decode(&mut deserializer);
+            let api_options = <crate::models::OpenOptions>::sse_decode(&mut deserializer);
             deserializer.end();
             move |context| async move {
                 transform_result_sse::<_, flutter_rust_bridge::for_generated::anyhow::Error>(
@@ -840,6 +850,7 @@ fn wire__crate__api__PillMomApi_open_remote_impl(
                             &*api_that_guard,
                             api_url,
                             api_auth_token,
+                            api_options,
                         )
                         .await?;
                         Ok(output_ok)
@@ -932,9 +943,11 @@ fn wire__crate__api__PillMomApi_update_medication_impl(
                 flutter_rust_bridge::for_generated::RustAutoOpaqueInner<PillMomApi>,
             >>::sse_decode(&mut deserializer);
             let api_medication = <crate::models::Medication>::sse_decode(&mut deserializer);
+            let api_policy = <crate::models::MergePolicy>::sse_decode(&mut deserializer);
+            let api_base = <Option<crate::models::Medication>>::sse_decode(&mut deserializer);
             deserializer.end();
             move |context| async move {
-                transform_result_sse::<_, flutter_rust_bridge::for_generated::anyhow::Error>(
+                transform_result_sse::<_, crate::error::PillMomError>(
                     (move || async move {
                         let mut api_that_guard = None;
                         let decode_indices_ =
@@ -956,6 +969,8 @@ fn wire__crate__api__PillMomApi_update_medication_impl(
                         let output_ok = crate::api::PillMomApi::update_medication(
                             &*api_that_guard,
                             api_medication,
+                            api_policy,
+                            api_base,
                         )
                         .await?;
                         Ok(output_ok)
@@ -992,9 +1007,11 @@ fn wire__crate__api__PillMomApi_update_reminder_impl(
                 flutter_rust_bridge::for_generated::RustAutoOpaqueInner<PillMomApi>,
             >>::sse_decode(&mut deserializer);
             let api_reminder = <crate::models::Reminder>::sse_decode(&mut deserializer);
+            let api_policy = <crate::models::MergePolicy>::sse_decode(&mut deserializer);
+            let api_base = <Option<crate::models::Reminder>>::sse_decode(&mut deserializer);
             deserializer.end();
             move |context| async move {
-                transform_result_sse::<_, flutter_rust_bridge::for_generated::anyhow::Error>(
+                transform_result_sse::<_, crate::error::PillMomError>(
                     (move || async move {
                         let mut api_that_guard = None;
                         let decode_indices_ =
@@ -1013,9 +1030,13 @@ fn wire__crate__api__PillMomApi_update_reminder_impl(
                             }
                         }
                         let api_that_guard = api_that_guard.unwrap();
-                        let output_ok =
-                            crate::api::PillMomApi::update_reminder(&*api_that_guard, api_reminder)
-                                .await?;
+                        let output_ok = crate::api::PillMomApi::update_reminder(
+                            &*api_that_guard,
+                            api_reminder,
+                            api_policy,
+                            api_base,
+                        )
+                        .await?;
                         Ok(output_ok)
                     })()
                     .await,
@@ -1070,6 +1091,19 @@ impl SseDecode for flutter_rust_bridge::for_generated::anyhow::Error {
     }
 }
 
+impl SseDecode for chrono::DateTime<chrono::Utc> {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    fn sse_decode(deserializer: &mut flutter_rust_bridge::for_generated::SseDeserializer) -> Self {
+        let mut inner = <i64>::sse_decode(deserializer);
+        return chrono::DateTime::<chrono::Utc>::from_naive_utc_and_offset(
+            chrono::DateTime::from_timestamp_micros(inner)
+                .expect("invalid or out-of-range datetime")
+                .naive_utc(),
+            chrono::Utc,
+        );
+    }
+}
+
 impl SseDecode for PillMomApi {
     // Codec=Sse (Serialization based), see doc to use other codecs
     fn sse_decode(deserializer: &mut flutter_rust_bridge::for_generated::SseDeserializer) -> Self {
@@ -1155,30 +1189,248 @@ impl SseDecode for Vec<crate::models::Reminder> {
     }
 }
 
+impl SseDecode for crate::models::JournalMode {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    fn sse_decode(deserializer: &mut flutter_rust_bridge::for_generated::SseDeserializer) -> Self {
+        let mut inner = <i32>::sse_decode(deserializer);
+        return match inner {
+            0 => crate::models::JournalMode::Delete,
+            1 => crate::models::JournalMode::Truncate,
+            2 => crate::models::JournalMode::Persist,
+            3 => crate::models::JournalMode::Wal,
+            _ => unreachable!("Invalid variant for JournalMode: {}", inner),
+        };
+    }
+}
+
+impl SseDecode for crate::models::OpenOptions {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    fn sse_decode(deserializer: &mut flutter_rust_bridge::for_generated::SseDeserializer) -> Self {
+        let mut var_journalMode = <Option<crate::models::JournalMode>>::sse_decode(deserializer);
+        let mut var_busyTimeoutMs = <Option<i64>>::sse_decode(deserializer);
+        let mut var_synchronous = <Option<crate::models::SynchronousMode>>::sse_decode(deserializer);
+        let mut var_cacheSizeKib = <Option<i64>>::sse_decode(deserializer);
+        let mut var_readOnly = <bool>::sse_decode(deserializer);
+        return crate::models::OpenOptions {
+            journal_mode: var_journalMode,
+            busy_timeout_ms: var_busyTimeoutMs,
+            synchronous: var_synchronous,
+            cache_size_kib: var_cacheSizeKib,
+            read_only: var_readOnly,
+        };
+    }
+}
+
+impl SseDecode for crate::models::SynchronousMode {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    fn sse_decode(deserializer: &mut flutter_rust_bridge::for_generated::SseDeserializer) -> Self {
+        let mut inner = <i32>::sse_decode(deserializer);
+        return match inner {
+            0 => crate::models::SynchronousMode::Off,
+            1 => crate::models::SynchronousMode::Normal,
+            2 => crate::models::SynchronousMode::Full,
+            3 => crate::models::SynchronousMode::Extra,
+            _ => unreachable!("Invalid variant for SynchronousMode: {}", inner),
+        };
+    }
+}
+
+impl SseDecode for crate::models::CreatedMedication {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    fn sse_decode(deserializer: &mut flutter_rust_bridge::for_generated::SseDeserializer) -> Self {
+        let mut var_id = <String>::sse_decode(deserializer);
+        let mut var_warnings = <Vec<crate::models::InteractionWarning>>::sse_decode(deserializer);
+        let mut var_interactionsChecked = <bool>::sse_decode(deserializer);
+        return crate::models::CreatedMedication {
+            id: var_id,
+            warnings: var_warnings,
+            interactions_checked: var_interactionsChecked,
+        };
+    }
+}
+
+impl SseDecode for crate::models::InteractionSeverity {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    fn sse_decode(deserializer: &mut flutter_rust_bridge::for_generated::SseDeserializer) -> Self {
+        let mut inner = <i32>::sse_decode(deserializer);
+        return match inner {
+            0 => crate::models::InteractionSeverity::Minor,
+            1 => crate::models::InteractionSeverity::Moderate,
+            2 => crate::models::InteractionSeverity::Major,
+            3 => crate::models::InteractionSeverity::Contraindicated,
+            _ => unreachable!("Invalid variant for InteractionSeverity: {}", inner),
+        };
+    }
+}
+
+impl SseDecode for crate::models::InteractionWarning {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    fn sse_decode(deserializer: &mut flutter_rust_bridge::for_generated::SseDeserializer) -> Self {
+        let mut var_medicationId = <String>::sse_decode(deserializer);
+        let mut var_medicationName = <String>::sse_decode(deserializer);
+        let mut var_ingredient = <String>::sse_decode(deserializer);
+        let mut var_otherIngredient = <String>::sse_decode(deserializer);
+        let mut var_severity = <crate::models::InteractionSeverity>::sse_decode(deserializer);
+        let mut var_description = <String>::sse_decode(deserializer);
+        return crate::models::InteractionWarning {
+            medication_id: var_medicationId,
+            medication_name: var_medicationName,
+            ingredient: var_ingredient,
+            other_ingredient: var_otherIngredient,
+            severity: var_severity,
+            description: var_description,
+        };
+    }
+}
+
+impl SseDecode for Vec<crate::models::InteractionWarning> {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    fn sse_decode(deserializer: &mut flutter_rust_bridge::for_generated::SseDeserializer) -> Self {
+        let mut len_ = <i32>::sse_decode(deserializer);
+        let mut ans_ = vec![];
+        for idx_ in 0..len_ {
+            ans_.push(<crate::models::InteractionWarning>::sse_decode(deserializer));
+        }
+        return ans_;
+    }
+}
+
 impl SseDecode for crate::models::Medication {
     // Codec=Sse (Serialization based), see doc to use other codecs
     fn sse_decode(deserializer: &mut flutter_rust_bridge::for_generated::SseDeserializer) -> Self {
-        let mut var_id = <Option<i64>>::sse_decode(deserializer);
+        let mut var_id = <Option<String>>::sse_decode(deserializer);
+        let mut var_profileId = <Option<String>>::sse_decode(deserializer);
         let mut var_name = <String>::sse_decode(deserializer);
         let mut var_dosage = <String>::sse_decode(deserializer);
         let mut var_description = <String>::sse_decode(deserializer);
-        let mut var_createdAt = <String>::sse_decode(deserializer);
-        let mut var_updatedAt = <String>::sse_decode(deserializer);
-        let mut var_deletedAt = <Option<String>>::sse_decode(deserializer);
+        let mut var_rxnormCui = <Option<String>>::sse_decode(deserializer);
+        let mut var_ndc = <Option<String>>::sse_decode(deserializer);
+        let mut var_atcCode = <Option<String>>::sse_decode(deserializer);
+        let mut var_isPrn = <bool>::sse_decode(deserializer);
+        let mut var_minHoursBetweenDoses = <Option<f64>>::sse_decode(deserializer);
+        let mut var_maxDailyDoses = <Option<i64>>::sse_decode(deserializer);
+        let mut var_courseStartDate = <Option<String>>::sse_decode(deserializer);
+        let mut var_courseEndDate = <Option<String>>::sse_decode(deserializer);
+        let mut var_courseTotalDoses = <Option<i64>>::sse_decode(deserializer);
+        let mut var_createdAt = <chrono::DateTime<chrono::Utc>>::sse_decode(deserializer);
+        let mut var_updatedAt = <chrono::DateTime<chrono::Utc>>::sse_decode(deserializer);
+        let mut var_deletedAt = <Option<chrono::DateTime<chrono::Utc>>>::sse_decode(deserializer);
+        let mut var_version = <i64>::sse_decode(deserializer);
         let mut var_reminders = <Vec<crate::models::Reminder>>::sse_decode(deserializer);
         return crate::models::Medication {
             id: var_id,
+            profile_id: var_profileId,
             name: var_name,
             dosage: var_dosage,
             description: var_description,
+            rxnorm_cui: var_rxnormCui,
+            ndc: var_ndc,
+            atc_code: var_atcCode,
+            is_prn: var_isPrn,
+            min_hours_between_doses: var_minHoursBetweenDoses,
+            max_daily_doses: var_maxDailyDoses,
+            course_start_date: var_courseStartDate,
+            course_end_date: var_courseEndDate,
+            course_total_doses: var_courseTotalDoses,
             created_at: var_createdAt,
             updated_at: var_updatedAt,
             deleted_at: var_deletedAt,
+            version: var_version,
             reminders: var_reminders,
         };
     }
 }
 
+impl SseDecode for Option<chrono::DateTime<chrono::Utc>> {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    fn sse_decode(deserializer: &mut flutter_rust_bridge::for_generated::SseDeserializer) -> Self {
+        if (<bool>::sse_decode(deserializer)) {
+            return Some(<chrono::DateTime<chrono::Utc>>::sse_decode(deserializer));
+        } else {
+            return None;
+        }
+    }
+}
+
+impl SseDecode for crate::models::MergePolicy {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    fn sse_decode(deserializer: &mut flutter_rust_bridge::for_generated::SseDeserializer) -> Self {
+        let mut inner = <i32>::sse_decode(deserializer);
+        return match inner {
+            0 => crate::models::MergePolicy::Reject,
+            1 => crate::models::MergePolicy::LastWriterWins,
+            2 => crate::models::MergePolicy::FieldLevelMerge,
+            _ => unreachable!("Invalid variant for MergePolicy: {}", inner),
+        };
+    }
+}
+
+impl SseDecode for Option<crate::models::Medication> {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    fn sse_decode(deserializer: &mut flutter_rust_bridge::for_generated::SseDeserializer) -> Self {
+        if (<bool>::sse_decode(deserializer)) {
+            return Some(<crate::models::Medication>::sse_decode(deserializer));
+        } else {
+            return None;
+        }
+    }
+}
+
+impl SseDecode for Option<crate::models::Reminder> {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    fn sse_decode(deserializer: &mut flutter_rust_bridge::for_generated::SseDeserializer) -> Self {
+        if (<bool>::sse_decode(deserializer)) {
+            return Some(<crate::models::Reminder>::sse_decode(deserializer));
+        } else {
+            return None;
+        }
+    }
+}
+
+impl SseDecode for crate::error::PillMomError {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    fn sse_decode(deserializer: &mut flutter_rust_bridge::for_generated::SseDeserializer) -> Self {
+        let mut tag_ = <i32>::sse_decode(deserializer);
+        match tag_ {
+            0 => {
+                let mut var_current = <crate::models::Medication>::sse_decode(deserializer);
+                return crate::error::PillMomError::MedicationConflict {
+                    current: Box::new(var_current),
+                };
+            }
+            1 => {
+                let mut var_current = <crate::models::Reminder>::sse_decode(deserializer);
+                return crate::error::PillMomError::ReminderConflict {
+                    current: var_current,
+                };
+            }
+            2 => {
+                return crate::error::PillMomError::ReadOnly;
+            }
+            3 => {
+                let mut var_message = <String>::sse_decode(deserializer);
+                return crate::error::PillMomError::Other {
+                    message: var_message,
+                };
+            }
+            _ => {
+                unimplemented!("");
+            }
+        }
+    }
+}
+
+impl SseDecode for Option<crate::models::JournalMode> {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    fn sse_decode(deserializer: &mut flutter_rust_bridge::for_generated::SseDeserializer) -> Self {
+        if (<bool>::sse_decode(deserializer)) {
+            return Some(<crate::models::JournalMode>::sse_decode(deserializer));
+        } else {
+            return None;
+        }
+    }
+}
+
 impl SseDecode for Option<String> {
     // Codec=Sse (Serialization based), see doc to use other codecs
     fn sse_decode(deserializer: &mut flutter_rust_bridge::for_generated::SseDeserializer) -> Self {
@@ -1212,17 +1464,29 @@ impl SseDecode for Option<i64> {
     }
 }
 
+impl SseDecode for Option<crate::models::SynchronousMode> {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    fn sse_decode(deserializer: &mut flutter_rust_bridge::for_generated::SseDeserializer) -> Self {
+        if (<bool>::sse_decode(deserializer)) {
+            return Some(<crate::models::SynchronousMode>::sse_decode(deserializer));
+        } else {
+            return None;
+        }
+    }
+}
+
 impl SseDecode for crate::models::Reminder {
     // Codec=Sse (Serialization based), see doc to use other codecs
     fn sse_decode(deserializer: &mut flutter_rust_bridge::for_generated::SseDeserializer) -> Self {
-        let mut var_id = <Option<i64>>::sse_decode(deserializer);
-        let mut var_medicationId = <i64>::sse_decode(deserializer);
+        let mut var_id = <Option<String>>::sse_decode(deserializer);
+        let mut var_medicationId = <String>::sse_decode(deserializer);
         let mut var_time = <String>::sse_decode(deserializer);
         let mut var_days = <String>::sse_decode(deserializer);
         let mut var_isActive = <bool>::sse_decode(deserializer);
-        let mut var_createdAt = <String>::sse_decode(deserializer);
-        let mut var_updatedAt = <String>::sse_decode(deserializer);
-        let mut var_deletedAt = <Option<String>>::sse_decode(deserializer);
+        let mut var_createdAt = <chrono::DateTime<chrono::Utc>>::sse_decode(deserializer);
+        let mut var_updatedAt = <chrono::DateTime<chrono::Utc>>::sse_decode(deserializer);
+        let mut var_deletedAt = <Option<chrono::DateTime<chrono::Utc>>>::sse_decode(deserializer);
+        let mut var_version = <i64>::sse_decode(deserializer);
         return crate::models::Reminder {
             id: var_id,
             medication_id: var_medicationId,
@@ -1232,6 +1496,7 @@ impl SseDecode for crate::models::Reminder {
             created_at: var_createdAt,
             updated_at: var_updatedAt,
             deleted_at: var_deletedAt,
+            version: var_version,
         };
     }
 }
@@ -1345,12 +1610,23 @@ impl flutter_rust_bridge::IntoDart for crate::models::Medication {
     fn into_dart(self) -> flutter_rust_bridge::for_generated::DartAbi {
         [
             self.id.into_into_dart().into_dart(),
+            self.profile_id.into_into_dart().into_dart(),
             self.name.into_into_dart().into_dart(),
             self.dosage.into_into_dart().into_dart(),
             self.description.into_into_dart().into_dart(),
+            self.rxnorm_cui.into_into_dart().into_dart(),
+            self.ndc.into_into_dart().into_dart(),
+            self.atc_code.into_into_dart().into_dart(),
+            self.is_prn.into_into_dart().into_dart(),
+            self.min_hours_between_doses.into_into_dart().into_dart(),
+            self.max_daily_doses.into_into_dart().into_dart(),
+            self.course_start_date.into_into_dart().into_dart(),
+            self.course_end_date.into_into_dart().into_dart(),
+            self.course_total_doses.into_into_dart().into_dart(),
             self.created_at.into_into_dart().into_dart(),
             self.updated_at.into_into_dart().into_dart(),
             self.deleted_at.into_into_dart().into_dart(),
+            self.version.into_into_dart().into_dart(),
             self.reminders.into_into_dart().into_dart(),
         ]
         .into_dart()
@@ -1374,6 +1650,7 @@ impl flutter_rust_bridge::IntoDart for crate::models::Reminder {
             self.created_at.into_into_dart().into_dart(),
             self.updated_at.into_into_dart().into_dart(),
             self.deleted_at.into_into_dart().into_dart(),
+            self.version.into_into_dart().into_dart(),
         ]
         .into_dart()
     }
@@ -1392,6 +1669,13 @@ impl SseEncode for flutter_rust_bridge::for_generated::anyhow::Error {
     }
 }
 
+impl SseEncode for chrono::DateTime<chrono::Utc> {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    fn sse_encode(self, serializer: &mut flutter_rust_bridge::for_generated::SseSerializer) {
+        <i64>::sse_encode(self.timestamp_micros(), serializer);
+    }
+}
+
 impl SseEncode for PillMomApi {
     // Codec=Sse (Serialization based), see doc to use other codecs
     fn sse_encode(self, serializer: &mut flutter_rust_bridge::for_generated::SseSerializer) {
@@ -1468,20 +1752,210 @@ impl SseEncode for Vec<crate::models::Reminder> {
     }
 }
 
+impl SseEncode for crate::models::JournalMode {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    fn sse_encode(self, serializer: &mut flutter_rust_bridge::for_generated::SseSerializer) {
+        <i32>::sse_encode(
+            match self {
+                crate::models::JournalMode::Delete => 0,
+                crate::models::JournalMode::Truncate => 1,
+                crate::models::JournalMode::Persist => 2,
+                crate::models::JournalMode::Wal => 3,
+                _ => {
+                    unimplemented!("");
+                }
+            },
+            serializer,
+        );
+    }
+}
+
+impl SseEncode for crate::models::OpenOptions {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    fn sse_encode(self, serializer: &mut flutter_rust_bridge::for_generated::SseSerializer) {
+        <Option<crate::models::JournalMode>>::sse_encode(self.journal_mode, serializer);
+        <Option<i64>>::sse_encode(self.busy_timeout_ms, serializer);
+        <Option<crate::models::SynchronousMode>>::sse_encode(self.synchronous, serializer);
+        <Option<i64>>::sse_encode(self.cache_size_kib, serializer);
+        <bool>::sse_encode(self.read_only, serializer);
+    }
+}
+
+impl SseEncode for crate::models::SynchronousMode {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    fn sse_encode(self, serializer: &mut flutter_rust_bridge::for_generated::SseSerializer) {
+        <i32>::sse_encode(
+            match self {
+                crate::models::SynchronousMode::Off => 0,
+                crate::models::SynchronousMode::Normal => 1,
+                crate::models::SynchronousMode::Full => 2,
+                crate::models::SynchronousMode::Extra => 3,
+                _ => {
+                    unimplemented!("");
+                }
+            },
+            serializer,
+        );
+    }
+}
+
+impl SseEncode for crate::models::CreatedMedication {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    fn sse_encode(self, serializer: &mut flutter_rust_bridge::for_generated::SseSerializer) {
+        <String>::sse_encode(self.id, serializer);
+        <Vec<crate::models::InteractionWarning>>::sse_encode(self.warnings, serializer);
+        <bool>::sse_encode(self.interactions_checked, serializer);
+    }
+}
+
+impl SseEncode for crate::models::InteractionSeverity {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    fn sse_encode(self, serializer: &mut flutter_rust_bridge::for_generated::SseSerializer) {
+        <i32>::sse_encode(
+            match self {
+                crate::models::InteractionSeverity::Minor => 0,
+                crate::models::InteractionSeverity::Moderate => 1,
+                crate::models::InteractionSeverity::Major => 2,
+                crate::models::InteractionSeverity::Contraindicated => 3,
+                _ => {
+                    unimplemented!("");
+                }
+            },
+            serializer,
+        );
+    }
+}
+
+impl SseEncode for crate::models::InteractionWarning {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    fn sse_encode(self, serializer: &mut flutter_rust_bridge::for_generated::SseSerializer) {
+        <String>::sse_encode(self.medication_id, serializer);
+        <String>::sse_encode(self.medication_name, serializer);
+        <String>::sse_encode(self.ingredient, serializer);
+        <String>::sse_encode(self.other_ingredient, serializer);
+        <crate::models::InteractionSeverity>::sse_encode(self.severity, serializer);
+        <String>::sse_encode(self.description, serializer);
+    }
+}
+
+impl SseEncode for Vec<crate::models::InteractionWarning> {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    fn sse_encode(self, serializer: &mut flutter_rust_bridge::for_generated::SseSerializer) {
+        <i32>::sse_encode(self.len() as _, serializer);
+        for item in self {
+            <crate::models::InteractionWarning>::sse_encode(item, serializer);
+        }
+    }
+}
+
 impl SseEncode for crate::models::Medication {
     // Codec=Sse (Serialization based), see doc to use other codecs
     fn sse_encode(self, serializer: &mut flutter_rust_bridge::for_generated::SseSerializer) {
-        <Option<i64>>::sse_encode(self.id, serializer);
+        <Option<String>>::sse_encode(self.id, serializer);
+        <Option<String>>::sse_encode(self.profile_id, serializer);
         <String>::sse_encode(self.name, serializer);
         <String>::sse_encode(self.dosage, serializer);
         <String>::sse_encode(self.description, serializer);
-        <String>::sse_encode(self.created_at, serializer);
-        <String>::sse_encode(self.updated_at, serializer);
-        <Option<String>>::sse_encode(self.deleted_at, serializer);
+        <Option<String>>::sse_encode(self.rxnorm_cui, serializer);
+        <Option<String>>::sse_encode(self.ndc, serializer);
+        <Option<String>>::sse_encode(self.atc_code, serializer);
+        <bool>::sse_encode(self.is_prn, serializer);
+        <Option<f64>>::sse_encode(self.min_hours_between_doses, serializer);
+        <Option<i64>>::sse_encode(self.max_daily_doses, serializer);
+        <Option<String>>::sse_encode(self.course_start_date, serializer);
+        <Option<String>>::sse_encode(self.course_end_date, serializer);
+        <Option<i64>>::sse_encode(self.course_total_doses, serializer);
+        <chrono::DateTime<chrono::Utc>>::sse_encode(self.created_at, serializer);
+        <chrono::DateTime<chrono::Utc>>::sse_encode(self.updated_at, serializer);
+        <Option<chrono::DateTime<chrono::Utc>>>::sse_encode(self.deleted_at, serializer);
+        <i64>::sse_encode(self.version, serializer);
         <Vec<crate::models::Reminder>>::sse_encode(self.reminders, serializer);
     }
 }
 
+impl SseEncode for Option<chrono::DateTime<chrono::Utc>> {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    fn sse_encode(self, serializer: &mut flutter_rust_bridge::for_generated::SseSerializer) {
+        <bool>::sse_encode(self.is_some(), serializer);
+        if let Some(value) = self {
+            <chrono::DateTime<chrono::Utc>>::sse_encode(value, serializer);
+        }
+    }
+}
+
+impl SseEncode for crate::models::MergePolicy {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    fn sse_encode(self, serializer: &mut flutter_rust_bridge::for_generated::SseSerializer) {
+        <i32>::sse_encode(
+            match self {
+                crate::models::MergePolicy::Reject => 0,
+                crate::models::MergePolicy::LastWriterWins => 1,
+                crate::models::MergePolicy::FieldLevelMerge => 2,
+                _ => {
+                    unimplemented!("");
+                }
+            },
+            serializer,
+        );
+    }
+}
+
+impl SseEncode for Option<crate::models::Medication> {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    fn sse_encode(self, serializer: &mut flutter_rust_bridge::for_generated::SseSerializer) {
+        <bool>::sse_encode(self.is_some(), serializer);
+        if let Some(value) = self {
+            <crate::models::Medication>::sse_encode(value, serializer);
+        }
+    }
+}
+
+impl SseEncode for Option<crate::models::Reminder> {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    fn sse_encode(self, serializer: &mut flutter_rust_bridge::for_generated::SseSerializer) {
+        <bool>::sse_encode(self.is_some(), serializer);
+        if let Some(value) = self {
+            <crate::models::Reminder>::sse_encode(value, serializer);
+        }
+    }
+}
+
+impl SseEncode for crate::error::PillMomError {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    fn sse_encode(self, serializer: &mut flutter_rust_bridge::for_generated::SseSerializer) {
+        match self {
+            crate::error::PillMomError::MedicationConflict { current } => {
+                <i32>::sse_encode(0, serializer);
+                <crate::models::Medication>::sse_encode(*current, serializer);
+            }
+            crate::error::PillMomError::ReminderConflict { current } => {
+                <i32>::sse_encode(1, serializer);
+                <crate::models::Reminder>::sse_encode(current, serializer);
+            }
+            crate::error::PillMomError::ReadOnly => {
+                <i32>::sse_encode(2, serializer);
+            }
+            crate::error::PillMomError::Other { message } => {
+                <i32>::sse_encode(3, serializer);
+                <String>::sse_encode(message, serializer);
+            }
+            _ => {
+                unimplemented!("");
+            }
+        }
+    }
+}
+
+impl SseEncode for Option<crate::models::JournalMode> {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    fn sse_encode(self, serializer: &mut flutter_rust_bridge::for_generated::SseSerializer) {
+        <bool>::sse_encode(self.is_some(), serializer);
+        if let Some(value) = self {
+            <crate::models::JournalMode>::sse_encode(value, serializer);
+        }
+    }
+}
+
 impl SseEncode for Option<String> {
     // Codec=Sse (Serialization based), see doc to use other codecs
     fn sse_encode(self, serializer: &mut flutter_rust_bridge::for_generated::SseSerializer) {
@@ -1512,17 +1986,28 @@ impl SseEncode for Option<i64> {
     }
 }
 
+impl SseEncode for Option<crate::models::SynchronousMode> {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    fn sse_encode(self, serializer: &mut flutter_rust_bridge::for_generated::SseSerializer) {
+        <bool>::sse_encode(self.is_some(), serializer);
+        if let Some(value) = self {
+            <crate::models::SynchronousMode>::sse_encode(value, serializer);
+        }
+    }
+}
+
 impl SseEncode for crate::models::Reminder {
     // Codec=Sse (Serialization based), see doc to use other codecs
     fn sse_encode(self, serializer: &mut flutter_rust_bridge::for_generated::SseSerializer) {
-        <Option<i64>>::sse_encode(self.id, serializer);
-        <i64>::sse_encode(self.medication_id, serializer);
+        <Option<String>>::sse_encode(self.id, serializer);
+        <String>::sse_encode(self.medication_id, serializer);
         <String>::sse_encode(self.time, serializer);
         <String>::sse_encode(self.days, serializer);
         <bool>::sse_encode(self.is_active, serializer);
-        <String>::sse_encode(self.created_at, serializer);
-        <String>::sse_encode(self.updated_at, serializer);
-        <Option<String>>::sse_encode(self.deleted_at, serializer);
+        <chrono::DateTime<chrono::Utc>>::sse_encode(self.created_at, serializer);
+        <chrono::DateTime<chrono::Utc>>::sse_encode(self.updated_at, serializer);
+        <Option<chrono::DateTime<chrono::Utc>>>::sse_encode(self.deleted_at, serializer);
+        <i64>::sse_encode(self.version, serializer);
     }
 }
 
@@ -0,0 +1,301 @@
+use crate::models::{Medication, Reminder};
+use crate::repository;
+use crate::schedule::{self, parse_days, parse_time, DaySet};
+use anyhow::Result;
+use chrono::{DateTime, Datelike, Duration, FixedOffset, NaiveDate, NaiveTime, Utc, Weekday};
+
+// iCalendar (RFC 5545) feed of active reminders. Each reminder becomes a
+// recurring VEVENT with a VALARM at the dose time. Times are written as
+// floating local times, so a 08:00 dose stays at 08:00 wherever the
+// calendar is; each event starts on the local day its reminder was created,
+// so the caller gives its UTC offset.
+
+const PRODUCT_ID: &str = "-//PillMom//libpillmom//EN";
+
+pub async fn export_calendar(utc_offset_minutes: i64) -> Result<String> {
+    let offset = schedule::utc_offset(utc_offset_minutes)?;
+    let medications = repository::get_all_medications().await?;
+    Ok(calendar(&medications, Utc::now(), offset))
+}
+
+pub async fn export_calendar_to_path(path: &str, utc_offset_minutes: i64) -> Result<()> {
+    let calendar = export_calendar(utc_offset_minutes).await?;
+    tokio::fs::write(path, calendar)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to write calendar to {}: {}", path, e))
+}
+
+fn calendar(medications: &[Medication], now: DateTime<Utc>, offset: FixedOffset) -> String {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        format!("PRODID:{}", PRODUCT_ID),
+        "CALSCALE:GREGORIAN".to_string(),
+        "METHOD:PUBLISH".to_string(),
+        "X-WR-CALNAME:Medications".to_string(),
+    ];
+
+    for med in medications.iter().filter(|med| med.deleted_at.is_none()) {
+        for reminder in med
+            .reminders
+            .iter()
+            .filter(|reminder| reminder.is_active && reminder.deleted_at.is_none())
+        {
+            match event(med, reminder, now, offset) {
+                Some(event) => lines.extend(event),
+                None => tracing::warn!(
+                    "Reminder {:?} has an unreadable schedule ({} / {}); left out of calendar",
+                    reminder.id,
+                    reminder.time,
+                    reminder.days
+                ),
+            }
+        }
+    }
+
+    lines.push("END:VCALENDAR".to_string());
+
+    let mut output = String::new();
+    for line in lines {
+        output.push_str(&fold(&line));
+        output.push_str("\r\n");
+    }
+    output
+}
+
+/// The VEVENT lines for one reminder, starting on the local day at
+/// `offset` it was created, or `None` if its time or days cannot be read
+fn event(
+    med: &Medication,
+    reminder: &Reminder,
+    now: DateTime<Utc>,
+    offset: FixedOffset,
+) -> Option<Vec<String>> {
+    let id = reminder.id.as_deref()?;
+    let time = parse_time(&reminder.time)?;
+    let days = parse_days(&reminder.days)?;
+
+    let rule = match &days {
+        DaySet::EveryDay => "FREQ=DAILY".to_string(),
+        DaySet::Only(weekdays) => {
+            let codes: Vec<&str> = weekdays.iter().copied().map(day_code).collect();
+            format!("FREQ=WEEKLY;BYDAY={}", codes.join(","))
+        }
+    };
+
+    let created_on = reminder.created_at.with_timezone(&offset).date_naive();
+    let start = first_occurrence(created_on, &days);
+
+    let mut summary = format!("Take {}", med.name);
+    if !med.dosage.is_empty() {
+        summary.push_str(&format!(" ({})", med.dosage));
+    }
+
+    let mut lines = vec![
+        "BEGIN:VEVENT".to_string(),
+        format!("UID:{}@pillmom", id),
+        format!("DTSTAMP:{}", now.format("%Y%m%dT%H%M%SZ")),
+        format!("DTSTART:{}", local_date_time(start, time)),
+        "DURATION:PT15M".to_string(),
+        format!("RRULE:{}", rule),
+        format!("SUMMARY:{}", escape_text(&summary)),
+    ];
+
+    if !med.description.is_empty() {
+        lines.push(format!("DESCRIPTION:{}", escape_text(&med.description)));
+    }
+
+    lines.extend([
+        "BEGIN:VALARM".to_string(),
+        "ACTION:DISPLAY".to_string(),
+        "TRIGGER:PT0M".to_string(),
+        format!("DESCRIPTION:{}", escape_text(&summary)),
+        "END:VALARM".to_string(),
+        "END:VEVENT".to_string(),
+    ]);
+
+    Some(lines)
+}
+
+/// First date on or after `from` that `days` includes. RFC 5545 leaves
+/// the meaning undefined when DTSTART itself does not match the RRULE.
+fn first_occurrence(from: NaiveDate, days: &DaySet) -> NaiveDate {
+    match days {
+        DaySet::EveryDay => from,
+        DaySet::Only(weekdays) => (0..7)
+            .map(|offset| from + Duration::days(offset))
+            .find(|date| weekdays.contains(&date.weekday()))
+            .unwrap_or(from),
+    }
+}
+
+fn local_date_time(date: NaiveDate, time: NaiveTime) -> String {
+    format!("{}T{}", date.format("%Y%m%d"), time.format("%H%M%S"))
+}
+
+/// RFC 5545 weekday code
+fn day_code(day: Weekday) -> &'static str {
+    match day {
+        Weekday::Mon => "MO",
+        Weekday::Tue => "TU",
+        Weekday::Wed => "WE",
+        Weekday::Thu => "TH",
+        Weekday::Fri => "FR",
+        Weekday::Sat => "SA",
+        Weekday::Sun => "SU",
+    }
+}
+
+/// Escape a TEXT value (RFC 5545 section 3.3.11)
+fn escape_text(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            ';' => escaped.push_str("\\;"),
+            ',' => escaped.push_str("\\,"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Fold a content line to at most 75 octets per physical line, without
+/// splitting a UTF-8 character (RFC 5545 section 3.1)
+fn fold(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len() + line.len() / 74 * 3);
+    let mut width = 0;
+    for c in line.chars() {
+        let len = c.len_utf8();
+        if width + len > 75 {
+            folded.push_str("\r\n ");
+            // The leading space of a continuation line counts toward its length
+            width = 1;
+        }
+        folded.push(c);
+        width += len;
+    }
+    folded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn escape_text_escapes_separators() {
+        assert_eq!(
+            escape_text("Take 1; with food, not milk\\juice"),
+            r"Take 1\; with food\, not milk\\juice"
+        );
+        assert_eq!(escape_text("line one\r\nline two"), "line one\\nline two");
+    }
+
+    #[test]
+    fn short_lines_are_not_folded() {
+        let line = "X".repeat(75);
+        assert_eq!(fold(&line), line);
+    }
+
+    #[test]
+    fn long_lines_fold_at_75_octets() {
+        let line = "X".repeat(160);
+        let folded = fold(&line);
+        let parts: Vec<&str> = folded.split("\r\n").collect();
+
+        assert_eq!(parts.len(), 3);
+        assert_eq!(parts[0].len(), 75);
+        assert!(parts[1..].iter().all(|part| part.starts_with(' ')));
+        assert!(parts.iter().all(|part| part.len() <= 75));
+        // Unfolding gives the original line back
+        assert_eq!(folded.replace("\r\n ", ""), line);
+    }
+
+    #[test]
+    fn folding_never_splits_a_character() {
+        // Two-byte characters would straddle octet 75
+        let line = format!("S{}", "é".repeat(60));
+        let folded = fold(&line);
+
+        assert!(folded.split("\r\n").all(|part| part.len() <= 75));
+        assert_eq!(folded.split("\r\n").next().unwrap().len(), 75);
+        assert_eq!(folded.replace("\r\n ", ""), line);
+    }
+
+    fn offset(minutes: i64) -> FixedOffset {
+        schedule::utc_offset(minutes).unwrap()
+    }
+
+    #[test]
+    fn event_for_weekly_reminder() {
+        let med = Medication {
+            name: "Vitamin D".to_string(),
+            dosage: "1000 IU".to_string(),
+            description: "With food, ideally".to_string(),
+            ..Default::default()
+        };
+        // A Wednesday, so the first Monday/Friday occurrence is that Friday
+        let reminder = Reminder {
+            id: Some("0190a5b0-0000-7000-8000-000000000004".to_string()),
+            time: "08:00".to_string(),
+            days: "Mon,Fri".to_string(),
+            created_at: Utc.with_ymd_and_hms(2024, 3, 6, 12, 0, 0).unwrap(),
+            ..Default::default()
+        };
+        let now = Utc.with_ymd_and_hms(2024, 3, 6, 12, 0, 0).unwrap();
+
+        let lines = event(&med, &reminder, now, offset(0)).unwrap();
+
+        assert!(lines.contains(&"UID:0190a5b0-0000-7000-8000-000000000004@pillmom".to_string()));
+        assert!(lines.contains(&"DTSTAMP:20240306T120000Z".to_string()));
+        assert!(lines.contains(&"DTSTART:20240308T080000".to_string()));
+        assert!(lines.contains(&"RRULE:FREQ=WEEKLY;BYDAY=MO,FR".to_string()));
+        assert!(lines.contains(&"SUMMARY:Take Vitamin D (1000 IU)".to_string()));
+        assert!(lines.contains(&"DESCRIPTION:With food\\, ideally".to_string()));
+    }
+
+    #[test]
+    fn unreadable_reminders_have_no_event() {
+        let reminder = Reminder {
+            id: Some("0190a5b0-0000-7000-8000-000000000004".to_string()),
+            time: "noon".to_string(),
+            ..Default::default()
+        };
+        assert!(event(&Medication::default(), &reminder, Utc::now(), offset(0)).is_none());
+    }
+
+    #[test]
+    fn event_starts_on_the_local_day_it_was_created() {
+        let dtstart = |created_at, minutes| {
+            let reminder = Reminder {
+                id: Some("0190a5b0-0000-7000-8000-000000000004".to_string()),
+                time: "08:00".to_string(),
+                days: "Daily".to_string(),
+                created_at,
+                ..Default::default()
+            };
+            event(
+                &Medication::default(),
+                &reminder,
+                created_at,
+                offset(minutes),
+            )
+            .unwrap()
+            .into_iter()
+            .find(|line| line.starts_with("DTSTART:"))
+            .unwrap()
+        };
+
+        // 20:00 on the 6th in New York is already the 7th in UTC
+        let evening = Utc.with_ymd_and_hms(2024, 3, 7, 1, 0, 0).unwrap();
+        assert_eq!(dtstart(evening, -300), "DTSTART:20240306T080000");
+        assert_eq!(dtstart(evening, 0), "DTSTART:20240307T080000");
+
+        // 07:00 on the 7th in Tokyo is still the 6th in UTC
+        let morning = Utc.with_ymd_and_hms(2024, 3, 6, 22, 0, 0).unwrap();
+        assert_eq!(dtstart(morning, 540), "DTSTART:20240307T080000");
+    }
+}
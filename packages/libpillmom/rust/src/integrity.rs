@@ -0,0 +1,557 @@
+use crate::database::{ensure_writable, get_connection};
+use crate::models::{IntegrityIssue, IntegrityIssueKind, IntegrityReport, PhasedSchedule};
+use crate::repository::{format_timestamp, new_record_id, read_phases};
+use crate::schedule::{check_schedule, parse_days, parse_time};
+use anyhow::Result;
+use chrono::Utc;
+use libsql::{params, Connection};
+
+// Checks for damage a killed process or an old client can leave behind:
+// SQLite's own file and foreign key checks, then the invariants the rest
+// of the crate relies on. Repairs only ever remove what cannot be used or
+// deactivate what cannot be scheduled; they never guess at lost data.
+
+/// Tables whose rows can be named in a report
+const RECORD_TABLES: [&str; 4] = ["medications", "reminders", "dose_logs", "phased_schedules"];
+
+/// Check the open database and, with `repair`, fix what can be fixed
+/// safely. Row repairs are written in one transaction. When SQLite reports
+/// damage a reindex cannot fix, the row checks are skipped: the file
+/// should be restored from a backup rather than written to.
+pub async fn check_integrity(repair: bool) -> Result<IntegrityReport> {
+    if repair {
+        ensure_writable()?;
+    }
+
+    let conn = get_connection().ok_or_else(|| anyhow::anyhow!("No database connection"))?;
+    let conn = conn.lock().await;
+
+    let mut issues = corruption_issues(&conn, repair).await?;
+
+    if issues.iter().all(|issue| issue.repaired) {
+        let tx = conn.transaction().await?;
+
+        foreign_key_issues(&tx, repair, &mut issues).await?;
+        orphaned_reminder_issues(&tx, repair, &mut issues).await?;
+        reminder_issues(&tx, repair, &mut issues).await?;
+        schedule_issues(&tx, repair, &mut issues).await?;
+        missing_id_issues(&tx, repair, &mut issues).await?;
+
+        if repair {
+            tx.commit().await?;
+        } else {
+            tx.rollback().await?;
+        }
+    }
+
+    Ok(IntegrityReport {
+        repair,
+        healthy: issues.iter().all(|issue| issue.repaired),
+        issues,
+    })
+}
+
+// ===== SQLite Checks =====
+
+/// Problems reported by `PRAGMA integrity_check`. Damaged indexes can be
+/// rebuilt from their tables, so in repair mode a `REINDEX` is tried and
+/// the problems that go away are marked repaired.
+async fn corruption_issues(conn: &Connection, repair: bool) -> Result<Vec<IntegrityIssue>> {
+    let problems = integrity_check(conn).await?;
+
+    let remaining = if repair && problems.iter().any(|problem| problem.contains("index")) {
+        conn.execute("REINDEX", ()).await?;
+        integrity_check(conn).await?
+    } else {
+        problems.clone()
+    };
+
+    Ok(problems
+        .into_iter()
+        .map(|problem| IntegrityIssue {
+            kind: IntegrityIssueKind::Corruption,
+            table: None,
+            record_id: None,
+            repaired: !remaining.contains(&problem),
+            message: problem,
+        })
+        .collect())
+}
+
+async fn integrity_check(conn: &Connection) -> Result<Vec<String>> {
+    let mut problems = Vec::new();
+    let mut rows = conn.query("PRAGMA integrity_check", ()).await?;
+    while let Some(row) = rows.next().await? {
+        let message: String = row.get(0)?;
+        if message != "ok" {
+            problems.push(message);
+        }
+    }
+    Ok(problems)
+}
+
+/// Rows whose parent is missing. A reminder without a medication is
+/// deleted, as is a dose or a phased schedule without one, and a phase
+/// without its schedule; a dose whose reminder is gone keeps its
+/// medication and loses the link.
+async fn foreign_key_issues(
+    conn: &Connection,
+    repair: bool,
+    issues: &mut Vec<IntegrityIssue>,
+) -> Result<()> {
+    let mut violations = Vec::new();
+    let mut rows = conn.query("PRAGMA foreign_key_check", ()).await?;
+    while let Some(row) = rows.next().await? {
+        violations.push((
+            row.get::<String>(0)?,
+            row.get::<Option<i64>>(1)?,
+            row.get::<String>(2)?,
+        ));
+    }
+
+    for (table, rowid, parent) in violations {
+        let record_id = match rowid {
+            Some(rowid) => record_id(conn, &table, rowid).await?,
+            None => None,
+        };
+
+        let fix = match (table.as_str(), parent.as_str(), rowid) {
+            ("reminders", "medications", Some(rowid)) => Some(vec![
+                (
+                    "UPDATE dose_logs SET reminder_id = NULL WHERE reminder_id = ?",
+                    rowid,
+                ),
+                ("DELETE FROM reminders WHERE id = ?", rowid),
+            ]),
+            // Keep the medication visible under the default profile
+            ("medications", "profiles", Some(rowid)) => Some(vec![(
+                "UPDATE medications SET profile_id =
+                     (SELECT MIN(id) FROM profiles WHERE deleted_at IS NULL)
+                 WHERE id = ?",
+                rowid,
+            )]),
+            ("dose_logs", "medications", Some(rowid)) => {
+                Some(vec![("DELETE FROM dose_logs WHERE id = ?", rowid)])
+            }
+            ("dose_logs", "reminders", Some(rowid)) => Some(vec![(
+                "UPDATE dose_logs SET reminder_id = NULL WHERE id = ?",
+                rowid,
+            )]),
+            ("phased_schedules", "medications", Some(rowid)) => Some(vec![
+                ("DELETE FROM schedule_phases WHERE schedule_id = ?", rowid),
+                ("DELETE FROM phased_schedules WHERE id = ?", rowid),
+            ]),
+            ("schedule_phases", "phased_schedules", Some(rowid)) => {
+                Some(vec![("DELETE FROM schedule_phases WHERE id = ?", rowid)])
+            }
+            _ => None,
+        };
+
+        let mut repaired = false;
+        if let (true, Some(statements)) = (repair, &fix) {
+            for (sql, rowid) in statements {
+                conn.execute(sql, params![*rowid]).await?;
+            }
+            repaired = true;
+        }
+
+        issues.push(IntegrityIssue {
+            kind: IntegrityIssueKind::ForeignKey,
+            message: format!("Row in {} refers to a missing row in {}", table, parent),
+            table: Some(table),
+            record_id,
+            repaired,
+        });
+    }
+
+    Ok(())
+}
+
+/// Record id of the row at `rowid`, for the tables that have one
+async fn record_id(conn: &Connection, table: &str, rowid: i64) -> Result<Option<String>> {
+    if !RECORD_TABLES.contains(&table) {
+        return Ok(None);
+    }
+
+    let sql = format!("SELECT uuid FROM {} WHERE id = ?", table);
+    let mut rows = conn.query(&sql, params![rowid]).await?;
+    match rows.next().await? {
+        Some(row) => Ok(row.get::<Option<String>>(0)?),
+        None => Ok(None),
+    }
+}
+
+// ===== Record Checks =====
+
+/// Live reminders of deleted medications, which `delete_medication` would
+/// have deleted with them. Repair deletes them the same way.
+async fn orphaned_reminder_issues(
+    conn: &Connection,
+    repair: bool,
+    issues: &mut Vec<IntegrityIssue>,
+) -> Result<()> {
+    let mut orphans = Vec::new();
+    let mut rows = conn
+        .query(
+            "SELECT r.id, r.uuid, m.name FROM reminders r
+             JOIN medications m ON m.id = r.medication_id
+             WHERE r.deleted_at IS NULL AND m.deleted_at IS NOT NULL",
+            (),
+        )
+        .await?;
+    while let Some(row) = rows.next().await? {
+        orphans.push((
+            row.get::<i64>(0)?,
+            row.get::<Option<String>>(1)?,
+            row.get::<String>(2)?,
+        ));
+    }
+
+    let now = format_timestamp(Utc::now());
+    for (rowid, uuid, name) in orphans {
+        if repair {
+            conn.execute(
+                "UPDATE reminders SET deleted_at = ? WHERE id = ?",
+                params![now.clone(), rowid],
+            )
+            .await?;
+        }
+
+        issues.push(IntegrityIssue {
+            kind: IntegrityIssueKind::OrphanedReminder,
+            table: Some("reminders".to_string()),
+            record_id: uuid,
+            message: format!("Reminder belongs to deleted medication '{}'", name),
+            repaired: repair,
+        });
+    }
+
+    Ok(())
+}
+
+/// Active reminders whose time or days cannot be read and so can never be
+/// scheduled. Repair deactivates them, keeping the text for the user to fix.
+async fn reminder_issues(
+    conn: &Connection,
+    repair: bool,
+    issues: &mut Vec<IntegrityIssue>,
+) -> Result<()> {
+    let mut reminders = Vec::new();
+    let mut rows = conn
+        .query(
+            "SELECT id, uuid, time, COALESCE(days, '') FROM reminders
+             WHERE deleted_at IS NULL AND is_active = 1",
+            (),
+        )
+        .await?;
+    while let Some(row) = rows.next().await? {
+        reminders.push((
+            row.get::<i64>(0)?,
+            row.get::<Option<String>>(1)?,
+            row.get::<String>(2)?,
+            row.get::<String>(3)?,
+        ));
+    }
+
+    let now = format_timestamp(Utc::now());
+    for (rowid, uuid, time, days) in reminders {
+        let mut found = Vec::new();
+        if parse_time(&time).is_none() {
+            found.push((
+                IntegrityIssueKind::InvalidTime,
+                format!("Reminder time '{}' is not HH:MM", time),
+            ));
+        }
+        if parse_days(&days).is_none() {
+            found.push((
+                IntegrityIssueKind::InvalidDays,
+                format!("Reminder days '{}' are not recognised", days),
+            ));
+        }
+
+        if found.is_empty() {
+            continue;
+        }
+
+        if repair {
+            conn.execute(
+                "UPDATE reminders SET is_active = 0, updated_at = ?, version = version + 1
+                 WHERE id = ?",
+                params![now.clone(), rowid],
+            )
+            .await?;
+        }
+
+        for (kind, message) in found {
+            issues.push(IntegrityIssue {
+                kind,
+                table: Some("reminders".to_string()),
+                record_id: uuid.clone(),
+                message,
+                repaired: repair,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Live phased schedules of deleted medications, which `delete_medication`
+/// would have deleted with them, and schedules whose phases cannot be read
+/// or would fail `check_schedule`. Repair deletes them the same way, so the
+/// medication's reminders apply again.
+async fn schedule_issues(
+    conn: &Connection,
+    repair: bool,
+    issues: &mut Vec<IntegrityIssue>,
+) -> Result<()> {
+    let mut schedules = Vec::new();
+    let mut rows = conn
+        .query(
+            "SELECT s.id, s.uuid, s.start_date, m.name, m.deleted_at IS NOT NULL
+             FROM phased_schedules s JOIN medications m ON m.id = s.medication_id
+             WHERE s.deleted_at IS NULL",
+            (),
+        )
+        .await?;
+    while let Some(row) = rows.next().await? {
+        schedules.push((
+            row.get::<i64>(0)?,
+            row.get::<String>(1)?,
+            row.get::<String>(2)?,
+            row.get::<String>(3)?,
+            row.get::<bool>(4)?,
+        ));
+    }
+    drop(rows);
+
+    let now = format_timestamp(Utc::now());
+    for (rowid, uuid, start_date, name, medication_deleted) in schedules {
+        let (kind, message) = if medication_deleted {
+            (
+                IntegrityIssueKind::OrphanedSchedule,
+                format!("Schedule belongs to deleted medication '{}'", name),
+            )
+        } else {
+            let problem = match read_phases(conn, rowid).await {
+                Ok(phases) => check_schedule(&PhasedSchedule {
+                    start_date,
+                    phases,
+                    ..Default::default()
+                })
+                .err(),
+                Err(e) => Some(anyhow::anyhow!("Its phases cannot be read: {}", e)),
+            };
+            let Some(problem) = problem else {
+                continue;
+            };
+            (
+                IntegrityIssueKind::InvalidSchedule,
+                format!("Schedule for '{}' cannot be followed: {}", name, problem),
+            )
+        };
+
+        if repair {
+            conn.execute(
+                "UPDATE phased_schedules SET deleted_at = ?, updated_at = ? WHERE id = ?",
+                params![now.clone(), now.clone(), rowid],
+            )
+            .await?;
+        }
+
+        issues.push(IntegrityIssue {
+            kind,
+            table: Some("phased_schedules".to_string()),
+            record_id: Some(uuid),
+            message,
+            repaired: repair,
+        });
+    }
+
+    Ok(())
+}
+
+/// Medications and reminders without a record id, which the API cannot
+/// address. Repair assigns new ids.
+async fn missing_id_issues(
+    conn: &Connection,
+    repair: bool,
+    issues: &mut Vec<IntegrityIssue>,
+) -> Result<()> {
+    for table in ["medications", "reminders"] {
+        let mut rowids = Vec::new();
+        let sql = format!("SELECT id FROM {} WHERE uuid IS NULL OR uuid = ''", table);
+        let mut rows = conn.query(&sql, ()).await?;
+        while let Some(row) = rows.next().await? {
+            rowids.push(row.get::<i64>(0)?);
+        }
+
+        let update = format!("UPDATE {} SET uuid = ? WHERE id = ?", table);
+        for rowid in rowids {
+            let mut record_id = None;
+            if repair {
+                let id = new_record_id();
+                conn.execute(&update, params![id.clone(), rowid]).await?;
+                record_id = Some(id);
+            }
+
+            issues.push(IntegrityIssue {
+                kind: IntegrityIssueKind::MissingId,
+                table: Some(table.to_string()),
+                record_id,
+                message: format!("Row {} in {} has no record id", rowid, table),
+                repaired: repair,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::test_session;
+    use crate::models::{Medication, Reminder, SchedulePhase};
+    use crate::repository;
+
+    async fn execute(sql: &str) {
+        let conn = get_connection().unwrap();
+        conn.lock().await.execute(sql, ()).await.unwrap();
+    }
+
+    async fn count(sql: &str) -> i64 {
+        let conn = get_connection().unwrap();
+        let conn = conn.lock().await;
+        let mut rows = conn.query(sql, ()).await.unwrap();
+        rows.next().await.unwrap().unwrap().get(0).unwrap()
+    }
+
+    async fn create_medication(name: &str) -> String {
+        let id = repository::create_medication(&Medication {
+            name: name.to_string(),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        repository::create_reminder(&Reminder {
+            medication_id: id.clone(),
+            time: "08:00".to_string(),
+            days: "Daily".to_string(),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        repository::create_schedule(&PhasedSchedule {
+            medication_id: id.clone(),
+            start_date: "2024-03-04".to_string(),
+            phases: vec![SchedulePhase {
+                duration_days: 10,
+                dose_quantity: 20.0,
+                dose_unit: "mg".to_string(),
+                times: vec!["08:00".to_string()],
+                days: "Daily".to_string(),
+                every_n_days: 1,
+            }],
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        id
+    }
+
+    fn kinds(report: &IntegrityReport) -> Vec<IntegrityIssueKind> {
+        let mut kinds: Vec<_> = report.issues.iter().map(|issue| issue.kind).collect();
+        kinds.sort_by_key(|kind| format!("{:?}", kind));
+        kinds
+    }
+
+    #[tokio::test]
+    async fn healthy_database_has_no_issues() {
+        let _session = test_session().await;
+        create_medication("Ibuprofen").await;
+
+        let report = check_integrity(true).await.unwrap();
+        assert!(report.healthy);
+        assert!(report.issues.is_empty());
+    }
+
+    #[tokio::test]
+    async fn check_reports_and_repair_fixes() {
+        let _session = test_session().await;
+        let deleted = create_medication("Amoxicillin").await;
+        let unreadable = create_medication("Prednisolone").await;
+        create_medication("Vitamin D").await;
+
+        execute(&format!(
+            "UPDATE medications SET deleted_at = '2024-03-01T00:00:00.000Z' WHERE uuid = '{}'",
+            deleted
+        ))
+        .await;
+        execute(&format!(
+            "UPDATE reminders SET time = '25:00', days = 'Someday'
+             WHERE medication_id = (SELECT id FROM medications WHERE uuid = '{}')",
+            unreadable
+        ))
+        .await;
+        execute(&format!(
+            "UPDATE schedule_phases SET duration_days = 0 WHERE schedule_id =
+                 (SELECT s.id FROM phased_schedules s
+                  JOIN medications m ON m.id = s.medication_id WHERE m.uuid = '{}')",
+            unreadable
+        ))
+        .await;
+        execute("UPDATE medications SET uuid = NULL WHERE name = 'Vitamin D'").await;
+
+        let expected = vec![
+            IntegrityIssueKind::InvalidDays,
+            IntegrityIssueKind::InvalidSchedule,
+            IntegrityIssueKind::InvalidTime,
+            IntegrityIssueKind::MissingId,
+            IntegrityIssueKind::OrphanedReminder,
+            IntegrityIssueKind::OrphanedSchedule,
+        ];
+
+        // A check-only pass reports everything and changes nothing
+        let report = check_integrity(false).await.unwrap();
+        assert!(!report.repair);
+        assert!(!report.healthy);
+        assert_eq!(kinds(&report), expected);
+        assert!(report.issues.iter().all(|issue| !issue.repaired));
+        assert_eq!(kinds(&check_integrity(false).await.unwrap()), expected);
+        assert_eq!(
+            count("SELECT COUNT(*) FROM medications WHERE uuid IS NULL").await,
+            1
+        );
+
+        let report = check_integrity(true).await.unwrap();
+        assert!(report.repair);
+        assert!(report.healthy);
+        assert_eq!(kinds(&report), expected);
+        assert!(report.issues.iter().all(|issue| issue.repaired));
+
+        // The orphans are deleted, the unreadable reminder deactivated and
+        // its schedule deleted, and the medication given an id
+        assert_eq!(
+            count("SELECT COUNT(*) FROM reminders WHERE deleted_at IS NULL").await,
+            2
+        );
+        assert_eq!(
+            count("SELECT COUNT(*) FROM reminders WHERE deleted_at IS NULL AND is_active = 1")
+                .await,
+            1
+        );
+        assert_eq!(
+            count("SELECT COUNT(*) FROM phased_schedules WHERE deleted_at IS NULL").await,
+            1
+        );
+        assert_eq!(
+            count("SELECT COUNT(*) FROM medications WHERE uuid IS NULL OR uuid = ''").await,
+            0
+        );
+
+        let report = check_integrity(false).await.unwrap();
+        assert!(report.healthy);
+        assert!(report.issues.is_empty());
+    }
+}
@@ -0,0 +1,400 @@
+use crate::models::{InteractionSeverity, InteractionWarning, Medication};
+use crate::repository;
+use anyhow::Result;
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, RwLock};
+
+// Drug-drug interaction checks against a dataset of ingredient pairs. A
+// medication is matched to ingredients by any of their names appearing as
+// whole words in its name, so "Coumadin 5mg" counts as warfarin. The
+// bundled dataset only covers a few well-known interactions; apps that need
+// more load their own in the same format.
+
+/// Value of `format` in every dataset, so unrelated JSON is rejected early
+const DATASET_FORMAT: &str = "pillmom-interactions";
+
+const BUNDLED_DATASET: &str = include_str!("../data/interactions.json");
+
+/// Dataset in use. `None` stands for the bundled one, which is parsed
+/// into place on first use.
+static DATASET: RwLock<Option<Arc<Dataset>>> = RwLock::new(None);
+
+#[derive(Deserialize)]
+struct DatasetFile {
+    format: String,
+    ingredients: Vec<IngredientEntry>,
+    interactions: Vec<InteractionEntry>,
+}
+
+#[derive(Deserialize)]
+struct IngredientEntry {
+    id: String,
+    /// Generic and brand names; the first is shown in warnings
+    names: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct InteractionEntry {
+    /// Ids of the two interacting ingredients
+    ingredients: [String; 2],
+    severity: String,
+    description: String,
+}
+
+struct Dataset {
+    /// Display name of each ingredient
+    ingredients: Vec<String>,
+    /// Normalised names with the ingredient they identify
+    names: Vec<(String, usize)>,
+    /// Interactions keyed by ingredient indices, smaller first
+    pairs: HashMap<(usize, usize), (InteractionSeverity, String)>,
+}
+
+impl Dataset {
+    fn parse(data: &str) -> Result<Self> {
+        let file: DatasetFile = serde_json::from_str(data)
+            .map_err(|e| anyhow::anyhow!("Invalid interaction dataset: {}", e))?;
+
+        if file.format != DATASET_FORMAT {
+            return Err(anyhow::anyhow!(
+                "Unrecognised interaction dataset format '{}'",
+                file.format
+            ));
+        }
+
+        let mut index = HashMap::new();
+        let mut ingredients = Vec::with_capacity(file.ingredients.len());
+        let mut names = Vec::new();
+
+        for entry in file.ingredients {
+            if entry.id.trim().is_empty() {
+                return Err(anyhow::anyhow!("Interaction dataset has an ingredient without an id"));
+            }
+            if index.insert(entry.id.clone(), ingredients.len()).is_some() {
+                return Err(anyhow::anyhow!(
+                    "Interaction dataset lists ingredient '{}' twice",
+                    entry.id
+                ));
+            }
+
+            let entry_names: Vec<String> = entry
+                .names
+                .iter()
+                .map(|name| normalize(name))
+                .filter(|name| !name.is_empty())
+                .collect();
+            if entry_names.is_empty() {
+                return Err(anyhow::anyhow!(
+                    "Ingredient '{}' in the interaction dataset has no names",
+                    entry.id
+                ));
+            }
+
+            names.extend(entry_names.into_iter().map(|name| (name, ingredients.len())));
+            ingredients.push(entry.names[0].trim().to_string());
+        }
+
+        let mut pairs = HashMap::new();
+        for entry in file.interactions {
+            let [a, b] = &entry.ingredients;
+            let lookup = |id: &String| {
+                index.get(id).copied().ok_or_else(|| {
+                    anyhow::anyhow!("Interaction dataset refers to unknown ingredient '{}'", id)
+                })
+            };
+            let key = pair_key(lookup(a)?, lookup(b)?);
+
+            if key.0 == key.1 {
+                return Err(anyhow::anyhow!(
+                    "Interaction dataset pairs ingredient '{}' with itself",
+                    a
+                ));
+            }
+
+            let severity = parse_severity(&entry.severity)?;
+            if pairs.insert(key, (severity, entry.description)).is_some() {
+                return Err(anyhow::anyhow!(
+                    "Interaction dataset lists '{}' and '{}' twice",
+                    a,
+                    b
+                ));
+            }
+        }
+
+        Ok(Self {
+            ingredients,
+            names,
+            pairs,
+        })
+    }
+
+    /// Ingredients whose names appear as whole words in `text`
+    fn ingredients_in(&self, text: &str) -> Vec<usize> {
+        let text = format!(" {} ", normalize(text));
+        let mut found: Vec<usize> = Vec::new();
+
+        for (name, ingredient) in &self.names {
+            if !found.contains(ingredient) && text.contains(&format!(" {} ", name)) {
+                found.push(*ingredient);
+            }
+        }
+
+        found
+    }
+
+    /// Interactions between `medication` and each other medication in
+    /// `current`, most severe first
+    fn check(&self, medication: &Medication, current: &[Medication]) -> Vec<InteractionWarning> {
+        let ingredients = self.ingredients_in(&medication.name);
+        if ingredients.is_empty() {
+            return Vec::new();
+        }
+
+        let mut warnings = Vec::new();
+        let mut seen = HashSet::new();
+
+        for other in current {
+            let Some(other_id) = other.id.as_deref() else {
+                continue;
+            };
+            if medication.id.as_deref() == Some(other_id) {
+                continue;
+            }
+
+            for other_ingredient in self.ingredients_in(&other.name) {
+                for &ingredient in &ingredients {
+                    let Some((severity, description)) =
+                        self.pairs.get(&pair_key(ingredient, other_ingredient))
+                    else {
+                        continue;
+                    };
+                    if !seen.insert((other_id, ingredient, other_ingredient)) {
+                        continue;
+                    }
+
+                    warnings.push(InteractionWarning {
+                        medication_id: other_id.to_string(),
+                        medication_name: other.name.clone(),
+                        ingredient: self.ingredients[ingredient].clone(),
+                        other_ingredient: self.ingredients[other_ingredient].clone(),
+                        severity: *severity,
+                        description: description.clone(),
+                    });
+                }
+            }
+        }
+
+        warnings.sort_by(|a, b| {
+            b.severity
+                .cmp(&a.severity)
+                .then_with(|| a.medication_name.cmp(&b.medication_name))
+        });
+        warnings
+    }
+}
+
+/// Replace the dataset with `data`, a JSON document in the bundled format.
+/// Returns the number of interactions it lists.
+pub fn load_dataset(data: &str) -> Result<i64> {
+    let dataset = Dataset::parse(data)?;
+    let count = dataset.pairs.len() as i64;
+    *DATASET.write().unwrap_or_else(|e| e.into_inner()) = Some(Arc::new(dataset));
+    Ok(count)
+}
+
+/// Go back to the dataset bundled with the library
+pub fn use_bundled_dataset() {
+    *DATASET.write().unwrap_or_else(|e| e.into_inner()) = None;
+}
+
+/// Known interactions between `medication` and the active profile's
+/// current medications
+pub async fn check_medication(medication: &Medication) -> Result<Vec<InteractionWarning>> {
+    let dataset = current_dataset()?;
+    let current = repository::get_all_medications().await?;
+    Ok(dataset.check(medication, &current))
+}
+
+fn current_dataset() -> Result<Arc<Dataset>> {
+    if let Some(dataset) = DATASET.read().unwrap_or_else(|e| e.into_inner()).as_ref() {
+        return Ok(dataset.clone());
+    }
+
+    let dataset = Arc::new(Dataset::parse(BUNDLED_DATASET)?);
+    let mut slot = DATASET.write().unwrap_or_else(|e| e.into_inner());
+    // Another caller may have loaded a dataset in the meantime
+    Ok(slot.get_or_insert(dataset).clone())
+}
+
+/// Lower case with runs of anything but letters and digits collapsed to
+/// one space, so names match regardless of punctuation
+pub(crate) fn normalize(text: &str) -> String {
+    text.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn pair_key(a: usize, b: usize) -> (usize, usize) {
+    (a.min(b), a.max(b))
+}
+
+fn parse_severity(name: &str) -> Result<InteractionSeverity> {
+    match name {
+        "minor" => Ok(InteractionSeverity::Minor),
+        "moderate" => Ok(InteractionSeverity::Moderate),
+        "major" => Ok(InteractionSeverity::Major),
+        "contraindicated" => Ok(InteractionSeverity::Contraindicated),
+        other => Err(anyhow::anyhow!("Unknown interaction severity '{}'", other)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    fn dataset_json() -> Value {
+        json!({
+            "format": "pillmom-interactions",
+            "ingredients": [
+                { "id": "warfarin", "names": ["Warfarin", "Coumadin", "Jantoven"] },
+                { "id": "aspirin", "names": ["Aspirin", "acetylsalicylic acid"] },
+                { "id": "ibuprofen", "names": ["Ibuprofen", "Advil"] },
+                { "id": "omeprazole", "names": ["Omeprazole"] },
+            ],
+            "interactions": [
+                {
+                    "ingredients": ["warfarin", "aspirin"],
+                    "severity": "major",
+                    "description": "Bleeding risk",
+                },
+                {
+                    "ingredients": ["ibuprofen", "warfarin"],
+                    "severity": "moderate",
+                    "description": "Bleeding risk, less so",
+                },
+                {
+                    "ingredients": ["omeprazole", "warfarin"],
+                    "severity": "minor",
+                    "description": "May raise INR",
+                },
+            ],
+        })
+    }
+
+    fn dataset() -> Dataset {
+        Dataset::parse(&dataset_json().to_string()).unwrap()
+    }
+
+    fn parse_error(edit: impl FnOnce(&mut Value)) -> String {
+        let mut data = dataset_json();
+        edit(&mut data);
+        match Dataset::parse(&data.to_string()) {
+            Ok(_) => panic!("dataset should be rejected"),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    fn medication(id: &str, name: &str) -> Medication {
+        Medication {
+            id: Some(id.to_string()),
+            name: name.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn parse_reads_a_dataset() {
+        let dataset = dataset();
+        assert_eq!(dataset.ingredients[0], "Warfarin");
+        assert_eq!(dataset.pairs.len(), 3);
+        // Stored by index, smaller first, whichever order the file uses
+        assert_eq!(dataset.pairs[&(0, 2)].0, InteractionSeverity::Moderate);
+    }
+
+    #[test]
+    fn parse_rejects_bad_datasets() {
+        assert!(parse_error(|data| data["format"] = json!("something-else"))
+            .contains("Unrecognised interaction dataset format"));
+        assert!(parse_error(|data| *data = json!({ "format": 1 }))
+            .contains("Invalid interaction dataset"));
+        assert!(
+            parse_error(|data| data["ingredients"][1]["id"] = json!("warfarin"))
+                .contains("lists ingredient 'warfarin' twice")
+        );
+        assert!(
+            parse_error(|data| data["ingredients"][3]["names"] = json!([" - "]))
+                .contains("has no names")
+        );
+        assert!(parse_error(
+            |data| data["interactions"][0]["ingredients"] = json!(["aspirin", "aspirin"])
+        )
+        .contains("pairs ingredient 'aspirin' with itself"));
+        assert!(parse_error(
+            |data| data["interactions"][0]["ingredients"] = json!(["aspirin", "heparin"])
+        )
+        .contains("unknown ingredient 'heparin'"));
+        assert!(
+            parse_error(|data| data["interactions"][0]["severity"] = json!("severe"))
+                .contains("Unknown interaction severity 'severe'")
+        );
+        assert!(parse_error(
+            |data| data["interactions"][1]["ingredients"] = json!(["aspirin", "warfarin"])
+        )
+        .contains("twice"));
+    }
+
+    #[test]
+    fn ingredients_match_whole_words() {
+        let dataset = dataset();
+        assert_eq!(dataset.ingredients_in("Coumadin 5mg"), [0]);
+        assert_eq!(
+            dataset.ingredients_in("ACETYLSALICYLIC-acid (low dose)"),
+            [1]
+        );
+        assert!(dataset.ingredients_in("aspirinx").is_empty());
+        assert!(dataset.ingredients_in("Coumadinal").is_empty());
+        // Each ingredient once, even when named twice
+        assert_eq!(dataset.ingredients_in("Warfarin (Coumadin)"), [0]);
+    }
+
+    #[test]
+    fn check_orders_warnings_by_severity() {
+        let dataset = dataset();
+        let current = [
+            medication("1", "Omeprazole 20mg"),
+            medication("2", "Advil"),
+            medication("3", "Aspirin 81mg"),
+            medication("4", "Vitamin D"),
+        ];
+        let warnings = dataset.check(&medication("5", "Coumadin"), &current);
+
+        let found: Vec<(&str, InteractionSeverity)> = warnings
+            .iter()
+            .map(|warning| (warning.medication_id.as_str(), warning.severity))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("3", InteractionSeverity::Major),
+                ("2", InteractionSeverity::Moderate),
+                ("1", InteractionSeverity::Minor),
+            ]
+        );
+        assert_eq!(warnings[0].ingredient, "Warfarin");
+        assert_eq!(warnings[0].other_ingredient, "Aspirin");
+    }
+
+    #[test]
+    fn check_skips_the_medication_itself() {
+        let dataset = dataset();
+        let med = medication("1", "Warfarin and aspirin");
+        assert!(dataset.check(&med, std::slice::from_ref(&med)).is_empty());
+        assert!(dataset
+            .check(&medication("2", "Vitamin D"), &[med])
+            .is_empty());
+    }
+}
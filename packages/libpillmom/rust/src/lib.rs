@@ -2,13 +2,37 @@ mod frb_generated; /* AUTO INJECTED BY flutter_rust_bridge. This line may not be
 
 // Required for Flutter Rust Bridge
 pub mod api;
+mod agenda;
+mod catalog;
+mod course;
+mod csv;
 mod database;
+pub mod error;
+mod export;
+mod fhir;
+mod ical;
+mod integrity;
+mod interactions;
+mod missed;
 pub mod models;
+mod notifications;
+mod outbox;
+#[cfg(feature = "pdf")]
+mod pdf;
+mod prn;
+mod report;
 mod repository;
+mod schedule;
 
 // Re-export for Flutter Rust Bridge
 pub use api::*;
-pub use models::{Medication, Reminder};
+pub use error::PillMomError;
+pub use models::{
+    ActivePhase, AgendaItem, CatalogEntry, CourseProgress, CreatedMedication, CsvColumnMapping, CsvImportReport, CsvRowError, DoseAvailability, DoseLimitReason, DoseLog, DoseStatus, EscalationEvent, EscalationKind,
+    FhirResourceType, ImportMode, ImportSummary, IntegrityIssue, IntegrityIssueKind,
+    IntegrityReport, InteractionSeverity, InteractionWarning, JournalMode, Medication, MedicationPage, MedicationQuery, MedicationSortField, MergePolicy, MissedDoseOptions, MissedDoseReport, NotificationRequest, OpenOptions,
+    OutboxFailure, OutboxStatus, PhasedSchedule, Profile, Reminder, SchedulePhase, SynchronousMode,
+};
 
 // Initialize flutter_rust_bridge
 // The macro was already injected by the code generator
\ No newline at end of file
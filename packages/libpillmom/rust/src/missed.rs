@@ -0,0 +1,366 @@
+use crate::database;
+use crate::models::{
+    DoseLog, DoseStatus, EscalationEvent, EscalationKind, Medication, MissedDoseOptions,
+    MissedDoseReport,
+};
+use crate::outbox::{self, Mutation};
+use crate::repository;
+use crate::schedule::{self, DoseSource};
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+
+// Finds scheduled doses that were neither taken nor skipped in time. Doses
+// past their grace window are recorded as missed; doses still not logged
+// after the escalation delays produce events for the app to deliver.
+// Reminder times are local, so the caller gives its UTC offset.
+
+/// Longest grace window and escalation delay, in minutes
+const MAX_DELAY_MINUTES: i64 = 7 * 24 * 60;
+
+/// Furthest back an evaluation may look, in hours
+const MAX_LOOKBACK_HOURS: i64 = 7 * 24;
+
+/// A dose that was due at `scheduled_for`
+struct Occurrence<'a> {
+    med: &'a Medication,
+    reminder_id: Option<String>,
+    /// Reminder or schedule the dose comes from
+    source_id: String,
+    scheduled_for: DateTime<Utc>,
+}
+
+pub async fn evaluate(now: DateTime<Utc>, options: &MissedDoseOptions) -> Result<MissedDoseReport> {
+    check_options(options)?;
+
+    let offset = schedule::utc_offset(options.utc_offset_minutes)?;
+    let window_start = now - Duration::hours(options.lookback_hours);
+    let grace = Duration::minutes(options.grace_minutes);
+
+    let medications = repository::get_all_medications().await?;
+    let schedules = repository::get_schedules().await?;
+
+    // Doses can be logged well before they are due, so look further back
+    let mut doses =
+        repository::get_dose_logs(Some(window_start - Duration::hours(24)), None).await?;
+    for med in &medications {
+        if let Some(id) = med.id.as_deref() {
+            doses.extend(outbox::queued_doses(id).await?);
+        }
+    }
+
+    // Every scheduled dose due in the window up to now
+    let mut occurrences = Vec::new();
+    for med in medications.iter().filter(|med| med.deleted_at.is_none()) {
+        let Some(medication_id) = med.id.as_deref() else {
+            continue;
+        };
+        let phased = schedules
+            .iter()
+            .find(|schedule| schedule.medication_id == medication_id);
+
+        for (scheduled_for, due) in schedule::doses_between(med, phased, window_start, now, offset)
+        {
+            let (reminder_id, source_id, since) = match due.source {
+                DoseSource::Reminder(reminder) => (
+                    reminder.id.clone(),
+                    reminder.id.clone().unwrap_or_default(),
+                    reminder.created_at,
+                ),
+                DoseSource::Phase { schedule, .. } => (
+                    None,
+                    schedule.id.clone().unwrap_or_default(),
+                    schedule.created_at,
+                ),
+            };
+            // Only doses due after the schedule was set up can be missed
+            if scheduled_for < since {
+                continue;
+            }
+
+            occurrences.push(Occurrence {
+                med,
+                reminder_id,
+                source_id,
+                scheduled_for,
+            });
+        }
+    }
+    occurrences.sort_by_key(|occurrence| occurrence.scheduled_for);
+
+    let recording = !database::is_read_only();
+    let mut used = vec![false; doses.len()];
+    let mut report = MissedDoseReport {
+        missed: Vec::new(),
+        escalations: Vec::new(),
+    };
+
+    for occurrence in &occurrences {
+        let medication_id = occurrence.med.id.clone().unwrap_or_default();
+
+        let Some(logged) = match_dose(
+            &medication_id,
+            occurrence.reminder_id.as_deref(),
+            occurrence.scheduled_for,
+            &doses,
+            &mut used,
+            grace,
+        ) else {
+            // Not logged at all
+            let missed_at = occurrence.scheduled_for + grace;
+            if missed_at <= now {
+                let mut dose = DoseLog {
+                    id: recording.then(repository::new_record_id),
+                    medication_id: medication_id.clone(),
+                    reminder_id: occurrence.reminder_id.clone(),
+                    status: DoseStatus::Missed,
+                    scheduled_for: Some(occurrence.scheduled_for),
+                    logged_at: missed_at,
+                };
+                if recording {
+                    let id = outbox::submit(Mutation::RecordDose { dose: dose.clone() })
+                        .await?
+                        .id();
+                    dose.id = Some(id);
+                }
+                report.missed.push(dose);
+            }
+            escalate(occurrence, &medication_id, options, now, &mut report);
+            continue;
+        };
+
+        if logged == DoseStatus::Missed {
+            escalate(occurrence, &medication_id, options, now, &mut report);
+        }
+    }
+
+    report
+        .escalations
+        .sort_by(|a, b| a.due_at.cmp(&b.due_at).then_with(|| a.id.cmp(&b.id)));
+
+    Ok(report)
+}
+
+// ===== Helper Functions =====
+
+fn check_options(options: &MissedDoseOptions) -> Result<()> {
+    let delays = [
+        ("Grace window", Some(options.grace_minutes)),
+        ("Re-notify delay", options.renotify_after_minutes),
+        ("Caregiver alert delay", options.caregiver_after_minutes),
+    ];
+    for (name, minutes) in delays {
+        if let Some(minutes) = minutes {
+            if !(0..=MAX_DELAY_MINUTES).contains(&minutes) {
+                return Err(anyhow::anyhow!(
+                    "{} must be between 0 and {} minutes, got {}",
+                    name,
+                    MAX_DELAY_MINUTES,
+                    minutes
+                ));
+            }
+        }
+    }
+
+    if !(1..=MAX_LOOKBACK_HOURS).contains(&options.lookback_hours) {
+        return Err(anyhow::anyhow!(
+            "Lookback must be between 1 and {} hours",
+            MAX_LOOKBACK_HOURS
+        ));
+    }
+
+    Ok(())
+}
+
+/// Status of the dose logged for the medication's dose due at
+/// `scheduled_for` from `reminder_id`, marking it used: one linked to that
+/// time (and reminder, if the dose names one), or else one logged without a
+/// time within the grace window either side of it
+pub(crate) fn match_dose(
+    medication_id: &str,
+    reminder_id: Option<&str>,
+    scheduled_for: DateTime<Utc>,
+    doses: &[DoseLog],
+    used: &mut [bool],
+    grace: Duration,
+) -> Option<DoseStatus> {
+    let linked = |dose: &DoseLog| {
+        dose.scheduled_for == Some(scheduled_for)
+            && (dose.reminder_id.is_none() || dose.reminder_id.as_deref() == reminder_id)
+    };
+    let unlinked = |dose: &DoseLog| {
+        dose.scheduled_for.is_none() && (dose.logged_at - scheduled_for).abs() <= grace
+    };
+    let available =
+        |index: usize, dose: &DoseLog| !used[index] && dose.medication_id == medication_id;
+
+    let index = doses
+        .iter()
+        .enumerate()
+        .position(|(index, dose)| available(index, dose) && linked(dose))
+        .or_else(|| {
+            doses
+                .iter()
+                .enumerate()
+                .position(|(index, dose)| available(index, dose) && unlinked(dose))
+        })?;
+
+    used[index] = true;
+    Some(doses[index].status)
+}
+
+/// Add the escalations of an unlogged dose that are due by `now`
+fn escalate(
+    occurrence: &Occurrence,
+    medication_id: &str,
+    options: &MissedDoseOptions,
+    now: DateTime<Utc>,
+    report: &mut MissedDoseReport,
+) {
+    let delays = [
+        (EscalationKind::Renotify, options.renotify_after_minutes),
+        (
+            EscalationKind::CaregiverAlert,
+            options.caregiver_after_minutes,
+        ),
+    ];
+
+    for (kind, minutes) in delays {
+        let Some(minutes) = minutes else {
+            continue;
+        };
+        let due_at = occurrence.scheduled_for + Duration::minutes(minutes);
+        if due_at > now {
+            continue;
+        }
+
+        let name = match kind {
+            EscalationKind::Renotify => "renotify",
+            EscalationKind::CaregiverAlert => "caregiver",
+        };
+        report.escalations.push(EscalationEvent {
+            id: format!(
+                "{}:{}:{}",
+                name,
+                occurrence.source_id,
+                occurrence.scheduled_for.timestamp()
+            ),
+            kind,
+            medication_id: medication_id.to_string(),
+            medication_name: occurrence.med.name.clone(),
+            reminder_id: occurrence.reminder_id.clone(),
+            scheduled_for: occurrence.scheduled_for,
+            due_at,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(value: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(value)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    fn dose(
+        medication_id: &str,
+        reminder_id: Option<&str>,
+        status: DoseStatus,
+        scheduled_for: Option<&str>,
+        logged_at: &str,
+    ) -> DoseLog {
+        DoseLog {
+            id: None,
+            medication_id: medication_id.to_string(),
+            reminder_id: reminder_id.map(str::to_string),
+            status,
+            scheduled_for: scheduled_for.map(at),
+            logged_at: at(logged_at),
+        }
+    }
+
+    fn matched(
+        reminder_id: &str,
+        scheduled_for: &str,
+        doses: &[DoseLog],
+        used: &mut [bool],
+    ) -> Option<DoseStatus> {
+        let grace = Duration::minutes(30);
+        match_dose(
+            "med",
+            Some(reminder_id),
+            at(scheduled_for),
+            doses,
+            used,
+            grace,
+        )
+    }
+
+    #[test]
+    fn match_dose_prefers_linked_doses() {
+        let doses = [
+            dose("med", None, DoseStatus::Taken, None, "2024-03-04T08:05:00Z"),
+            dose(
+                "med",
+                Some("r1"),
+                DoseStatus::Skipped,
+                Some("2024-03-04T08:00:00Z"),
+                "2024-03-04T09:00:00Z",
+            ),
+        ];
+        let mut used = [false; 2];
+
+        assert_eq!(
+            matched("r1", "2024-03-04T08:00:00Z", &doses, &mut used),
+            Some(DoseStatus::Skipped)
+        );
+        assert_eq!(used, [false, true]);
+
+        // The unlinked dose is left for a second reminder at the same time
+        assert_eq!(
+            matched("r2", "2024-03-04T08:00:00Z", &doses, &mut used),
+            Some(DoseStatus::Taken)
+        );
+        assert_eq!(
+            matched("r2", "2024-03-04T08:00:00Z", &doses, &mut used),
+            None
+        );
+    }
+
+    #[test]
+    fn match_dose_checks_reminder_medication_and_grace() {
+        let doses = [
+            dose(
+                "med",
+                Some("r2"),
+                DoseStatus::Taken,
+                Some("2024-03-04T08:00:00Z"),
+                "2024-03-04T08:00:00Z",
+            ),
+            dose(
+                "other",
+                None,
+                DoseStatus::Taken,
+                None,
+                "2024-03-04T08:00:00Z",
+            ),
+            dose("med", None, DoseStatus::Taken, None, "2024-03-04T08:31:00Z"),
+            dose("med", None, DoseStatus::Taken, None, "2024-03-04T07:30:00Z"),
+        ];
+        let mut used = [false; 4];
+
+        // Logged against another reminder, for another medication, or
+        // after the grace window; the last one is early but within it
+        assert_eq!(
+            matched("r1", "2024-03-04T08:00:00Z", &doses, &mut used),
+            Some(DoseStatus::Taken)
+        );
+        assert_eq!(used, [false, false, false, true]);
+        assert_eq!(
+            matched("r1", "2024-03-04T08:00:00Z", &doses, &mut used),
+            None
+        );
+    }
+}
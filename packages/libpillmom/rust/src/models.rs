@@ -1,42 +1,97 @@
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Medication {
-    pub id: Option<i64>,
+    /// Globally unique id (UUID); `None` until the medication is saved
+    pub id: Option<String>,
+    /// Profile the medication belongs to; `None` on a new medication means
+    /// the active profile
+    #[serde(default)]
+    pub profile_id: Option<String>,
     pub name: String,
     pub dosage: String,
     pub description: String,
-    pub created_at: String,  // Use String for simpler FFI
-    pub updated_at: String,  // Use String for simpler FFI
-    pub deleted_at: Option<String>,  // Use String for simpler FFI
+    /// RxNorm concept id (RXCUI), e.g. "855332"
+    #[serde(default)]
+    pub rxnorm_cui: Option<String>,
+    /// National Drug Code of the package, in any hyphenation
+    #[serde(default)]
+    pub ndc: Option<String>,
+    /// WHO ATC classification code, e.g. "B01AA03"
+    #[serde(default)]
+    pub atc_code: Option<String>,
+    /// Taken as needed (PRN) rather than on a schedule
+    #[serde(default)]
+    pub is_prn: bool,
+    /// Shortest time allowed between two doses taken
+    #[serde(default)]
+    pub min_hours_between_doses: Option<f64>,
+    /// Most doses allowed in any 24 hours
+    #[serde(default)]
+    pub max_daily_doses: Option<i64>,
+    /// First day of a fixed course of treatment, "YYYY-MM-DD"
+    #[serde(default)]
+    pub course_start_date: Option<String>,
+    /// Last day of the course, "YYYY-MM-DD"
+    #[serde(default)]
+    pub course_end_date: Option<String>,
+    /// Doses taken (from `course_start_date`, if set) that complete the course
+    #[serde(default)]
+    pub course_total_doses: Option<i64>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+    /// Incremented on every update; updates must carry the version they
+    /// were based on
+    #[serde(default = "initial_version")]
+    pub version: i64,
     #[serde(default)]
     pub reminders: Vec<Reminder>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Reminder {
-    pub id: Option<i64>,
-    pub medication_id: i64,
+    /// Globally unique id (UUID); `None` until the reminder is saved
+    pub id: Option<String>,
+    /// Id of the medication this reminder belongs to
+    pub medication_id: String,
     pub time: String, // Format: "HH:MM"
     pub days: String, // Comma-separated days: "Mon,Wed,Fri" or "Daily"
     pub is_active: bool,
-    pub created_at: String,  // Use String for simpler FFI
-    pub updated_at: String,  // Use String for simpler FFI
-    pub deleted_at: Option<String>,  // Use String for simpler FFI
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+    #[serde(default = "initial_version")]
+    pub version: i64,
+}
+
+fn initial_version() -> i64 {
+    1
 }
 
 impl Default for Medication {
     fn default() -> Self {
-        let now = Utc::now().to_rfc3339();
+        let now = Utc::now();
         Self {
             id: None,
+            profile_id: None,
             name: String::new(),
             dosage: String::new(),
             description: String::new(),
-            created_at: now.clone(),
+            rxnorm_cui: None,
+            ndc: None,
+            atc_code: None,
+            is_prn: false,
+            min_hours_between_doses: None,
+            max_daily_doses: None,
+            course_start_date: None,
+            course_end_date: None,
+            course_total_doses: None,
+            created_at: now,
             updated_at: now,
             deleted_at: None,
+            version: initial_version(),
             reminders: Vec::new(),
         }
     }
@@ -44,16 +99,572 @@ impl Default for Medication {
 
 impl Default for Reminder {
     fn default() -> Self {
-        let now = Utc::now().to_rfc3339();
+        let now = Utc::now();
         Self {
             id: None,
-            medication_id: 0,
+            medication_id: String::new(),
             time: String::new(),
             days: String::new(),
             is_active: true,
-            created_at: now.clone(),
+            created_at: now,
+            updated_at: now,
+            deleted_at: None,
+            version: initial_version(),
+        }
+    }
+}
+
+/// A dose that changes over time, such as a steroid taper or a titration:
+/// phases run back to back from `start_date`. On the days it covers, the
+/// schedule replaces the medication's reminders. A medication has at most
+/// one live schedule.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PhasedSchedule {
+    /// Globally unique id (UUID); `None` until the schedule is saved
+    pub id: Option<String>,
+    pub medication_id: String,
+    /// First day of the first phase, "YYYY-MM-DD"
+    pub start_date: String,
+    /// In the order they run
+    pub phases: Vec<SchedulePhase>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+    #[serde(default = "initial_version")]
+    pub version: i64,
+}
+
+impl Default for PhasedSchedule {
+    fn default() -> Self {
+        let now = Utc::now();
+        Self {
+            id: None,
+            medication_id: String::new(),
+            start_date: String::new(),
+            phases: Vec::new(),
+            created_at: now,
             updated_at: now,
             deleted_at: None,
+            version: initial_version(),
         }
     }
-}
\ No newline at end of file
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SchedulePhase {
+    /// Days the phase lasts; the next phase starts the day after
+    pub duration_days: i64,
+    /// Amount taken at each time, in `dose_unit`
+    pub dose_quantity: f64,
+    /// e.g. "mg" or "tablets"
+    pub dose_unit: String,
+    /// Times of day, "HH:MM"
+    pub times: Vec<String>,
+    /// Days it applies on, written like `Reminder::days`
+    pub days: String,
+    /// 1 for every such day, 2 for every other day counted from the start
+    /// of the phase, and so on
+    pub every_n_days: i64,
+}
+
+/// The phase of a schedule in effect on a date
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActivePhase {
+    pub schedule_id: String,
+    /// Position in the schedule's phases, from 0
+    pub index: i64,
+    pub phase: SchedulePhase,
+    /// First and last day of the phase, "YYYY-MM-DD"
+    pub start_date: String,
+    pub end_date: String,
+    /// 1 on the first day of the phase
+    pub day_of_phase: i64,
+}
+
+/// One dose due on a day, from `get_agenda`. Times are local, like
+/// reminder times.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgendaItem {
+    /// "YYYY-MM-DD"
+    pub date: String,
+    /// "HH:MM"
+    pub time: String,
+    pub medication_id: String,
+    pub medication_name: String,
+    /// The phase's dose, or the medication's dosage for a reminder
+    pub dose: String,
+    /// Set when the dose comes from a reminder
+    pub reminder_id: Option<String>,
+    /// Set when the dose comes from a phased schedule
+    pub schedule_id: Option<String>,
+    pub phase_index: Option<i64>,
+}
+
+/// A person whose medications are managed, e.g. one member of a family.
+/// Medications belong to one profile; their reminders and doses follow.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Profile {
+    /// Globally unique id (UUID); `None` until the profile is saved
+    pub id: Option<String>,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        let now = Utc::now();
+        Self {
+            id: None,
+            name: String::new(),
+            created_at: now,
+            updated_at: now,
+            deleted_at: None,
+        }
+    }
+}
+
+/// A dose the user took or skipped
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DoseLog {
+    /// Globally unique id (UUID); `None` until the dose is recorded
+    pub id: Option<String>,
+    pub medication_id: String,
+    /// Reminder the dose answered, if it was a scheduled one
+    pub reminder_id: Option<String>,
+    pub status: DoseStatus,
+    /// When the dose was due, for scheduled doses
+    pub scheduled_for: Option<DateTime<Utc>>,
+    /// When the dose was taken or skipped, or found missed
+    pub logged_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DoseStatus {
+    #[default]
+    Taken,
+    Skipped,
+    /// Not logged within the grace window; recorded by `evaluate_missed_doses`
+    Missed,
+}
+
+/// Settings for `evaluate_missed_doses`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MissedDoseOptions {
+    /// How long after its time a dose can still be logged before it is
+    /// recorded as missed
+    pub grace_minutes: i64,
+    /// Remind again this long after a dose's time while it is not logged
+    pub renotify_after_minutes: Option<i64>,
+    /// Alert a caregiver this long after a dose's time while it is not logged
+    pub caregiver_after_minutes: Option<i64>,
+    /// Offset of the user's local time from UTC; reminder times are local
+    pub utc_offset_minutes: i64,
+    /// How far back to look for scheduled doses
+    pub lookback_hours: i64,
+}
+
+impl Default for MissedDoseOptions {
+    fn default() -> Self {
+        Self {
+            grace_minutes: 60,
+            renotify_after_minutes: Some(15),
+            caregiver_after_minutes: None,
+            utc_offset_minutes: 0,
+            lookback_hours: 24,
+        }
+    }
+}
+
+/// Outcome of `evaluate_missed_doses`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MissedDoseReport {
+    /// Doses found missed on this run, oldest first. They are recorded
+    /// unless the database is read-only, in which case their id is `None`.
+    pub missed: Vec<DoseLog>,
+    /// Escalations due by now for doses not taken or skipped, oldest first
+    pub escalations: Vec<EscalationEvent>,
+}
+
+/// Something the app should deliver about a dose that is late
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EscalationEvent {
+    /// The same on every run for the same dose and kind, so each event can
+    /// be delivered once
+    pub id: String,
+    pub kind: EscalationKind,
+    pub medication_id: String,
+    pub medication_name: String,
+    /// Set when the dose comes from a reminder
+    pub reminder_id: Option<String>,
+    pub scheduled_for: DateTime<Utc>,
+    /// When the escalation became due
+    pub due_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EscalationKind {
+    /// Remind the user again
+    Renotify,
+    /// Let a caregiver know the dose has not been taken
+    CaregiverAlert,
+}
+
+/// Whether a dose may be taken now, from `can_take_now`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DoseAvailability {
+    pub allowed: bool,
+    /// Earliest time the next dose is allowed; `None` when allowed now
+    pub next_allowed_at: Option<DateTime<Utc>>,
+    /// Limit that blocks a dose now, or the later one when both do
+    pub reason: Option<DoseLimitReason>,
+    /// Last dose taken in the last 24 hours, or within the minimum
+    /// interval if that is longer
+    pub last_taken_at: Option<DateTime<Utc>>,
+    /// Doses taken in the 24 hours up to now
+    pub doses_last_24h: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DoseLimitReason {
+    /// Too soon after the last dose (`min_hours_between_doses`)
+    MinimumInterval,
+    /// `max_daily_doses` already taken in the last 24 hours
+    DailyMaximum,
+}
+
+/// A course of treatment and how far along it is, from
+/// `get_courses_ending_soon`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CourseProgress {
+    pub medication_id: String,
+    pub medication_name: String,
+    /// Last day of the course, "YYYY-MM-DD": its end date, or the day the
+    /// remaining doses fall due if that is sooner
+    pub end_date: String,
+    /// Whether `end_date` is projected from the schedule rather than set
+    pub end_projected: bool,
+    /// Days from today to `end_date`; 0 when the course ends today
+    pub days_remaining: i64,
+    pub doses_taken: i64,
+    /// Doses still to take, when the course has a total
+    pub doses_remaining: Option<i64>,
+}
+
+/// A local notification for the OS scheduler, from `build_notification_plan`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationRequest {
+    /// The same for the same dose in every plan, so the app can diff a new
+    /// plan against what it has scheduled
+    pub id: String,
+    /// `id` as a non-negative 32-bit number, for schedulers that need an
+    /// integer id (e.g. Android). Unique within a plan: in the rare case
+    /// of two ids hashing alike, the later one takes the next free number.
+    pub numeric_id: i32,
+    pub fire_at: DateTime<Utc>,
+    pub title: String,
+    pub body: String,
+    /// Shared by notifications that fire at the same time, so they can be
+    /// stacked (Android group, iOS thread identifier)
+    pub group_key: String,
+    pub medication_id: String,
+}
+
+/// Outcome of `create_medication`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreatedMedication {
+    pub id: String,
+    /// Known interactions with the profile's current medications, most
+    /// severe first
+    pub warnings: Vec<InteractionWarning>,
+    /// False if the current list could not be read (e.g. while offline), so
+    /// no warnings does not mean no interactions
+    pub interactions_checked: bool,
+}
+
+/// A known interaction between a medication and one already on the list
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InteractionWarning {
+    /// The medication already on the list
+    pub medication_id: String,
+    pub medication_name: String,
+    /// Ingredient of the medication being checked, as named in the dataset
+    pub ingredient: String,
+    /// Ingredient of the listed medication it interacts with
+    pub other_ingredient: String,
+    pub severity: InteractionSeverity,
+    pub description: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum InteractionSeverity {
+    Minor,
+    Moderate,
+    Major,
+    /// The combination should be avoided
+    Contraindicated,
+}
+
+/// A product in the drug catalog, for autocompleting a new medication
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CatalogEntry {
+    pub name: String,
+    /// e.g. "5 mg"; empty when the catalog does not say
+    pub strength: String,
+    /// Dose form, e.g. "Oral Tablet"; empty when the catalog does not say
+    pub form: String,
+    pub rxnorm_cui: Option<String>,
+    pub ndc: Option<String>,
+    pub atc_code: Option<String>,
+}
+
+/// How an update resolves a version conflict with the stored copy
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MergePolicy {
+    /// Fail with a conflict error carrying the stored copy
+    #[default]
+    Reject,
+    /// Overwrite the stored copy with the update
+    LastWriterWins,
+    /// Apply only the fields the caller changed relative to the copy it
+    /// started from; conflicts remain when both sides changed the same field
+    FieldLevelMerge,
+}
+
+/// Column used to order the results of a medication query
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MedicationSortField {
+    Name,
+    #[default]
+    CreatedAt,
+    UpdatedAt,
+}
+
+/// Filter, sort and pagination parameters for listing medications
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MedicationQuery {
+    /// Profile to list; `None` lists the active profile
+    pub profile_id: Option<String>,
+    /// Case-insensitive text matched against name and description
+    pub search: Option<String>,
+    /// Include soft-deleted medications (and their reminders)
+    pub include_deleted: bool,
+    pub sort_by: MedicationSortField,
+    pub descending: bool,
+    /// Maximum number of medications to return; `None` returns all matches
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+/// One page of medications returned by a `MedicationQuery`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MedicationPage {
+    pub medications: Vec<Medication>,
+    /// Number of medications matching the query, ignoring limit/offset
+    pub total_count: i64,
+    /// Offset to request the next page with, or `None` on the last page
+    pub next_offset: Option<i64>,
+}
+
+/// State of the offline write queue
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OutboxStatus {
+    /// Writes waiting for the remote database to become reachable
+    pub pending_count: i64,
+    /// Writes the remote rejected during replay; they stay until discarded
+    pub failures: Vec<OutboxFailure>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutboxFailure {
+    /// Position in the queue, used to discard the entry
+    pub seq: i64,
+    /// Name of the `PillMomApi` method that was queued, e.g. "update_medication"
+    pub operation: String,
+    /// Id of the medication or reminder the write targeted
+    pub record_id: String,
+    pub error: String,
+    pub queued_at: DateTime<Utc>,
+    pub failed_at: DateTime<Utc>,
+}
+
+/// How `import_json` treats data already in the database
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ImportMode {
+    /// Keep existing records; imported ones are added, or replace a stored
+    /// copy with the same id when they were updated more recently
+    #[default]
+    Merge,
+    /// Delete all existing medications, reminders and doses first, and
+    /// the profiles too when the export has any. Exports from before
+    /// profiles existed are loaded into the active profile.
+    Replace,
+}
+
+/// Counts of what an import changed
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ImportSummary {
+    pub profiles_added: i64,
+    pub profiles_updated: i64,
+    pub medications_added: i64,
+    pub medications_updated: i64,
+    pub reminders_added: i64,
+    pub reminders_updated: i64,
+    pub schedules_added: i64,
+    pub schedules_updated: i64,
+    pub doses_added: i64,
+    /// Records skipped because the stored copy is as new or newer, or the
+    /// dose is already recorded
+    pub unchanged: i64,
+    /// Records given a new id because theirs was missing, malformed or
+    /// repeated within the import
+    pub ids_remapped: i64,
+}
+
+/// FHIR R4 resource each medication is exported as
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FhirResourceType {
+    /// What the patient reports taking; the usual choice for sharing a list
+    #[default]
+    MedicationStatement,
+    /// Medication orders, marked as reported by the patient
+    MedicationRequest,
+}
+
+/// Which CSV header holds each field when importing. Headers are matched
+/// case-insensitively. An optional field that is `None`, or whose header is
+/// not in the file, is left at its default.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CsvColumnMapping {
+    pub name: String,
+    pub dosage: Option<String>,
+    pub description: Option<String>,
+    /// Rows sharing a value here belong to one medication; without it,
+    /// rows with the same name, dosage and description are grouped
+    pub medication_id: Option<String>,
+    pub time: Option<String>,
+    pub days: Option<String>,
+    pub is_active: Option<String>,
+}
+
+impl Default for CsvColumnMapping {
+    /// The headers written by `export_csv`
+    fn default() -> Self {
+        Self {
+            name: "name".to_string(),
+            dosage: Some("dosage".to_string()),
+            description: Some("description".to_string()),
+            medication_id: Some("medication_id".to_string()),
+            time: Some("time".to_string()),
+            days: Some("days".to_string()),
+            is_active: Some("is_active".to_string()),
+        }
+    }
+}
+
+/// Outcome of a CSV import. Nothing is written unless `errors` is empty.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CsvImportReport {
+    pub dry_run: bool,
+    /// Whether the rows were written to the database
+    pub committed: bool,
+    pub rows_read: i64,
+    /// Medications and reminders in the valid rows; written only when
+    /// `committed`
+    pub medications_created: i64,
+    pub reminders_created: i64,
+    pub errors: Vec<CsvRowError>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CsvRowError {
+    /// Line in the file where the row starts; the header is line 1
+    pub line: i64,
+    /// Header of the offending column, if the error concerns one
+    pub column: Option<String>,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum IntegrityIssueKind {
+    /// SQLite found the file damaged (`PRAGMA integrity_check`)
+    Corruption,
+    /// A row refers to a row that does not exist (`PRAGMA foreign_key_check`)
+    ForeignKey,
+    /// A live reminder belongs to a deleted medication
+    OrphanedReminder,
+    /// An active reminder has a time that cannot be read
+    InvalidTime,
+    /// An active reminder has days that cannot be read
+    InvalidDays,
+    /// A medication or reminder has no record id
+    MissingId,
+    /// A live phased schedule belongs to a deleted medication
+    OrphanedSchedule,
+    /// A live phased schedule has phases that cannot be read or followed
+    InvalidSchedule,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IntegrityIssue {
+    pub kind: IntegrityIssueKind,
+    /// Table the problem was found in, if known
+    pub table: Option<String>,
+    /// Id of the affected record, if it has one
+    pub record_id: Option<String>,
+    pub message: String,
+    /// Whether repair mode fixed it
+    pub repaired: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IntegrityReport {
+    /// Whether the check was run in repair mode
+    pub repair: bool,
+    /// True when no issues were found or all of them were repaired
+    pub healthy: bool,
+    pub issues: Vec<IntegrityIssue>,
+}
+
+/// Connection settings for the `open_*` methods. The default leaves every
+/// setting as SQLite has it. Pragmas apply to local files (and embedded
+/// replicas); a remote server manages its own, so they are ignored there.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OpenOptions {
+    /// Journal mode to switch the file to. `Wal` lets readers and a writer
+    /// use the file at the same time. In-memory databases ignore it.
+    pub journal_mode: Option<JournalMode>,
+    /// How long a statement waits for a lock held by another connection
+    /// (another isolate or process) before failing with "database is locked"
+    pub busy_timeout_ms: Option<i64>,
+    pub synchronous: Option<SynchronousMode>,
+    /// Page cache size per connection, in KiB
+    pub cache_size_kib: Option<i64>,
+    /// Refuse every write with `PillMomError::ReadOnly`, e.g. for a
+    /// caregiver viewing a patient's data. The database must exist and be at
+    /// the current schema version. Local files are opened without write
+    /// access; for a remote database, pair it with a read-only token.
+    /// Not available for in-memory databases.
+    pub read_only: bool,
+}
+
+/// SQLite `journal_mode`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum JournalMode {
+    Delete,
+    Truncate,
+    Persist,
+    Wal,
+}
+
+/// SQLite `synchronous`: how often writes are flushed to disk
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SynchronousMode {
+    Off,
+    /// Safe from corruption in WAL mode; the last commits may be lost on
+    /// power failure
+    Normal,
+    Full,
+    Extra,
+}
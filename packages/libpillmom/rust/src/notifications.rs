@@ -0,0 +1,246 @@
+use crate::missed::match_dose;
+use crate::models::{DoseStatus, MissedDoseOptions, NotificationRequest};
+use crate::outbox;
+use crate::repository;
+use crate::schedule::{self, DoseSource};
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use std::collections::HashSet;
+
+// Plans the local notifications the app hands to the OS scheduler, which
+// only keeps so many pending (64 on iOS) and forgets them on some reboots
+// (Android alarms). The app rebuilds the plan whenever it runs and
+// reschedules what changed.
+
+/// Furthest ahead a plan may reach, in hours
+const MAX_HORIZON_HOURS: i64 = 31 * 24;
+
+/// Most notifications one plan may hold
+const MAX_NOTIFICATIONS: i64 = 1000;
+
+/// Notifications for the doses due in the `horizon_hours` after `now`, at
+/// most `limit` of them, in firing order. Reminder times are read as local
+/// times at `utc_offset_minutes`. A dose already taken or skipped needs no
+/// notification; doses logged without a time count for a dose due within
+/// the default missed-dose grace window, as `missed::evaluate` counts them.
+pub async fn plan(
+    now: DateTime<Utc>,
+    horizon_hours: i64,
+    limit: i64,
+    utc_offset_minutes: i64,
+) -> Result<Vec<NotificationRequest>> {
+    if !(1..=MAX_HORIZON_HOURS).contains(&horizon_hours) {
+        return Err(anyhow::anyhow!(
+            "Notification horizon must be between 1 and {} hours",
+            MAX_HORIZON_HOURS
+        ));
+    }
+    if !(1..=MAX_NOTIFICATIONS).contains(&limit) {
+        return Err(anyhow::anyhow!(
+            "Notification limit must be between 1 and {}",
+            MAX_NOTIFICATIONS
+        ));
+    }
+    let offset = schedule::utc_offset(utc_offset_minutes)?;
+    let end = now + Duration::hours(horizon_hours);
+
+    let medications = repository::get_all_medications().await?;
+    let schedules = repository::get_schedules().await?;
+    let grace = Duration::minutes(MissedDoseOptions::default().grace_minutes);
+
+    // Doses due in the last day are matched to what was logged first, so a
+    // dose taken late is not taken as one taken early for the next
+    let since = now - Duration::hours(24);
+    let mut doses = repository::get_dose_logs(Some(since - grace), None).await?;
+    for med in &medications {
+        if let Some(id) = med.id.as_deref() {
+            doses.extend(outbox::queued_doses(id).await?);
+        }
+    }
+    doses.retain(|dose| dose.status != DoseStatus::Missed);
+    let mut used = vec![false; doses.len()];
+
+    let mut requests = Vec::new();
+    let mut planned = HashSet::new();
+    for med in medications.iter().filter(|med| med.deleted_at.is_none()) {
+        let Some(medication_id) = med.id.as_deref() else {
+            continue;
+        };
+        let phased = schedules
+            .iter()
+            .find(|schedule| schedule.medication_id == medication_id);
+
+        for (fire_at, due) in schedule::doses_between(med, phased, since, end, offset) {
+            // Reminders of the same medication at the same time need one
+            // notification
+            let id = format!("dose:{}:{}", medication_id, fire_at.timestamp());
+            if !planned.insert(id.clone()) {
+                continue;
+            }
+
+            let (reminder_id, dose) = match due.source {
+                DoseSource::Reminder(reminder) => {
+                    (reminder.id.as_deref(), med.dosage.trim().to_string())
+                }
+                DoseSource::Phase { phase, .. } => (None, schedule::describe_dose(phase)),
+            };
+            let logged = match_dose(
+                medication_id,
+                reminder_id,
+                fire_at,
+                &doses,
+                &mut used,
+                grace,
+            );
+            if logged.is_some() || fire_at <= now {
+                continue;
+            }
+
+            requests.push(NotificationRequest {
+                numeric_id: 0,
+                id,
+                fire_at,
+                title: med.name.clone(),
+                body: if dose.is_empty() {
+                    "Time to take your dose".to_string()
+                } else {
+                    format!("Time to take {}", dose)
+                },
+                group_key: format!("doses:{}", fire_at.timestamp()),
+                medication_id: medication_id.to_string(),
+            });
+        }
+    }
+
+    requests.sort_by(|a, b| {
+        a.fire_at
+            .cmp(&b.fire_at)
+            .then_with(|| a.title.to_lowercase().cmp(&b.title.to_lowercase()))
+            .then_with(|| a.id.cmp(&b.id))
+    });
+
+    let mut requests = prioritise(requests, limit as usize);
+    assign_numeric_ids(&mut requests);
+    Ok(requests)
+}
+
+// ===== Helper Functions =====
+
+/// Keep `limit` of `requests` (in firing order): first the next dose of
+/// each medication, so a medication taken often cannot crowd out the others,
+/// then the rest soonest first
+fn prioritise(requests: Vec<NotificationRequest>, limit: usize) -> Vec<NotificationRequest> {
+    if requests.len() <= limit {
+        return requests;
+    }
+
+    let mut keep = vec![false; requests.len()];
+    let mut kept = 0;
+    let mut medications = HashSet::new();
+    for (index, request) in requests.iter().enumerate() {
+        if kept < limit && medications.insert(request.medication_id.as_str()) {
+            keep[index] = true;
+            kept += 1;
+        }
+    }
+    for flag in keep.iter_mut().filter(|flag| !**flag) {
+        if kept == limit {
+            break;
+        }
+        *flag = true;
+        kept += 1;
+    }
+
+    requests
+        .into_iter()
+        .zip(keep)
+        .filter_map(|(request, keep)| keep.then_some(request))
+        .collect()
+}
+
+/// Give each request the numeric form of its id, or where two ids hash to
+/// the same number, the next one free for the later request, so no two
+/// notifications in a plan share a number
+fn assign_numeric_ids(requests: &mut [NotificationRequest]) {
+    let mut taken = HashSet::new();
+    for request in requests.iter_mut() {
+        let mut numeric_id = numeric_id(&request.id);
+        while !taken.insert(numeric_id) {
+            numeric_id = numeric_id.checked_add(1).unwrap_or(0);
+        }
+        request.numeric_id = numeric_id;
+    }
+}
+
+/// 32-bit FNV-1a hash of `id`, without the sign bit
+fn numeric_id(id: &str) -> i32 {
+    let hash = id.bytes().fold(0x811c_9dc5_u32, |hash, byte| {
+        (hash ^ byte as u32).wrapping_mul(0x0100_0193)
+    });
+    (hash & 0x7fff_ffff) as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(medication_id: &str, minutes: i64) -> NotificationRequest {
+        let fire_at = DateTime::UNIX_EPOCH + Duration::minutes(minutes);
+        NotificationRequest {
+            id: format!("dose:{}:{}", medication_id, fire_at.timestamp()),
+            numeric_id: 0,
+            fire_at,
+            title: medication_id.to_string(),
+            body: String::new(),
+            group_key: format!("doses:{}", fire_at.timestamp()),
+            medication_id: medication_id.to_string(),
+        }
+    }
+
+    fn kept(requests: &[NotificationRequest]) -> Vec<(&str, i64)> {
+        requests
+            .iter()
+            .map(|request| {
+                (
+                    request.medication_id.as_str(),
+                    request.fire_at.timestamp() / 60,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn prioritise_keeps_each_medications_next_dose() {
+        let requests = vec![
+            request("often", 0),
+            request("often", 10),
+            request("often", 20),
+            request("daily", 30),
+            request("often", 40),
+            request("weekly", 50),
+        ];
+
+        assert_eq!(
+            kept(&prioritise(requests.clone(), 4)),
+            [("often", 0), ("often", 10), ("daily", 30), ("weekly", 50)]
+        );
+        assert_eq!(
+            kept(&prioritise(requests.clone(), 2)),
+            [("often", 0), ("daily", 30)]
+        );
+        assert_eq!(prioritise(requests, 6).len(), 6);
+    }
+
+    #[test]
+    fn numeric_ids_are_unique_within_a_plan() {
+        let mut requests = vec![request("a", 0), request("b", 0), request("a", 0)];
+        assign_numeric_ids(&mut requests);
+
+        let hashed = numeric_id(&requests[0].id);
+        assert_eq!(requests[0].numeric_id, hashed);
+        assert_eq!(requests[1].numeric_id, numeric_id(&requests[1].id));
+        // The same id again takes the next free number
+        assert_eq!(requests[2].numeric_id, hashed.checked_add(1).unwrap_or(0));
+        assert!(requests.iter().all(|request| request.numeric_id >= 0));
+    }
+}
@@ -0,0 +1,605 @@
+use crate::database;
+use crate::models::{
+    DoseLog, Medication, MergePolicy, OutboxFailure, OutboxStatus, PhasedSchedule, Profile,
+    Reminder,
+};
+use crate::repository::{self, format_timestamp, parse_timestamp};
+use anyhow::Result;
+use chrono::Utc;
+use libsql::{params, Builder, Connection, Database};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, RwLock};
+use tokio::sync::Mutex;
+
+// Durable queue of writes made while a remote database is unreachable.
+// Writes go through `submit`; once anything is queued, later writes queue
+// behind it so the remote always sees them in the order they were made.
+
+/// The enabled outbox. Set by `enable` and cleared by `disable` when the
+/// database is closed, so a new session never replays writes queued for a
+/// different remote.
+static OUTBOX: RwLock<Option<Arc<Outbox>>> = RwLock::new(None);
+
+struct Outbox {
+    conn: Mutex<Connection>,
+    _handle: Database,
+}
+
+/// A write made through `PillMomApi`, in a form that can be stored and replayed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum Mutation {
+    CreateProfile {
+        profile: Profile,
+    },
+    UpdateProfile {
+        profile: Profile,
+    },
+    DeleteProfile {
+        id: String,
+    },
+    CreateMedication {
+        medication: Medication,
+    },
+    UpdateMedication {
+        medication: Medication,
+        policy: MergePolicy,
+        base: Option<Box<Medication>>,
+    },
+    DeleteMedication {
+        id: String,
+    },
+    CreateReminder {
+        reminder: Reminder,
+    },
+    UpdateReminder {
+        reminder: Reminder,
+        policy: MergePolicy,
+        base: Option<Reminder>,
+    },
+    DeleteReminder {
+        id: String,
+    },
+    CreateSchedule {
+        schedule: PhasedSchedule,
+    },
+    UpdateSchedule {
+        schedule: PhasedSchedule,
+    },
+    DeleteSchedule {
+        id: String,
+    },
+    RecordDose {
+        dose: DoseLog,
+    },
+    EndCourse {
+        medication_id: String,
+    },
+}
+
+/// Result of a mutation, whether applied now or queued for later
+pub enum Applied {
+    /// Id of the created record
+    Created(String),
+    /// Whether the targeted record was changed; queued writes report `true`
+    Changed(bool),
+}
+
+impl Applied {
+    pub fn id(self) -> String {
+        match self {
+            Applied::Created(id) => id,
+            Applied::Changed(_) => String::new(),
+        }
+    }
+
+    pub fn changed(self) -> bool {
+        match self {
+            Applied::Created(_) => true,
+            Applied::Changed(changed) => changed,
+        }
+    }
+}
+
+impl Mutation {
+    fn name(&self) -> &'static str {
+        match self {
+            Mutation::CreateProfile { .. } => "create_profile",
+            Mutation::UpdateProfile { .. } => "update_profile",
+            Mutation::DeleteProfile { .. } => "delete_profile",
+            Mutation::CreateMedication { .. } => "create_medication",
+            Mutation::UpdateMedication { .. } => "update_medication",
+            Mutation::DeleteMedication { .. } => "delete_medication",
+            Mutation::CreateReminder { .. } => "create_reminder",
+            Mutation::UpdateReminder { .. } => "update_reminder",
+            Mutation::DeleteReminder { .. } => "delete_reminder",
+            Mutation::CreateSchedule { .. } => "create_schedule",
+            Mutation::UpdateSchedule { .. } => "update_schedule",
+            Mutation::DeleteSchedule { .. } => "delete_schedule",
+            Mutation::RecordDose { .. } => "record_dose",
+            Mutation::EndCourse { .. } => "end_course",
+        }
+    }
+
+    fn record_id(&self) -> String {
+        let id = match self {
+            Mutation::CreateProfile { profile } | Mutation::UpdateProfile { profile } => {
+                profile.id.as_deref()
+            }
+            Mutation::CreateMedication { medication }
+            | Mutation::UpdateMedication { medication, .. } => medication.id.as_deref(),
+            Mutation::CreateReminder { reminder } | Mutation::UpdateReminder { reminder, .. } => {
+                reminder.id.as_deref()
+            }
+            Mutation::CreateSchedule { schedule } | Mutation::UpdateSchedule { schedule } => {
+                schedule.id.as_deref()
+            }
+            Mutation::DeleteProfile { id }
+            | Mutation::DeleteMedication { id }
+            | Mutation::DeleteReminder { id }
+            | Mutation::DeleteSchedule { id }
+            | Mutation::EndCourse { medication_id: id } => Some(id.as_str()),
+            Mutation::RecordDose { dose } => dose.id.as_deref(),
+        };
+        id.unwrap_or_default().to_string()
+    }
+
+    fn version(&self) -> Option<i64> {
+        match self {
+            Mutation::UpdateMedication { medication, .. } => Some(medication.version),
+            Mutation::UpdateReminder { reminder, .. } => Some(reminder.version),
+            Mutation::UpdateSchedule { schedule } => Some(schedule.version),
+            _ => None,
+        }
+    }
+
+    fn set_version(&mut self, version: i64) {
+        match self {
+            Mutation::UpdateMedication { medication, .. } => medication.version = version,
+            Mutation::UpdateReminder { reminder, .. } => reminder.version = version,
+            Mutation::UpdateSchedule { schedule } => schedule.version = version,
+            _ => {}
+        }
+    }
+
+    async fn apply(&self) -> Result<Applied> {
+        Ok(match self {
+            Mutation::CreateProfile { profile } => {
+                Applied::Created(repository::create_profile(profile).await?)
+            }
+            Mutation::UpdateProfile { profile } => {
+                Applied::Changed(repository::update_profile(profile).await?)
+            }
+            Mutation::DeleteProfile { id } => {
+                Applied::Changed(repository::delete_profile(id).await?)
+            }
+            Mutation::CreateMedication { medication } => {
+                Applied::Created(repository::create_medication(medication).await?)
+            }
+            Mutation::UpdateMedication {
+                medication,
+                policy,
+                base,
+            } => Applied::Changed(
+                repository::update_medication(medication, *policy, base.as_deref()).await?,
+            ),
+            Mutation::DeleteMedication { id } => {
+                Applied::Changed(repository::delete_medication(id).await?)
+            }
+            Mutation::CreateReminder { reminder } => {
+                Applied::Created(repository::create_reminder(reminder).await?)
+            }
+            Mutation::UpdateReminder {
+                reminder,
+                policy,
+                base,
+            } => Applied::Changed(
+                repository::update_reminder(reminder, *policy, base.as_ref()).await?,
+            ),
+            Mutation::DeleteReminder { id } => {
+                Applied::Changed(repository::delete_reminder(id).await?)
+            }
+            Mutation::CreateSchedule { schedule } => {
+                Applied::Created(repository::create_schedule(schedule).await?)
+            }
+            Mutation::UpdateSchedule { schedule } => {
+                Applied::Changed(repository::update_schedule(schedule).await?)
+            }
+            Mutation::DeleteSchedule { id } => {
+                Applied::Changed(repository::delete_schedule(id).await?)
+            }
+            Mutation::RecordDose { dose } => Applied::Created(repository::record_dose(dose).await?),
+            Mutation::EndCourse { medication_id } => {
+                Applied::Changed(repository::end_course(medication_id).await?)
+            }
+        })
+    }
+
+    /// What the caller is told when the mutation is queued instead of applied
+    fn queued_result(&self) -> Applied {
+        match self {
+            Mutation::CreateProfile { .. }
+            | Mutation::CreateMedication { .. }
+            | Mutation::CreateReminder { .. }
+            | Mutation::CreateSchedule { .. }
+            | Mutation::RecordDose { .. } => Applied::Created(self.record_id()),
+            _ => Applied::Changed(true),
+        }
+    }
+}
+
+/// Open (or create) the outbox file. Queuing only takes effect while the
+/// main database is remote.
+pub async fn enable(path: &str) -> Result<()> {
+    let db = Builder::new_local(path).build().await?;
+    let conn = db.connect()?;
+
+    conn.execute(
+        r#"
+        CREATE TABLE IF NOT EXISTS outbox (
+            seq INTEGER PRIMARY KEY AUTOINCREMENT,
+            operation TEXT NOT NULL,
+            record_id TEXT NOT NULL,
+            payload TEXT NOT NULL,
+            queued_at TEXT NOT NULL,
+            last_error TEXT,
+            failed_at TEXT
+        )
+        "#,
+        (),
+    )
+    .await?;
+
+    let mut outbox = OUTBOX.write().unwrap_or_else(|e| e.into_inner());
+    if outbox.is_some() {
+        return Err(anyhow::anyhow!("Outbox is already enabled"));
+    }
+    *outbox = Some(Arc::new(Outbox {
+        conn: Mutex::new(conn),
+        _handle: db,
+    }));
+
+    Ok(())
+}
+
+/// Stop queuing, once any replay or write in progress has finished. Queued
+/// mutations stay in the file and are replayed when it is enabled again.
+pub async fn disable() {
+    let outbox = OUTBOX.write().unwrap_or_else(|e| e.into_inner()).take();
+    if let Some(outbox) = outbox {
+        drop(outbox.conn.lock().await);
+    }
+}
+
+fn current() -> Option<Arc<Outbox>> {
+    OUTBOX.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// Apply a mutation, or queue it if the remote database is unreachable
+pub async fn submit(mut mutation: Mutation) -> Result<Applied> {
+    // Checked before queueing, so nothing is queued for a read-only remote
+    database::ensure_writable()?;
+
+    let outbox = match current() {
+        Some(outbox) if database::is_remote() => outbox,
+        _ => return mutation.apply().await,
+    };
+
+    // Holding the outbox for the whole call keeps concurrent writes from
+    // overtaking each other or a replay in progress
+    let conn = outbox.conn.lock().await;
+
+    if replay(&conn).await? > 0 {
+        enqueue(&conn, &mut mutation).await?;
+        return Ok(mutation.queued_result());
+    }
+
+    match mutation.apply().await {
+        Err(e) if is_offline_error(&e) => {
+            tracing::info!("Remote unreachable, queued {}: {}", mutation.name(), e);
+            enqueue(&conn, &mut mutation).await?;
+            Ok(mutation.queued_result())
+        }
+        result => result,
+    }
+}
+
+/// Replay queued mutations now and report what is left
+pub async fn flush() -> Result<OutboxStatus> {
+    let Some(outbox) = current() else {
+        return Ok(OutboxStatus::default());
+    };
+    database::ensure_writable()?;
+
+    let conn = outbox.conn.lock().await;
+    replay(&conn).await?;
+    read_status(&conn).await
+}
+
+pub async fn status() -> Result<OutboxStatus> {
+    let Some(outbox) = current() else {
+        return Ok(OutboxStatus::default());
+    };
+
+    let conn = outbox.conn.lock().await;
+    read_status(&conn).await
+}
+
+/// Drop a queued or failed mutation without applying it
+pub async fn discard(seq: i64) -> Result<bool> {
+    let outbox = current().ok_or_else(|| anyhow::anyhow!("Outbox is not enabled"))?;
+
+    let conn = outbox.conn.lock().await;
+    let removed = conn
+        .execute("DELETE FROM outbox WHERE seq = ?", params![seq])
+        .await?;
+
+    Ok(removed > 0)
+}
+
+/// Doses of `medication_id` recorded but still waiting in the queue
+pub async fn queued_doses(medication_id: &str) -> Result<Vec<DoseLog>> {
+    let Some(outbox) = current() else {
+        return Ok(Vec::new());
+    };
+
+    let conn = outbox.conn.lock().await;
+    let mut rows = conn
+        .query(
+            "SELECT payload FROM outbox
+             WHERE operation = 'record_dose' AND failed_at IS NULL ORDER BY seq",
+            (),
+        )
+        .await?;
+
+    let mut doses = Vec::new();
+    while let Some(row) = rows.next().await? {
+        if let Mutation::RecordDose { dose } = serde_json::from_str(&row.get::<String>(0)?)? {
+            if dose.medication_id == medication_id {
+                doses.push(dose);
+            }
+        }
+    }
+
+    Ok(doses)
+}
+
+// ===== Helper Functions =====
+
+async fn enqueue(conn: &Connection, mutation: &mut Mutation) -> Result<()> {
+    // An update queued behind another update of the same record must
+    // expect the version the earlier one will produce when replayed
+    if mutation.version().is_some() {
+        let mut rows = conn
+            .query(
+                "SELECT payload FROM outbox
+                 WHERE record_id = ? AND operation = ? AND failed_at IS NULL
+                 ORDER BY seq DESC LIMIT 1",
+                params![mutation.record_id(), mutation.name()],
+            )
+            .await?;
+
+        if let Some(row) = rows.next().await? {
+            let previous: Mutation = serde_json::from_str(&row.get::<String>(0)?)?;
+            if let Some(version) = previous.version() {
+                mutation.set_version(version + 1);
+            }
+        }
+    }
+
+    conn.execute(
+        "INSERT INTO outbox (operation, record_id, payload, queued_at) VALUES (?, ?, ?, ?)",
+        params![
+            mutation.name(),
+            mutation.record_id(),
+            serde_json::to_string(mutation)?,
+            format_timestamp(Utc::now())
+        ],
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Apply pending mutations oldest first. Stops at the first one that cannot
+/// reach the remote; any other error marks that item failed and moves on.
+/// Returns the number of mutations still pending.
+async fn replay(conn: &Connection) -> Result<i64> {
+    let mut rows = conn
+        .query(
+            "SELECT seq, payload FROM outbox WHERE failed_at IS NULL ORDER BY seq",
+            (),
+        )
+        .await?;
+
+    let mut pending = Vec::new();
+    while let Some(row) = rows.next().await? {
+        pending.push((row.get::<i64>(0)?, row.get::<String>(1)?));
+    }
+
+    for (index, (seq, payload)) in pending.iter().enumerate() {
+        let result = match serde_json::from_str::<Mutation>(payload) {
+            Ok(mutation) => mutation.apply().await.map(|_| ()),
+            Err(e) => Err(e.into()),
+        };
+
+        match result {
+            Ok(()) => {
+                conn.execute("DELETE FROM outbox WHERE seq = ?", params![*seq])
+                    .await?;
+            }
+            Err(e) if is_offline_error(&e) => {
+                return Ok((pending.len() - index) as i64);
+            }
+            Err(e) => {
+                tracing::warn!("Queued mutation {} failed: {}", seq, e);
+                conn.execute(
+                    "UPDATE outbox SET last_error = ?, failed_at = ? WHERE seq = ?",
+                    params![e.to_string(), format_timestamp(Utc::now()), *seq],
+                )
+                .await?;
+            }
+        }
+    }
+
+    Ok(0)
+}
+
+async fn read_status(conn: &Connection) -> Result<OutboxStatus> {
+    let mut rows = conn
+        .query("SELECT COUNT(*) FROM outbox WHERE failed_at IS NULL", ())
+        .await?;
+    let pending_count: i64 = match rows.next().await? {
+        Some(row) => row.get(0)?,
+        None => 0,
+    };
+
+    let mut rows = conn
+        .query(
+            "SELECT seq, operation, record_id, last_error, queued_at, failed_at
+             FROM outbox WHERE failed_at IS NOT NULL ORDER BY seq",
+            (),
+        )
+        .await?;
+
+    let mut failures = Vec::new();
+    while let Some(row) = rows.next().await? {
+        failures.push(OutboxFailure {
+            seq: row.get(0)?,
+            operation: row.get(1)?,
+            record_id: row.get(2)?,
+            error: row.get::<Option<String>>(3)?.unwrap_or_default(),
+            queued_at: parse_timestamp(&row.get::<String>(4)?)?,
+            failed_at: parse_timestamp(&row.get::<String>(5)?)?,
+        });
+    }
+
+    Ok(OutboxStatus {
+        pending_count,
+        failures,
+    })
+}
+
+/// Whether an error means the remote could not be reached, as opposed to
+/// the remote rejecting the write
+fn is_offline_error(error: &anyhow::Error) -> bool {
+    error.chain().any(is_offline_cause)
+}
+
+fn is_offline_cause(cause: &(dyn std::error::Error + 'static)) -> bool {
+    if let Some(e) = cause.downcast_ref::<std::io::Error>() {
+        return matches!(
+            e.kind(),
+            std::io::ErrorKind::ConnectionRefused
+                | std::io::ErrorKind::ConnectionReset
+                | std::io::ErrorKind::ConnectionAborted
+                | std::io::ErrorKind::NotConnected
+                | std::io::ErrorKind::TimedOut
+        );
+    }
+
+    match cause.downcast_ref::<libsql::Error>() {
+        Some(libsql::Error::ConnectionFailed(_)) => true,
+        Some(libsql::Error::Hrana(inner)) => match hrana_failure(inner.as_ref()) {
+            HranaFailure::Transport => true,
+            // The edge answered but the database behind it did not
+            HranaFailure::Status(status) => matches!(status, 502..=504),
+            HranaFailure::Other => false,
+        },
+        _ => false,
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum HranaFailure {
+    /// The request never got an HTTP response
+    Transport,
+    /// The remote answered with a non-success HTTP status
+    Status(u16),
+    Other,
+}
+
+/// Classify the error libsql wraps in `Error::Hrana`. Its type is private to
+/// libsql, so the variant is read from the derived `Debug` output: `Http`
+/// carries transport failures and `Api` carries `status=<code>, body=...`.
+fn hrana_failure(inner: &(dyn std::error::Error + Send + Sync)) -> HranaFailure {
+    let debug = format!("{:?}", inner);
+    if debug.starts_with("Http(") {
+        return HranaFailure::Transport;
+    }
+
+    debug
+        .strip_prefix("Api(")
+        .and_then(|rest| rest.trim_start_matches('"').strip_prefix("status="))
+        .and_then(|rest| {
+            let digits: String = rest.chars().take_while(|c| c.is_ascii_digit()).collect();
+            digits.parse().ok()
+        })
+        .map_or(HranaFailure::Other, HranaFailure::Status)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Stands in for libsql's private Hrana error, which derives `Debug`
+    #[derive(Debug)]
+    enum FakeHranaError {
+        Http(String),
+        Api(String),
+        StreamClosed(String),
+    }
+
+    impl std::fmt::Display for FakeHranaError {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            match self {
+                FakeHranaError::Http(message) => write!(f, "http error: `{}`", message),
+                FakeHranaError::Api(message) => write!(f, "api error: `{}`", message),
+                FakeHranaError::StreamClosed(message) => {
+                    write!(f, "stream closed: `{}`", message)
+                }
+            }
+        }
+    }
+
+    impl std::error::Error for FakeHranaError {}
+
+    fn hrana(error: FakeHranaError) -> anyhow::Error {
+        libsql::Error::Hrana(Box::new(error)).into()
+    }
+
+    #[test]
+    fn transport_and_gateway_failures_are_offline() {
+        assert!(is_offline_error(&hrana(FakeHranaError::Http(
+            "hyper::Error(Connect, ConnectError(\"tcp connect error\"))".into()
+        ))));
+        assert!(is_offline_error(&hrana(FakeHranaError::Api(
+            "status=503 Service Unavailable, body=".into()
+        ))));
+        assert!(is_offline_error(
+            &libsql::Error::ConnectionFailed("refused".into()).into()
+        ));
+        assert!(is_offline_error(&anyhow::Error::from(
+            std::io::Error::from(std::io::ErrorKind::ConnectionRefused)
+        )));
+    }
+
+    #[test]
+    fn rejected_writes_are_not_offline() {
+        assert!(!is_offline_error(&hrana(FakeHranaError::Api(
+            "status=400 Bad Request, body=SQLITE_CONSTRAINT".into()
+        ))));
+        assert!(!is_offline_error(&hrana(FakeHranaError::StreamClosed(
+            "status=503".into()
+        ))));
+        assert!(!is_offline_error(
+            &libsql::Error::SqliteFailure(19, "constraint failed".into()).into()
+        ));
+    }
+
+    #[test]
+    fn status_is_read_from_api_errors() {
+        let error = FakeHranaError::Api("status=504 Gateway Timeout, body=".into());
+        assert_eq!(hrana_failure(&error), HranaFailure::Status(504));
+    }
+}
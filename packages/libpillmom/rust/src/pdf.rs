@@ -0,0 +1,282 @@
+use chrono::{DateTime, Utc};
+
+// Minimal PDF 1.4 writer. It uses the standard Helvetica fonts
+// every PDF reader provides, so nothing is embedded and the output depends
+// only on what is drawn. Text is encoded as WinAnsi; characters outside it
+// are replaced with '?'.
+
+/// A4 in points
+pub const PAGE_WIDTH: f32 = 595.0;
+pub const PAGE_HEIGHT: f32 = 842.0;
+
+#[derive(Debug, Clone, Copy)]
+pub enum Font {
+    Regular,
+    Bold,
+}
+
+impl Font {
+    fn resource(self) -> &'static str {
+        match self {
+            Font::Regular => "F1",
+            Font::Bold => "F2",
+        }
+    }
+
+    /// Width of `text` in points at `size`
+    pub fn width(self, text: &str, size: f32) -> f32 {
+        let units: u32 = text.chars().map(|c| self.char_width(c) as u32).sum();
+        units as f32 * size / 1000.0
+    }
+
+    /// `text`, shortened with "..." if needed to fit in `max_width`
+    pub fn fit(self, text: &str, size: f32, max_width: f32) -> String {
+        if self.width(text, size) <= max_width {
+            return text.to_string();
+        }
+
+        let mut fitted: String = text.to_string();
+        while !fitted.is_empty() && self.width(&format!("{}...", fitted), size) > max_width {
+            fitted.pop();
+        }
+        format!("{}...", fitted.trim_end())
+    }
+
+    /// Break `text` into at most `max_lines` lines of `max_width`, ending
+    /// the last line with "..." if text is left over
+    pub fn wrap(self, text: &str, size: f32, max_width: f32, max_lines: usize) -> Vec<String> {
+        let mut lines: Vec<String> = Vec::new();
+        let mut current = String::new();
+
+        for word in text.split_whitespace() {
+            let candidate = if current.is_empty() {
+                word.to_string()
+            } else {
+                format!("{} {}", current, word)
+            };
+
+            if self.width(&candidate, size) <= max_width || current.is_empty() {
+                current = candidate;
+            } else {
+                lines.push(std::mem::replace(&mut current, word.to_string()));
+            }
+        }
+        if !current.is_empty() {
+            lines.push(current);
+        }
+
+        if lines.len() > max_lines {
+            lines.truncate(max_lines);
+            if let Some(last) = lines.last_mut() {
+                *last = self.fit(&format!("{} ...", last), size, max_width);
+            }
+        }
+
+        lines
+            .into_iter()
+            .map(|line| self.fit(&line, size, max_width))
+            .collect()
+    }
+
+    /// Advance width in 1/1000 em, from the Adobe metrics of the base fonts
+    fn char_width(self, c: char) -> u16 {
+        const REGULAR: [u16; 95] = [
+            278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278,
+            278, // ' '..'/'
+            556, 556, 556, 556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584,
+            556, // '0'..'?'
+            1015, 667, 667, 722, 722, 667, 611, 778, 722, 278, 500, 667, 556, 833, 722,
+            778, // '@'..'O'
+            667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 278, 278, 278, 469,
+            556, // 'P'..'_'
+            333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500, 222, 833, 556,
+            556, // '`'..'o'
+            556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334,
+            584, // 'p'..'~'
+        ];
+        const BOLD: [u16; 95] = [
+            278, 333, 474, 556, 556, 889, 722, 238, 333, 333, 389, 584, 278, 333, 278,
+            278, // ' '..'/'
+            556, 556, 556, 556, 556, 556, 556, 556, 556, 556, 333, 333, 584, 584, 584,
+            611, // '0'..'?'
+            975, 722, 722, 722, 722, 667, 611, 778, 722, 278, 556, 722, 611, 833, 722,
+            778, // '@'..'O'
+            667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 333, 278, 333, 584,
+            556, // 'P'..'_'
+            333, 556, 611, 556, 611, 556, 333, 611, 611, 278, 278, 556, 278, 889, 611,
+            611, // '`'..'o'
+            611, 611, 389, 556, 333, 611, 556, 778, 556, 556, 500, 389, 280, 389,
+            584, // 'p'..'~'
+        ];
+
+        match c {
+            ' '..='~' => {
+                let index = c as usize - ' ' as usize;
+                match self {
+                    Font::Regular => REGULAR[index],
+                    Font::Bold => BOLD[index],
+                }
+            }
+            '\u{2026}' | '\u{2014}' => 1000,
+            // Close enough for layout: the average lowercase width
+            _ => 556,
+        }
+    }
+}
+
+/// Content of one page, drawn in PDF user space (origin bottom left)
+#[derive(Default)]
+pub struct Page {
+    content: Vec<u8>,
+}
+
+impl Page {
+    pub fn new() -> Self {
+        Self {
+            content: Vec::new(),
+        }
+    }
+
+    pub fn text(&mut self, x: f32, y: f32, font: Font, size: f32, text: &str) {
+        self.content.extend_from_slice(
+            format!(
+                "BT /{} {:.2} Tf {:.2} {:.2} Td (",
+                font.resource(),
+                size,
+                x,
+                y
+            )
+            .as_bytes(),
+        );
+        for byte in encode_text(text) {
+            if matches!(byte, b'(' | b')' | b'\\') {
+                self.content.push(b'\\');
+            }
+            self.content.push(byte);
+        }
+        self.content.extend_from_slice(b") Tj ET\n");
+    }
+
+    /// Fill colour for following text: 0 is black, 1 is white
+    pub fn set_gray(&mut self, gray: f32) {
+        self.content
+            .extend_from_slice(format!("{:.2} g\n", gray).as_bytes());
+    }
+
+    /// A horizontal or vertical rule; hairlines are drawn lighter
+    pub fn line(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, width: f32) {
+        let gray = if width < 1.0 { 0.7 } else { 0.0 };
+        self.content.extend_from_slice(
+            format!(
+                "q {:.2} G {:.2} w {:.2} {:.2} m {:.2} {:.2} l S Q\n",
+                gray, width, x1, y1, x2, y2
+            )
+            .as_bytes(),
+        );
+    }
+}
+
+/// A complete PDF file of `pages`, in order
+pub fn document(pages: &[Page], title: &str, created_at: DateTime<Utc>) -> Vec<u8> {
+    let mut info = b"<< /Title (".to_vec();
+    info.extend(
+        encode_text(title)
+            .into_iter()
+            .filter(|b| !matches!(b, b'(' | b')' | b'\\')),
+    );
+    info.extend_from_slice(
+        format!(
+            ") /Producer (libpillmom) /CreationDate (D:{}Z) >>",
+            created_at.format("%Y%m%d%H%M%S")
+        )
+        .as_bytes(),
+    );
+
+    // Objects 1-5 are fixed; each page then adds its page object and its
+    // content stream
+    const FIRST_PAGE: usize = 6;
+    let kids: Vec<String> = (0..pages.len())
+        .map(|index| format!("{} 0 R", FIRST_PAGE + index * 2))
+        .collect();
+
+    let mut objects: Vec<Vec<u8>> = vec![
+        b"<< /Type /Catalog /Pages 2 0 R >>".to_vec(),
+        format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            kids.join(" "),
+            pages.len()
+        )
+        .into_bytes(),
+        b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>"
+            .to_vec(),
+        b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica-Bold /Encoding /WinAnsiEncoding >>"
+            .to_vec(),
+        info,
+    ];
+
+    for (index, page) in pages.iter().enumerate() {
+        objects.push(
+            format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] \
+                 /Resources << /Font << /F1 3 0 R /F2 4 0 R >> >> /Contents {} 0 R >>",
+                PAGE_WIDTH,
+                PAGE_HEIGHT,
+                FIRST_PAGE + index * 2 + 1
+            )
+            .into_bytes(),
+        );
+
+        let mut stream = format!("<< /Length {} >>\nstream\n", page.content.len()).into_bytes();
+        stream.extend_from_slice(&page.content);
+        stream.extend_from_slice(b"\nendstream");
+        objects.push(stream);
+    }
+
+    // The binary comment marks the file as binary for transfer tools
+    let mut output = b"%PDF-1.4\n%\xE2\xE3\xCF\xD3\n".to_vec();
+    let mut offsets = Vec::with_capacity(objects.len());
+
+    for (index, object) in objects.iter().enumerate() {
+        offsets.push(output.len());
+        output.extend_from_slice(format!("{} 0 obj\n", index + 1).as_bytes());
+        output.extend_from_slice(object);
+        output.extend_from_slice(b"\nendobj\n");
+    }
+
+    let xref = output.len();
+    output.extend_from_slice(format!("xref\n0 {}\n", objects.len() + 1).as_bytes());
+    output.extend_from_slice(b"0000000000 65535 f \n");
+    for offset in offsets {
+        output.extend_from_slice(format!("{:010} 00000 n \n", offset).as_bytes());
+    }
+    output.extend_from_slice(
+        format!(
+            "trailer\n<< /Size {} /Root 1 0 R /Info 5 0 R >>\nstartxref\n{}\n%%EOF\n",
+            objects.len() + 1,
+            xref
+        )
+        .as_bytes(),
+    );
+
+    output
+}
+
+/// WinAnsi bytes for `text`
+fn encode_text(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|c| match c {
+            ' '..='~' => c as u8,
+            '\u{a0}'..='\u{ff}' => c as u32 as u8,
+            '\u{20ac}' => 0x80,
+            '\u{2026}' => 0x85,
+            '\u{2018}' => 0x91,
+            '\u{2019}' => 0x92,
+            '\u{201c}' => 0x93,
+            '\u{201d}' => 0x94,
+            '\u{2022}' => 0x95,
+            '\u{2013}' => 0x96,
+            '\u{2014}' => 0x97,
+            _ => b'?',
+        })
+        .collect()
+}
@@ -0,0 +1,214 @@
+use crate::models::{DoseAvailability, DoseLimitReason, DoseLog, DoseStatus, Medication};
+use crate::outbox;
+use crate::repository;
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+
+// Safety limits for medications taken as needed (PRN): a minimum time
+// between doses and a maximum number of doses in any 24 hours. The limits
+// apply to any medication that sets them, and only doses logged as taken
+// count towards them.
+
+pub async fn can_take_now(medication_id: &str) -> Result<DoseAvailability> {
+    let medication = repository::get_medication(medication_id)
+        .await?
+        .filter(|med| med.deleted_at.is_none())
+        .ok_or_else(|| anyhow::anyhow!("Medication {} not found", medication_id))?;
+
+    let now = Utc::now();
+    let since = now - lookback(&medication);
+
+    let mut doses = repository::get_medication_dose_logs(medication_id, since).await?;
+    // A dose recorded while offline still counts before it reaches the database
+    doses.extend(
+        outbox::queued_doses(medication_id)
+            .await?
+            .into_iter()
+            .filter(|dose| dose.logged_at >= since),
+    );
+
+    Ok(availability(&medication, &doses, now))
+}
+
+/// Check the medication's limits make sense
+pub fn check_limits(medication: &Medication) -> Result<()> {
+    if let Some(hours) = medication.min_hours_between_doses {
+        if !hours.is_finite() || hours <= 0.0 {
+            return Err(anyhow::anyhow!(
+                "Minimum hours between doses must be positive, got {}",
+                hours
+            ));
+        }
+    }
+
+    if let Some(max) = medication.max_daily_doses {
+        if max < 1 {
+            return Err(anyhow::anyhow!(
+                "Maximum daily doses must be at least 1, got {}",
+                max
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+// ===== Helper Functions =====
+
+fn availability(
+    medication: &Medication,
+    doses: &[DoseLog],
+    now: DateTime<Utc>,
+) -> DoseAvailability {
+    let mut taken: Vec<DateTime<Utc>> = doses
+        .iter()
+        .filter(|dose| dose.status == DoseStatus::Taken)
+        .map(|dose| dose.logged_at)
+        .collect();
+    taken.sort();
+
+    let day_start = now - Duration::hours(24);
+    let last_day: Vec<DateTime<Utc>> = taken.iter().copied().filter(|at| *at > day_start).collect();
+
+    let mut blocks = Vec::new();
+
+    if let (Some(hours), Some(last)) = (medication.min_hours_between_doses, taken.last()) {
+        let next = *last + hours_duration(hours);
+        if next > now {
+            blocks.push((next, DoseLimitReason::MinimumInterval));
+        }
+    }
+
+    if let Some(max) = medication.max_daily_doses {
+        let count = last_day.len() as i64;
+        if count >= max {
+            // Another dose fits once this one is more than 24 hours old
+            if let Some(oldest) = last_day.get((count - max) as usize) {
+                blocks.push((*oldest + Duration::hours(24), DoseLimitReason::DailyMaximum));
+            }
+        }
+    }
+
+    // Each limit is met from its own time on, so both are met from the later
+    let block = blocks.into_iter().max_by_key(|(at, _)| *at);
+
+    DoseAvailability {
+        allowed: block.is_none(),
+        next_allowed_at: block.map(|(at, _)| at),
+        reason: block.map(|(_, reason)| reason),
+        last_taken_at: taken.last().copied(),
+        doses_last_24h: last_day.len() as i64,
+    }
+}
+
+/// How far back doses can matter to the limits
+fn lookback(medication: &Medication) -> Duration {
+    let interval = medication
+        .min_hours_between_doses
+        .map(hours_duration)
+        .unwrap_or_default();
+    interval.max(Duration::hours(24))
+}
+
+fn hours_duration(hours: f64) -> Duration {
+    // Capped so an unchecked value (e.g. from an import) cannot overflow
+    let hours = hours.clamp(0.0, 24.0 * 366.0);
+    Duration::milliseconds((hours * 3_600_000.0).round() as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 3, 4, 12, 0, 0).unwrap()
+    }
+
+    fn medication(min_hours: Option<f64>, max_daily: Option<i64>) -> Medication {
+        Medication {
+            is_prn: true,
+            min_hours_between_doses: min_hours,
+            max_daily_doses: max_daily,
+            ..Default::default()
+        }
+    }
+
+    /// Doses logged `hours_ago` before `now`
+    fn doses(status: DoseStatus, hours_ago: &[i64]) -> Vec<DoseLog> {
+        hours_ago
+            .iter()
+            .map(|hours| DoseLog {
+                id: None,
+                medication_id: "med".to_string(),
+                reminder_id: None,
+                status,
+                scheduled_for: None,
+                logged_at: now() - Duration::hours(*hours),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn minimum_interval_blocks_a_dose() {
+        let med = medication(Some(4.0), None);
+        let checked = availability(&med, &doses(DoseStatus::Taken, &[10, 1]), now());
+
+        assert!(!checked.allowed);
+        assert_eq!(checked.reason, Some(DoseLimitReason::MinimumInterval));
+        assert_eq!(checked.next_allowed_at, Some(now() + Duration::hours(3)));
+        assert_eq!(checked.last_taken_at, Some(now() - Duration::hours(1)));
+
+        // Skipped doses do not count
+        let checked = availability(&med, &doses(DoseStatus::Skipped, &[1]), now());
+        assert!(checked.allowed);
+        assert_eq!(checked.last_taken_at, None);
+    }
+
+    #[test]
+    fn daily_maximum_blocks_a_dose() {
+        let med = medication(None, Some(3));
+        let checked = availability(&med, &doses(DoseStatus::Taken, &[20, 8, 2]), now());
+
+        assert!(!checked.allowed);
+        assert_eq!(checked.reason, Some(DoseLimitReason::DailyMaximum));
+        // Allowed again once the oldest of the three is 24 hours old
+        assert_eq!(checked.next_allowed_at, Some(now() + Duration::hours(4)));
+        assert_eq!(checked.doses_last_24h, 3);
+
+        let checked = availability(&med, &doses(DoseStatus::Taken, &[8, 2]), now());
+        assert!(checked.allowed);
+        assert_eq!(checked.next_allowed_at, None);
+    }
+
+    #[test]
+    fn later_limit_sets_next_allowed_time() {
+        // The daily maximum frees up in 1 hour, the interval in 5
+        let med = medication(Some(6.0), Some(2));
+        let checked = availability(&med, &doses(DoseStatus::Taken, &[23, 1]), now());
+        assert_eq!(checked.reason, Some(DoseLimitReason::MinimumInterval));
+        assert_eq!(checked.next_allowed_at, Some(now() + Duration::hours(5)));
+
+        // The daily maximum frees up in 10 hours, the interval in 1
+        let med = medication(Some(2.0), Some(2));
+        let checked = availability(&med, &doses(DoseStatus::Taken, &[14, 1]), now());
+        assert_eq!(checked.reason, Some(DoseLimitReason::DailyMaximum));
+        assert_eq!(checked.next_allowed_at, Some(now() + Duration::hours(10)));
+    }
+
+    #[test]
+    fn doses_outside_the_window_are_ignored() {
+        let med = medication(Some(4.0), Some(1));
+        let checked = availability(&med, &doses(DoseStatus::Taken, &[30, 25]), now());
+
+        assert!(checked.allowed);
+        assert_eq!(checked.doses_last_24h, 0);
+        assert_eq!(checked.last_taken_at, Some(now() - Duration::hours(25)));
+    }
+
+    #[test]
+    fn lookback_covers_a_long_interval() {
+        assert_eq!(lookback(&medication(None, Some(2))), Duration::hours(24));
+        assert_eq!(lookback(&medication(Some(36.0), None)), Duration::hours(36));
+    }
+}
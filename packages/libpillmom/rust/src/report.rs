@@ -0,0 +1,740 @@
+use crate::models::{DoseLog, DoseStatus, Medication, PhasedSchedule, Reminder};
+use crate::repository;
+use crate::schedule::{self, parse_days, parse_time, DaySet};
+use anyhow::Result;
+use chrono::{DateTime, Duration, FixedOffset, NaiveDate, Utc};
+use std::collections::HashMap;
+
+// Summary for doctor visits: current medications, their schedules
+// and adherence over the last days. The content is computed once and then
+// rendered as HTML or, with the `pdf` feature, PDF. Rendering uses no
+// locale or platform settings, so every platform produces the same bytes.
+
+/// Longest adherence window a report can cover
+const MAX_ADHERENCE_DAYS: i64 = 365;
+
+struct ReportData {
+    generated_at: DateTime<Utc>,
+    /// The reader's time zone; days and times are shown in it
+    offset: FixedOffset,
+    /// First and last local day counted for adherence
+    window_start: NaiveDate,
+    window_end: NaiveDate,
+    rows: Vec<ReportRow>,
+    overall: Adherence,
+}
+
+struct ReportRow {
+    name: String,
+    dosage: String,
+    description: String,
+    /// The phase in effect today, or one line per active reminder
+    schedule: Vec<String>,
+    adherence: Adherence,
+}
+
+#[derive(Default, Clone, Copy)]
+struct Adherence {
+    /// Doses the active reminders and schedules called for in the window
+    expected: i64,
+    taken: i64,
+    skipped: i64,
+    missed: i64,
+}
+
+impl Adherence {
+    /// Share of expected doses taken, rounded to a whole percent
+    fn percent(&self) -> Option<i64> {
+        (self.expected > 0)
+            .then(|| (self.taken.min(self.expected) * 200 + self.expected) / (self.expected * 2))
+    }
+
+    fn add(&mut self, other: Adherence) {
+        self.expected += other.expected;
+        self.taken += other.taken;
+        self.skipped += other.skipped;
+        self.missed += other.missed;
+    }
+}
+
+pub async fn html_report(adherence_days: i64, utc_offset_minutes: i64) -> Result<String> {
+    Ok(render_html(
+        &load(adherence_days, utc_offset_minutes).await?,
+    ))
+}
+
+#[cfg(feature = "pdf")]
+pub async fn pdf_report(adherence_days: i64, utc_offset_minutes: i64) -> Result<Vec<u8>> {
+    Ok(render_pdf(&load(adherence_days, utc_offset_minutes).await?))
+}
+
+#[cfg(not(feature = "pdf"))]
+pub async fn pdf_report(_adherence_days: i64, _utc_offset_minutes: i64) -> Result<Vec<u8>> {
+    Err(anyhow::anyhow!(
+        "This build of pillmom does not support PDF reports (enable the `pdf` feature)"
+    ))
+}
+
+// ===== Report Data =====
+
+/// Gather the report for the `adherence_days` full local days before today,
+/// in the time zone `utc_offset_minutes` from UTC
+async fn load(adherence_days: i64, utc_offset_minutes: i64) -> Result<ReportData> {
+    if !(1..=MAX_ADHERENCE_DAYS).contains(&adherence_days) {
+        return Err(anyhow::anyhow!(
+            "Adherence window must be between 1 and {} days",
+            MAX_ADHERENCE_DAYS
+        ));
+    }
+
+    let offset = schedule::utc_offset(utc_offset_minutes)?;
+    let generated_at = Utc::now();
+    let today = generated_at.with_timezone(&offset).date_naive();
+    let window_start = today - Duration::days(adherence_days);
+
+    let medications = repository::get_all_medications().await?;
+    let schedules = repository::get_schedules().await?;
+    let doses = repository::get_dose_logs(
+        Some(schedule::local_midnight(window_start, offset)),
+        Some(schedule::local_midnight(today, offset)),
+    )
+    .await?;
+
+    Ok(build(
+        &medications,
+        &schedules,
+        &doses,
+        generated_at,
+        offset,
+        window_start,
+        today - Duration::days(1),
+    ))
+}
+
+fn build(
+    medications: &[Medication],
+    schedules: &[PhasedSchedule],
+    doses: &[DoseLog],
+    generated_at: DateTime<Utc>,
+    offset: FixedOffset,
+    window_start: NaiveDate,
+    window_end: NaiveDate,
+) -> ReportData {
+    let mut logged: HashMap<&str, Adherence> = HashMap::new();
+    for dose in doses {
+        let entry = logged.entry(dose.medication_id.as_str()).or_default();
+        match dose.status {
+            DoseStatus::Taken => entry.taken += 1,
+            DoseStatus::Skipped => entry.skipped += 1,
+            DoseStatus::Missed => entry.missed += 1,
+        }
+    }
+
+    let today = generated_at.with_timezone(&offset).date_naive();
+    // Doses due in the window; a dose at midnight after the last day
+    // belongs to the next one
+    let from = schedule::local_midnight(window_start, offset);
+    let to =
+        schedule::local_midnight(window_end + Duration::days(1), offset) - Duration::seconds(1);
+
+    let mut rows: Vec<(&Medication, ReportRow)> = medications
+        .iter()
+        .filter(|med| med.deleted_at.is_none())
+        .map(|med| {
+            let phased = schedules
+                .iter()
+                .find(|schedule| med.id.as_deref() == Some(schedule.medication_id.as_str()));
+
+            let mut adherence = med
+                .id
+                .as_deref()
+                .and_then(|id| logged.get(id).copied())
+                .unwrap_or_default();
+            adherence.expected =
+                schedule::doses_between(med, phased, from, to, offset).len() as i64;
+
+            let schedule = match phased.and_then(|schedule| describe_phase(schedule, today)) {
+                Some(line) => vec![line],
+                None => med
+                    .reminders
+                    .iter()
+                    .filter(|reminder| reminder.is_active && reminder.deleted_at.is_none())
+                    .map(describe_schedule)
+                    .collect(),
+            };
+
+            let row = ReportRow {
+                name: med.name.clone(),
+                dosage: med.dosage.clone(),
+                description: med.description.clone(),
+                schedule,
+                adherence,
+            };
+            (med, row)
+        })
+        .collect();
+
+    rows.sort_by(|(a, _), (b, _)| {
+        a.name
+            .to_lowercase()
+            .cmp(&b.name.to_lowercase())
+            .then_with(|| a.id.cmp(&b.id))
+    });
+
+    let mut overall = Adherence::default();
+    for (_, row) in &rows {
+        overall.add(row.adherence);
+    }
+
+    ReportData {
+        generated_at,
+        offset,
+        window_start,
+        window_end,
+        rows: rows.into_iter().map(|(_, row)| row).collect(),
+        overall,
+    }
+}
+
+/// The phase of `phased` in effect on `date`, e.g.
+/// "Phase 2 of 3: 2.5 mg at 08:00, 20:00 daily until 2026-11-01"
+fn describe_phase(phased: &PhasedSchedule, date: NaiveDate) -> Option<String> {
+    let (index, _, end) = schedule::phase_on(phased, date)?;
+    let phase = &phased.phases[index];
+
+    let days = match parse_days(&phase.days) {
+        Some(DaySet::EveryDay) if phase.every_n_days > 1 => {
+            format!("every {} days", phase.every_n_days)
+        }
+        Some(DaySet::EveryDay) => "daily".to_string(),
+        Some(DaySet::Only(weekdays)) => weekdays
+            .iter()
+            .map(|day| day.to_string())
+            .collect::<Vec<_>>()
+            .join(", "),
+        None => phase.days.clone(),
+    };
+
+    Some(format!(
+        "Phase {} of {}: {} at {} {} until {}",
+        index + 1,
+        phased.phases.len(),
+        schedule::describe_dose(phase),
+        phase.times.join(", "),
+        days,
+        schedule::format_date(end)
+    ))
+}
+
+fn describe_schedule(reminder: &Reminder) -> String {
+    let (Some(time), Some(days)) = (parse_time(&reminder.time), parse_days(&reminder.days)) else {
+        return format!("{} ({})", reminder.time, reminder.days);
+    };
+
+    let days = match days {
+        DaySet::EveryDay => "daily".to_string(),
+        DaySet::Only(weekdays) => weekdays
+            .iter()
+            .map(|day| day.to_string())
+            .collect::<Vec<_>>()
+            .join(", "),
+    };
+
+    format!("{} {}", time.format("%H:%M"), days)
+}
+
+fn adherence_text(adherence: &Adherence) -> String {
+    match adherence.percent() {
+        Some(percent) => format!("{}%", percent),
+        None => "-".to_string(),
+    }
+}
+
+fn adherence_detail(adherence: &Adherence) -> String {
+    let mut detail = format!("{} of {} taken", adherence.taken, adherence.expected);
+    if adherence.skipped > 0 {
+        detail.push_str(&format!(", {} skipped", adherence.skipped));
+    }
+    if adherence.missed > 0 {
+        detail.push_str(&format!(", {} missed", adherence.missed));
+    }
+    detail
+}
+
+fn window_text(data: &ReportData) -> String {
+    format!(
+        "Adherence from {} to {}",
+        data.window_start.format("%Y-%m-%d"),
+        data.window_end.format("%Y-%m-%d")
+    )
+}
+
+/// When the report was made, in the reader's time zone
+fn generated_text(data: &ReportData) -> String {
+    data.generated_at
+        .with_timezone(&data.offset)
+        .format("%Y-%m-%d %H:%M UTC%:z")
+        .to_string()
+}
+
+const FOOTNOTE: &str = "Adherence compares doses logged as taken with the schedules of the \
+    currently active reminders, or the phased schedule on the days it covers. Medications \
+    without either have no expected doses.";
+
+// ===== HTML =====
+
+fn render_html(data: &ReportData) -> String {
+    let mut html = String::new();
+
+    html.push_str(concat!(
+        "<!DOCTYPE html>\n",
+        "<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n",
+        "<title>Medication summary</title>\n<style>\n",
+        "@page { size: A4; margin: 15mm; }\n",
+        "body { font-family: Helvetica, Arial, sans-serif; font-size: 11pt; color: #111; margin: 0; }\n",
+        "h1 { font-size: 18pt; margin: 0 0 4pt; }\n",
+        ".meta, .footnote { color: #555; font-size: 9pt; }\n",
+        "table { width: 100%; border-collapse: collapse; margin: 12pt 0; }\n",
+        "th, td { text-align: left; vertical-align: top; padding: 4pt 6pt; border-bottom: 1px solid #ccc; }\n",
+        "th { border-bottom: 2px solid #111; }\n",
+        ".note, .detail { color: #555; font-size: 9pt; }\n",
+        "</style>\n</head>\n<body>\n",
+        "<h1>Medication summary</h1>\n",
+    ));
+
+    html.push_str(&format!(
+        "<p class=\"meta\">Generated {} &middot; {}</p>\n",
+        generated_text(data),
+        escape_html(&window_text(data))
+    ));
+
+    html.push_str(
+        "<table>\n<thead><tr><th>Medication</th><th>Dosage</th><th>Schedule</th><th>Adherence</th></tr></thead>\n<tbody>\n",
+    );
+
+    if data.rows.is_empty() {
+        html.push_str("<tr><td colspan=\"4\">No current medications</td></tr>\n");
+    }
+
+    for row in &data.rows {
+        html.push_str("<tr><td>");
+        html.push_str(&escape_html(&row.name));
+        if !row.description.is_empty() {
+            html.push_str(&format!(
+                "<div class=\"note\">{}</div>",
+                escape_html(&row.description)
+            ));
+        }
+        html.push_str(&format!("</td><td>{}</td><td>", escape_html(&row.dosage)));
+
+        if row.schedule.is_empty() {
+            html.push_str("No active reminders");
+        } else {
+            let lines: Vec<String> = row.schedule.iter().map(|line| escape_html(line)).collect();
+            html.push_str(&lines.join("<br>"));
+        }
+
+        html.push_str(&format!(
+            "</td><td>{}<div class=\"detail\">{}</div></td></tr>\n",
+            adherence_text(&row.adherence),
+            adherence_detail(&row.adherence)
+        ));
+    }
+
+    html.push_str("</tbody>\n</table>\n");
+    html.push_str(&format!(
+        "<p><strong>Overall adherence: {}</strong> ({})</p>\n",
+        adherence_text(&data.overall),
+        adherence_detail(&data.overall)
+    ));
+    html.push_str(&format!("<p class=\"footnote\">{}</p>\n", FOOTNOTE));
+    html.push_str("</body>\n</html>\n");
+
+    html
+}
+
+fn escape_html(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+// ===== PDF =====
+
+#[cfg(feature = "pdf")]
+fn render_pdf(data: &ReportData) -> Vec<u8> {
+    use crate::pdf::{self, Font, Page, PAGE_HEIGHT, PAGE_WIDTH};
+
+    const MARGIN: f32 = 50.0;
+    const LINE: f32 = 12.0;
+    /// Lowest baseline a table line may use, above the footer
+    const BOTTOM: f32 = 110.0;
+    // Left edge and width of each table column
+    const COLUMNS: [(f32, f32); 4] = [(50.0, 170.0), (226.0, 90.0), (322.0, 150.0), (478.0, 67.0)];
+
+    /// Text drawn on one line of a table row
+    struct Cell {
+        x: f32,
+        font: Font,
+        size: f32,
+        gray: f32,
+        text: String,
+    }
+
+    /// Column titles at `y`; returns the baseline of the first row
+    fn table_header(page: &mut Page, y: f32) -> f32 {
+        for ((x, width), title) in
+            COLUMNS
+                .iter()
+                .zip(["Medication", "Dosage", "Schedule", "Adherence"])
+        {
+            page.text(
+                *x,
+                y,
+                Font::Bold,
+                10.0,
+                &Font::Bold.fit(title, 10.0, *width),
+            );
+        }
+        page.line(MARGIN, y - 5.0, PAGE_WIDTH - MARGIN, y - 5.0, 1.0);
+        y - 5.0 - LINE
+    }
+
+    /// Finish `page` and start another that continues the table
+    fn next_page(pages: &mut Vec<Page>, page: &mut Page) -> f32 {
+        pages.push(std::mem::take(page));
+
+        let y = PAGE_HEIGHT - MARGIN - 10.0;
+        page.set_gray(0.33);
+        page.text(
+            MARGIN,
+            y,
+            Font::Regular,
+            9.0,
+            "Medication summary (continued)",
+        );
+        page.set_gray(0.0);
+        table_header(page, y - 20.0)
+    }
+
+    let mut pages = Vec::new();
+    let mut page = Page::new();
+    let mut y = PAGE_HEIGHT - MARGIN - 18.0;
+
+    page.text(MARGIN, y, Font::Bold, 18.0, "Medication summary");
+    y -= 16.0;
+    page.set_gray(0.33);
+    page.text(
+        MARGIN,
+        y,
+        Font::Regular,
+        9.0,
+        &format!("Generated {} - {}", generated_text(data), window_text(data)),
+    );
+    page.set_gray(0.0);
+    y = table_header(&mut page, y - 24.0);
+
+    if data.rows.is_empty() {
+        page.text(MARGIN, y, Font::Regular, 10.0, "No current medications");
+        y -= LINE;
+    }
+
+    // Whether the table on this page has no rows yet
+    let mut page_empty = true;
+
+    for row in &data.rows {
+        let mut lines: Vec<Vec<Cell>> = Vec::new();
+        let mut put = |line: usize, x: f32, font: Font, size: f32, gray: f32, text: String| {
+            if lines.len() <= line {
+                lines.resize_with(line + 1, Vec::new);
+            }
+            lines[line].push(Cell {
+                x,
+                font,
+                size,
+                gray,
+                text,
+            });
+        };
+
+        let (name_x, name_width) = COLUMNS[0];
+        put(
+            0,
+            name_x,
+            Font::Bold,
+            10.0,
+            0.0,
+            Font::Bold.fit(&row.name, 10.0, name_width),
+        );
+        for (line, text) in Font::Regular
+            .wrap(&row.description, 8.5, name_width, 2)
+            .into_iter()
+            .enumerate()
+        {
+            put(line + 1, name_x, Font::Regular, 8.5, 0.33, text);
+        }
+
+        let (dosage_x, dosage_width) = COLUMNS[1];
+        put(
+            0,
+            dosage_x,
+            Font::Regular,
+            9.5,
+            0.0,
+            Font::Regular.fit(&row.dosage, 9.5, dosage_width),
+        );
+
+        let (schedule_x, schedule_width) = COLUMNS[2];
+        if row.schedule.is_empty() {
+            put(
+                0,
+                schedule_x,
+                Font::Regular,
+                9.5,
+                0.0,
+                "No active reminders".to_string(),
+            );
+        }
+        for (line, text) in row.schedule.iter().enumerate() {
+            let text = Font::Regular.fit(text, 9.5, schedule_width);
+            put(line, schedule_x, Font::Regular, 9.5, 0.0, text);
+        }
+
+        let (adherence_x, adherence_width) = COLUMNS[3];
+        put(
+            0,
+            adherence_x,
+            Font::Bold,
+            10.0,
+            0.0,
+            adherence_text(&row.adherence),
+        );
+        for (line, text) in Font::Regular
+            .wrap(&adherence_detail(&row.adherence), 8.0, adherence_width, 2)
+            .into_iter()
+            .enumerate()
+        {
+            put(line + 1, adherence_x, Font::Regular, 8.0, 0.33, text);
+        }
+
+        // A row is moved whole to the next page when it does not fit;
+        // only one too tall for any page is split
+        let height = lines.len() as f32 * LINE;
+        if y - height + LINE < BOTTOM && !page_empty {
+            y = next_page(&mut pages, &mut page);
+        }
+
+        for cells in &lines {
+            if y < BOTTOM {
+                y = next_page(&mut pages, &mut page);
+            }
+            for cell in cells {
+                page.set_gray(cell.gray);
+                page.text(cell.x, y, cell.font, cell.size, &cell.text);
+            }
+            y -= LINE;
+        }
+        page.set_gray(0.0);
+        page_empty = false;
+
+        y += 4.0;
+        page.line(MARGIN, y, PAGE_WIDTH - MARGIN, y, 0.5);
+        y -= LINE;
+    }
+
+    y -= 4.0;
+    page.text(
+        MARGIN,
+        y,
+        Font::Bold,
+        10.5,
+        &format!(
+            "Overall adherence: {} ({})",
+            adherence_text(&data.overall),
+            adherence_detail(&data.overall)
+        ),
+    );
+
+    page.set_gray(0.33);
+    let footnote_width = PAGE_WIDTH - 2.0 * MARGIN;
+    for (line, text) in Font::Regular
+        .wrap(FOOTNOTE, 8.0, footnote_width, 3)
+        .iter()
+        .enumerate()
+    {
+        page.text(
+            MARGIN,
+            MARGIN + 20.0 - line as f32 * 10.0,
+            Font::Regular,
+            8.0,
+            text,
+        );
+    }
+    pages.push(page);
+
+    if pages.len() > 1 {
+        let count = pages.len();
+        for (index, page) in pages.iter_mut().enumerate() {
+            let number = format!("Page {} of {}", index + 1, count);
+            let x = PAGE_WIDTH - MARGIN - Font::Regular.width(&number, 8.0);
+            page.set_gray(0.33);
+            page.text(x, MARGIN - 20.0, Font::Regular, 8.0, &number);
+        }
+    }
+
+    pdf::document(&pages, "Medication summary", data.generated_at)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(value: &str) -> NaiveDate {
+        schedule::parse_date(value).unwrap()
+    }
+
+    fn at(value: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(value)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    fn medication(id: &str, name: &str, reminders: &[(&str, &str)]) -> Medication {
+        Medication {
+            id: Some(id.to_string()),
+            name: name.to_string(),
+            dosage: "10mg".to_string(),
+            created_at: at("2024-01-01T00:00:00Z"),
+            reminders: reminders
+                .iter()
+                .map(|(time, days)| Reminder {
+                    medication_id: id.to_string(),
+                    time: time.to_string(),
+                    days: days.to_string(),
+                    created_at: at("2024-01-01T00:00:00Z"),
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    fn taken(medication_id: &str, logged_at: &str) -> DoseLog {
+        DoseLog {
+            id: None,
+            medication_id: medication_id.to_string(),
+            reminder_id: None,
+            status: DoseStatus::Taken,
+            scheduled_for: None,
+            logged_at: at(logged_at),
+        }
+    }
+
+    #[test]
+    fn expected_doses_count_local_days() {
+        let offset = schedule::utc_offset(-300).unwrap();
+        // 2024-03-03 is a Sunday; the Sunday dose is Monday in UTC, and the
+        // Monday midnight dose falls just after the window
+        let med = medication(
+            "0190a5b0-0000-7000-8000-000000000002",
+            "Methotrexate",
+            &[("23:30", "Sun"), ("00:00", "Mon")],
+        );
+        let doses = [taken(
+            "0190a5b0-0000-7000-8000-000000000002",
+            "2024-03-04T04:45:00Z",
+        )];
+
+        let data = build(
+            &[med],
+            &[],
+            &doses,
+            at("2024-03-05T03:00:00Z"),
+            offset,
+            date("2024-03-03"),
+            date("2024-03-03"),
+        );
+
+        let adherence = data.rows[0].adherence;
+        assert_eq!(adherence.expected, 1);
+        assert_eq!(adherence.taken, 1);
+        assert_eq!(adherence.percent(), Some(100));
+        assert_eq!(generated_text(&data), "2024-03-04 22:00 UTC-05:00");
+    }
+
+    #[test]
+    fn rows_are_sorted_by_name() {
+        let data = build(
+            &[
+                medication("0190a5b0-0000-7000-8000-000000000002", "zinc", &[]),
+                medication("0190a5b0-0000-7000-8000-000000000003", "Aspirin", &[]),
+            ],
+            &[],
+            &[],
+            at("2024-03-05T03:00:00Z"),
+            schedule::utc_offset(0).unwrap(),
+            date("2024-03-03"),
+            date("2024-03-04"),
+        );
+
+        let names: Vec<&str> = data.rows.iter().map(|row| row.name.as_str()).collect();
+        assert_eq!(names, ["Aspirin", "zinc"]);
+        assert_eq!(data.overall.percent(), None);
+    }
+
+    #[cfg(feature = "pdf")]
+    #[test]
+    fn pdf_continues_on_further_pages() {
+        let medications: Vec<Medication> = (0..80)
+            .map(|index| {
+                medication(
+                    &format!("0190a5b0-0000-7000-8000-{:012}", index),
+                    &format!("Medication {:02}", index),
+                    &[("08:00", "Daily"), ("20:00", "Mon,Wed,Fri")],
+                )
+            })
+            .collect();
+        let data = build(
+            &medications,
+            &[],
+            &[],
+            at("2024-03-05T03:00:00Z"),
+            schedule::utc_offset(0).unwrap(),
+            date("2024-03-03"),
+            date("2024-03-04"),
+        );
+
+        let pdf = render_pdf(&data);
+        let text = String::from_utf8_lossy(&pdf);
+
+        let pages = text.matches("/Type /Page ").count();
+        assert!(pages > 1);
+        assert!(text.contains(&format!("/Count {} ", pages)));
+        assert!(text.contains(&format!("(Page {} of {})", pages, pages)));
+        assert!(!text.contains("not shown"));
+        for index in 0..80 {
+            assert!(text.contains(&format!("(Medication {:02})", index)));
+        }
+
+        // Every cross-reference points at the object it names
+        let xref = text.rfind("\nxref\n").unwrap();
+        let offsets: Vec<usize> = text[xref..]
+            .lines()
+            .filter(|line| line.ends_with(" n "))
+            .map(|line| line[..10].parse().unwrap())
+            .collect();
+        assert_eq!(offsets.len(), 5 + pages * 2);
+        for (number, offset) in offsets.into_iter().enumerate() {
+            assert!(pdf[offset..].starts_with(format!("{} 0 obj", number + 1).as_bytes()));
+        }
+    }
+}
@@ -1,169 +1,443 @@
-use crate::database::get_connection;
-use crate::models::{Medication, Reminder};
+use crate::database::{self, get_connection, get_reader};
+use crate::error::PillMomError;
+use crate::models::{
+    DoseLog, DoseStatus, ImportMode, ImportSummary, Medication, MedicationPage, MedicationQuery,
+    MedicationSortField, MergePolicy, PhasedSchedule, Profile, Reminder, SchedulePhase,
+};
 use anyhow::Result;
-use chrono::Utc;
-use libsql::params;
+use chrono::{DateTime, NaiveDateTime, SecondsFormat, Utc};
+use libsql::{params, params_from_iter, Connection, Row, Value};
+use std::collections::HashMap;
+use uuid::Uuid;
 
-pub async fn create_medication(med: &Medication) -> Result<i64> {
-    let now = Utc::now().to_rfc3339();
+// Records are identified by their `uuid` column everywhere outside this
+// module; the integer `id` is only a rowid used for joins.
 
+/// Columns read by `medication_from_row`, from `medications m`
+macro_rules! medication_select {
+    () => {
+        "SELECT m.uuid, m.name, m.dosage, m.description, m.created_at, m.updated_at, m.deleted_at,
+                m.version, p.uuid, m.rxnorm_cui, m.ndc, m.atc_code, m.is_prn,
+                m.min_hours_between_doses, m.max_daily_doses, m.course_start_date,
+                m.course_end_date, m.course_total_doses
+         FROM medications m LEFT JOIN profiles p ON p.id = m.profile_id"
+    };
+}
+
+/// Columns read by `profile_from_row`, from `profiles p`
+macro_rules! profile_select {
+    () => {
+        "SELECT p.uuid, p.name, p.created_at, p.updated_at, p.deleted_at FROM profiles p"
+    };
+}
+
+/// Columns read by `reminder_from_row`, from `reminders r` joined to its medication
+macro_rules! reminder_select {
+    () => {
+        "SELECT r.uuid, m.uuid, r.time, r.days, r.is_active, r.created_at, r.updated_at, r.deleted_at,
+                r.version
+         FROM reminders r JOIN medications m ON m.id = r.medication_id"
+    };
+}
+
+/// Columns read by `schedule_from_row`, from `phased_schedules s` joined to
+/// its medication; the rowid comes last for loading the phases
+macro_rules! schedule_select {
+    () => {
+        "SELECT s.uuid, m.uuid, s.start_date, s.created_at, s.updated_at, s.deleted_at, s.version,
+                s.id
+         FROM phased_schedules s JOIN medications m ON m.id = s.medication_id"
+    };
+}
+
+/// Columns read by `dose_log_from_row`, from `dose_logs d` joined to its
+/// medication and reminder
+macro_rules! dose_log_select {
+    () => {
+        "SELECT d.uuid, m.uuid, r.uuid, d.status, d.scheduled_for, d.logged_at
+         FROM dose_logs d
+         JOIN medications m ON m.id = d.medication_id
+         LEFT JOIN reminders r ON r.id = d.reminder_id"
+    };
+}
+
+/// Generate a time-ordered, globally unique record id
+pub(crate) fn new_record_id() -> String {
+    Uuid::now_v7().to_string()
+}
+
+pub async fn create_medication(med: &Medication) -> Result<String> {
+    let conn = get_connection().ok_or_else(|| anyhow::anyhow!("No database connection"))?;
+    let conn = conn.lock().await;
+
+    insert_medication(&conn, med).await
+}
+
+/// Create medications and their nested reminders in one transaction, so
+/// either all of them are written or none are. Returns the medication ids.
+pub async fn create_medications_with_reminders(medications: &[Medication]) -> Result<Vec<String>> {
     let conn = get_connection().ok_or_else(|| anyhow::anyhow!("No database connection"))?;
     let conn = conn.lock().await;
 
+    let tx = conn.transaction().await?;
+    let mut ids = Vec::with_capacity(medications.len());
+
+    for med in medications {
+        let id = insert_medication(&tx, med).await?;
+
+        for reminder in &med.reminders {
+            let reminder = Reminder {
+                medication_id: id.clone(),
+                ..reminder.clone()
+            };
+            insert_reminder(&tx, &reminder).await?;
+        }
+
+        ids.push(id);
+    }
+
+    tx.commit().await?;
+
+    Ok(ids)
+}
+
+async fn insert_medication(conn: &Connection, med: &Medication) -> Result<String> {
+    let now = format_timestamp(Utc::now());
+    // Keep a caller-supplied id so records created elsewhere keep their identity
+    let uuid = med.id.clone().unwrap_or_else(new_record_id);
+    let profile = resolve_profile(conn, med.profile_id.as_deref()).await?;
+
     let stmt = conn
         .prepare(
-            "INSERT INTO medications (name, dosage, description, created_at, updated_at)
-             VALUES (?, ?, ?, ?, ?) RETURNING id",
+            "INSERT INTO medications
+                (uuid, profile_id, name, dosage, description, rxnorm_cui, ndc, atc_code, is_prn,
+                 min_hours_between_doses, max_daily_doses, course_start_date, course_end_date,
+                 course_total_doses, created_at, updated_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?) RETURNING uuid",
         )
         .await?;
 
     let mut rows = stmt
         .query(params![
+            uuid,
+            profile,
             med.name.clone(),
             med.dosage.clone(),
             med.description.clone(),
+            med.rxnorm_cui.clone(),
+            med.ndc.clone(),
+            med.atc_code.clone(),
+            med.is_prn as i64,
+            med.min_hours_between_doses,
+            med.max_daily_doses,
+            med.course_start_date.clone(),
+            med.course_end_date.clone(),
+            med.course_total_doses,
             now.clone(),
             now
         ])
         .await?;
 
     if let Some(row) = rows.next().await? {
-        let id: i64 = row.get(0)?;
+        let id: String = row.get(0)?;
         Ok(id)
     } else {
         Err(anyhow::anyhow!("Failed to create medication"))
     }
 }
 
-#[allow(dead_code)]
-pub async fn get_medication(id: i64) -> Result<Option<Medication>> {
-    let conn = get_connection().ok_or_else(|| anyhow::anyhow!("No database connection"))?;
+pub async fn get_medication(id: &str) -> Result<Option<Medication>> {
+    let conn = get_reader().ok_or_else(|| anyhow::anyhow!("No database connection"))?;
     let conn = conn.lock().await;
 
-    let stmt = conn
-        .prepare(
-            "SELECT id, name, dosage, description, created_at, updated_at, deleted_at
-             FROM medications WHERE id = ? AND deleted_at IS NULL",
+    find_medication(&conn, id).await
+}
+
+async fn find_medication(conn: &Connection, id: &str) -> Result<Option<Medication>> {
+    let mut rows = conn
+        .query(
+            concat!(
+                medication_select!(),
+                " WHERE m.uuid = ? AND m.deleted_at IS NULL"
+            ),
+            params![id],
         )
         .await?;
 
-    let mut rows = stmt.query(params![id]).await?;
-
     if let Some(row) = rows.next().await? {
-        Ok(Some(Medication {
-            id: Some(row.get(0)?),
-            name: row.get(1)?,
-            dosage: row.get(2)?,
-            description: row.get(3)?,
-            created_at: row.get::<Option<String>>(4)?.unwrap_or_default(),
-            updated_at: row.get::<Option<String>>(5)?.unwrap_or_default(),
-            deleted_at: row.get::<Option<String>>(6)?,
-            reminders: Vec::new(),
-        }))
+        Ok(Some(medication_from_row(&row)?))
     } else {
         Ok(None)
     }
 }
 
+/// Live medications of the active profile with their reminders
 pub async fn get_all_medications() -> Result<Vec<Medication>> {
-    let conn = get_connection().ok_or_else(|| anyhow::anyhow!("No database connection"))?;
-    let conn = conn.lock().await;
+    let conn = get_reader().ok_or_else(|| anyhow::anyhow!("No database connection"))?;
+    let mut conn = conn.lock().await;
+
+    let profile = resolve_profile(&conn, None).await?;
 
     // Get all medications
     let mut medications = Vec::new();
     let med_stmt = conn
-        .prepare(
-            "SELECT id, name, dosage, description, created_at, updated_at, deleted_at
-             FROM medications WHERE deleted_at IS NULL ORDER BY id",
-        )
+        .prepare_cached(concat!(
+            medication_select!(),
+            " WHERE m.profile_id = ? AND m.deleted_at IS NULL ORDER BY m.id"
+        ))
         .await?;
 
-    let mut med_rows = med_stmt.query(()).await?;
+    let mut med_rows = med_stmt.query(params![profile]).await?;
 
     while let Some(row) = med_rows.next().await? {
-        medications.push(Medication {
-            id: Some(row.get(0)?),
-            name: row.get(1)?,
-            dosage: row.get(2)?,
-            description: row.get(3)?,
-            created_at: row.get::<Option<String>>(4)?.unwrap_or_default(),
-            updated_at: row.get::<Option<String>>(5)?.unwrap_or_default(),
-            deleted_at: row.get::<Option<String>>(6)?,
-            reminders: Vec::new(),
-        });
+        medications.push(medication_from_row(&row)?);
     }
 
-    // Get all reminders and associate with medications
+    // Only reminders whose medication is still live; the join skips the
+    // leftovers of soft-deleted medications instead of scanning them
     let rem_stmt = conn
-        .prepare(
-            "SELECT id, medication_id, time, days, is_active, created_at, updated_at, deleted_at
-             FROM reminders WHERE deleted_at IS NULL ORDER BY medication_id",
-        )
+        .prepare_cached(concat!(
+            reminder_select!(),
+            " WHERE m.profile_id = ? AND r.deleted_at IS NULL AND m.deleted_at IS NULL
+             ORDER BY r.medication_id, r.id"
+        ))
         .await?;
 
-    let mut rem_rows = rem_stmt.query(()).await?;
+    let mut rem_rows = rem_stmt.query(params![profile]).await?;
+    let mut reminders = Vec::new();
 
     while let Some(row) = rem_rows.next().await? {
-        let med_id: i64 = row.get(1)?;
-        let reminder = Reminder {
-            id: Some(row.get(0)?),
-            medication_id: med_id,
-            time: row.get(2)?,
-            days: row.get(3)?,
-            is_active: row.get::<Option<i64>>(4)?.map(|v| v != 0).unwrap_or(true),
-            created_at: row.get::<Option<String>>(5)?.unwrap_or_default(),
-            updated_at: row.get::<Option<String>>(6)?.unwrap_or_default(),
-            deleted_at: row.get::<Option<String>>(7)?,
+        reminders.push(reminder_from_row(&row)?);
+    }
+
+    attach_reminders(&mut medications, reminders);
+
+    Ok(medications)
+}
+
+pub async fn query_medications(query: &MedicationQuery) -> Result<MedicationPage> {
+    let conn = get_reader().ok_or_else(|| anyhow::anyhow!("No database connection"))?;
+    let conn = conn.lock().await;
+
+    let profile = resolve_profile(&conn, query.profile_id.as_deref()).await?;
+
+    let mut conditions = vec!["m.profile_id = ?"];
+    let mut filter_params: Vec<Value> = vec![Value::Integer(profile)];
+
+    if !query.include_deleted {
+        conditions.push("m.deleted_at IS NULL");
+    }
+
+    if let Some(search) = query.search.as_deref().map(str::trim).filter(|s| !s.is_empty()) {
+        let pattern = format!("%{}%", escape_like(search));
+        conditions.push("(m.name LIKE ? ESCAPE '\\' OR m.description LIKE ? ESCAPE '\\')");
+        filter_params.push(Value::Text(pattern.clone()));
+        filter_params.push(Value::Text(pattern));
+    }
+
+    let where_clause = format!("WHERE {}", conditions.join(" AND "));
+
+    // Count matches before paging so callers can render page controls
+    let mut count_rows = conn
+        .query(
+            &format!("SELECT COUNT(*) FROM medications m {}", where_clause),
+            params_from_iter(filter_params.clone()),
+        )
+        .await?;
+    let total_count: i64 = match count_rows.next().await? {
+        Some(row) => row.get(0)?,
+        None => 0,
+    };
+
+    let sort_column = match query.sort_by {
+        MedicationSortField::Name => "m.name COLLATE NOCASE",
+        MedicationSortField::CreatedAt => "m.created_at",
+        MedicationSortField::UpdatedAt => "m.updated_at",
+    };
+    let direction = if query.descending { "DESC" } else { "ASC" };
+
+    let offset = query.offset.unwrap_or(0).max(0);
+    let mut page_params = filter_params;
+    // SQLite treats a negative LIMIT as "no limit"
+    page_params.push(Value::Integer(query.limit.map(|l| l.max(0)).unwrap_or(-1)));
+    page_params.push(Value::Integer(offset));
+
+    let mut med_rows = conn
+        .query(
+            &format!(
+                "{} {} ORDER BY {} {}, m.id {} LIMIT ? OFFSET ?",
+                medication_select!(),
+                where_clause,
+                sort_column,
+                direction,
+                direction
+            ),
+            params_from_iter(page_params),
+        )
+        .await?;
+
+    let mut medications = Vec::new();
+    while let Some(row) = med_rows.next().await? {
+        medications.push(medication_from_row(&row)?);
+    }
+
+    if !medications.is_empty() {
+        let ids: Vec<Value> = medications
+            .iter()
+            .filter_map(|m| m.id.clone())
+            .map(Value::Text)
+            .collect();
+        let placeholders = vec!["?"; ids.len()].join(", ");
+        let deleted_filter = if query.include_deleted {
+            ""
+        } else {
+            "AND r.deleted_at IS NULL"
         };
 
-        // Find the medication and add the reminder
-        if let Some(medication) = medications.iter_mut().find(|m| m.id == Some(med_id)) {
-            medication.reminders.push(reminder);
+        let mut rem_rows = conn
+            .query(
+                &format!(
+                    "{} WHERE m.uuid IN ({}) {} ORDER BY r.medication_id, r.id",
+                    reminder_select!(),
+                    placeholders,
+                    deleted_filter
+                ),
+                params_from_iter(ids),
+            )
+            .await?;
+
+        let mut reminders = Vec::new();
+        while let Some(row) = rem_rows.next().await? {
+            reminders.push(reminder_from_row(&row)?);
         }
+
+        attach_reminders(&mut medications, reminders);
     }
 
-    Ok(medications)
+    let fetched = medications.len() as i64;
+    let next_offset = if offset + fetched < total_count && fetched > 0 {
+        Some(offset + fetched)
+    } else {
+        None
+    };
+
+    Ok(MedicationPage {
+        medications,
+        total_count,
+        next_offset,
+    })
 }
 
-pub async fn update_medication(med: &Medication) -> Result<bool> {
-    let id = med.id.ok_or_else(|| anyhow::anyhow!("Medication ID is required for update"))?;
-    let now = Utc::now().to_rfc3339();
+/// Update a medication if it is still at `med.version`. When another writer
+/// got there first, `policy` decides whether to overwrite, merge against
+/// `base` (the copy the caller started editing from), or fail with
+/// `PillMomError::MedicationConflict`. Returns `false` if the medication
+/// does not exist.
+pub async fn update_medication(
+    med: &Medication,
+    policy: MergePolicy,
+    base: Option<&Medication>,
+) -> Result<bool> {
+    let id = med
+        .id
+        .clone()
+        .ok_or_else(|| anyhow::anyhow!("Medication ID is required for update"))?;
 
     let conn = get_connection().ok_or_else(|| anyhow::anyhow!("No database connection"))?;
     let conn = conn.lock().await;
 
-    conn.execute(
-        "UPDATE medications SET name = ?, dosage = ?, description = ?, updated_at = ?
-         WHERE id = ? AND deleted_at IS NULL",
-        params![
-            med.name.clone(),
-            med.dosage.clone(),
-            med.description.clone(),
-            now,
-            id
-        ],
-    )
-    .await?;
+    if write_medication(&conn, &id, med, med.version).await? {
+        return Ok(true);
+    }
 
-    Ok(true)
+    let Some(current) = find_medication(&conn, &id).await? else {
+        return Ok(false);
+    };
+
+    let resolved = match policy {
+        MergePolicy::Reject => None,
+        MergePolicy::LastWriterWins => Some(med.clone()),
+        MergePolicy::FieldLevelMerge => base.and_then(|base| merge_medication(base, med, &current)),
+    };
+
+    if let Some(resolved) = resolved {
+        if write_medication(&conn, &id, &resolved, current.version).await? {
+            return Ok(true);
+        }
+    }
+
+    // Re-read so the conflict reports what is stored now, not what we
+    // compared against
+    let current = find_medication(&conn, &id).await?.unwrap_or(current);
+    Err(PillMomError::MedicationConflict {
+        current: Box::new(current),
+    }
+    .into())
+}
+
+/// Write `med` over the row only if it is still at `expected_version`
+async fn write_medication(
+    conn: &Connection,
+    id: &str,
+    med: &Medication,
+    expected_version: i64,
+) -> Result<bool> {
+    let now = format_timestamp(Utc::now());
+
+    let updated = conn
+        .execute(
+            "UPDATE medications SET name = ?, dosage = ?, description = ?, rxnorm_cui = ?, ndc = ?,
+                    atc_code = ?, is_prn = ?, min_hours_between_doses = ?, max_daily_doses = ?,
+                    course_start_date = ?, course_end_date = ?, course_total_doses = ?,
+                    updated_at = ?, version = version + 1
+             WHERE uuid = ? AND version = ? AND deleted_at IS NULL",
+            params![
+                med.name.clone(),
+                med.dosage.clone(),
+                med.description.clone(),
+                med.rxnorm_cui.clone(),
+                med.ndc.clone(),
+                med.atc_code.clone(),
+                med.is_prn as i64,
+                med.min_hours_between_doses,
+                med.max_daily_doses,
+                med.course_start_date.clone(),
+                med.course_end_date.clone(),
+                med.course_total_doses,
+                now,
+                id,
+                expected_version
+            ],
+        )
+        .await?;
+
+    Ok(updated > 0)
 }
 
-pub async fn delete_medication(id: i64) -> Result<bool> {
-    let now = Utc::now().to_rfc3339();
+pub async fn delete_medication(id: &str) -> Result<bool> {
+    let now = format_timestamp(Utc::now());
 
     let conn = get_connection().ok_or_else(|| anyhow::anyhow!("No database connection"))?;
     let conn = conn.lock().await;
 
     // Soft delete the medication
     conn.execute(
-        "UPDATE medications SET deleted_at = ? WHERE id = ?",
+        "UPDATE medications SET deleted_at = ? WHERE uuid = ?",
         params![now.clone(), id],
     )
     .await?;
 
-    // Also soft delete associated reminders
+    // Also soft delete associated reminders and schedules
+    conn.execute(
+        "UPDATE reminders SET deleted_at = ?
+         WHERE medication_id = (SELECT id FROM medications WHERE uuid = ?)",
+        params![now.clone(), id],
+    )
+    .await?;
     conn.execute(
-        "UPDATE reminders SET deleted_at = ? WHERE medication_id = ?",
+        "UPDATE phased_schedules SET deleted_at = ?
+         WHERE deleted_at IS NULL
+           AND medication_id = (SELECT id FROM medications WHERE uuid = ?)",
         params![now, id],
     )
     .await?;
@@ -171,69 +445,143 @@ pub async fn delete_medication(id: i64) -> Result<bool> {
     Ok(true)
 }
 
-pub async fn create_reminder(reminder: &Reminder) -> Result<i64> {
-    let now = Utc::now().to_rfc3339();
-
+pub async fn create_reminder(reminder: &Reminder) -> Result<String> {
     let conn = get_connection().ok_or_else(|| anyhow::anyhow!("No database connection"))?;
     let conn = conn.lock().await;
 
+    insert_reminder(&conn, reminder).await
+}
+
+async fn insert_reminder(conn: &Connection, reminder: &Reminder) -> Result<String> {
+    let now = format_timestamp(Utc::now());
+    let uuid = reminder.id.clone().unwrap_or_else(new_record_id);
+
+    // Resolve the medication's rowid in the same statement; no row comes
+    // back when the medication does not exist or has been deleted
     let stmt = conn
         .prepare(
-            "INSERT INTO reminders (medication_id, time, days, is_active, created_at, updated_at)
-             VALUES (?, ?, ?, ?, ?, ?) RETURNING id",
+            "INSERT INTO reminders (uuid, medication_id, time, days, is_active, created_at, updated_at)
+             SELECT ?, id, ?, ?, ?, ?, ? FROM medications WHERE uuid = ? AND deleted_at IS NULL
+             RETURNING uuid",
         )
         .await?;
 
     let mut rows = stmt
         .query(params![
-            reminder.medication_id,
+            uuid,
             reminder.time.clone(),
             reminder.days.clone(),
             reminder.is_active as i64,
             now.clone(),
-            now
+            now,
+            reminder.medication_id.clone()
         ])
         .await?;
 
     if let Some(row) = rows.next().await? {
-        let id: i64 = row.get(0)?;
+        let id: String = row.get(0)?;
         Ok(id)
     } else {
-        Err(anyhow::anyhow!("Failed to create reminder"))
+        Err(anyhow::anyhow!(
+            "Failed to create reminder: medication {} not found",
+            reminder.medication_id
+        ))
     }
 }
 
-pub async fn update_reminder(reminder: &Reminder) -> Result<bool> {
-    let id = reminder.id.ok_or_else(|| anyhow::anyhow!("Reminder ID is required for update"))?;
-    let now = Utc::now().to_rfc3339();
+/// Reminder counterpart of `update_medication`
+pub async fn update_reminder(
+    reminder: &Reminder,
+    policy: MergePolicy,
+    base: Option<&Reminder>,
+) -> Result<bool> {
+    let id = reminder
+        .id
+        .clone()
+        .ok_or_else(|| anyhow::anyhow!("Reminder ID is required for update"))?;
 
     let conn = get_connection().ok_or_else(|| anyhow::anyhow!("No database connection"))?;
     let conn = conn.lock().await;
 
-    conn.execute(
-        "UPDATE reminders SET time = ?, days = ?, is_active = ?, updated_at = ?
-         WHERE id = ? AND deleted_at IS NULL",
-        params![
-            reminder.time.clone(),
-            reminder.days.clone(),
-            reminder.is_active as i64,
-            now,
-            id
-        ],
-    )
-    .await?;
+    if write_reminder(&conn, &id, reminder, reminder.version).await? {
+        return Ok(true);
+    }
 
-    Ok(true)
+    let Some(current) = find_reminder(&conn, &id).await? else {
+        return Ok(false);
+    };
+
+    let resolved = match policy {
+        MergePolicy::Reject => None,
+        MergePolicy::LastWriterWins => Some(reminder.clone()),
+        MergePolicy::FieldLevelMerge => {
+            base.and_then(|base| merge_reminder(base, reminder, &current))
+        }
+    };
+
+    if let Some(resolved) = resolved {
+        if write_reminder(&conn, &id, &resolved, current.version).await? {
+            return Ok(true);
+        }
+    }
+
+    let current = find_reminder(&conn, &id).await?.unwrap_or(current);
+    Err(PillMomError::ReminderConflict { current }.into())
+}
+
+async fn find_reminder(conn: &Connection, id: &str) -> Result<Option<Reminder>> {
+    let mut rows = conn
+        .query(
+            concat!(
+                reminder_select!(),
+                " WHERE r.uuid = ? AND r.deleted_at IS NULL"
+            ),
+            params![id],
+        )
+        .await?;
+
+    if let Some(row) = rows.next().await? {
+        Ok(Some(reminder_from_row(&row)?))
+    } else {
+        Ok(None)
+    }
+}
+
+async fn write_reminder(
+    conn: &Connection,
+    id: &str,
+    reminder: &Reminder,
+    expected_version: i64,
+) -> Result<bool> {
+    let now = format_timestamp(Utc::now());
+
+    let updated = conn
+        .execute(
+            "UPDATE reminders SET time = ?, days = ?, is_active = ?, updated_at = ?,
+                    version = version + 1
+             WHERE uuid = ? AND version = ? AND deleted_at IS NULL",
+            params![
+                reminder.time.clone(),
+                reminder.days.clone(),
+                reminder.is_active as i64,
+                now,
+                id,
+                expected_version
+            ],
+        )
+        .await?;
+
+    Ok(updated > 0)
 }
 
-pub async fn delete_reminder(id: i64) -> Result<bool> {
-    let now = Utc::now().to_rfc3339();
+pub async fn delete_reminder(id: &str) -> Result<bool> {
+    let now = format_timestamp(Utc::now());
 
     let conn = get_connection().ok_or_else(|| anyhow::anyhow!("No database connection"))?;
     let conn = conn.lock().await;
 
     conn.execute(
-        "UPDATE reminders SET deleted_at = ? WHERE id = ?",
+        "UPDATE reminders SET deleted_at = ? WHERE uuid = ?",
         params![now, id],
     )
     .await?;
@@ -241,63 +589,1369 @@ pub async fn delete_reminder(id: i64) -> Result<bool> {
     Ok(true)
 }
 
-#[allow(dead_code)]
-pub async fn get_reminders_for_medication(medication_id: i64) -> Result<Vec<Reminder>> {
+/// Turn off the active reminders of a medication whose course is complete
+/// and delete its live phased schedule. Returns `false` if there was
+/// neither.
+pub async fn end_course(medication_id: &str) -> Result<bool> {
+    let now = format_timestamp(Utc::now());
+
     let conn = get_connection().ok_or_else(|| anyhow::anyhow!("No database connection"))?;
     let conn = conn.lock().await;
 
-    let stmt = conn
-        .prepare(
-            "SELECT id, medication_id, time, days, is_active, created_at, updated_at, deleted_at
-             FROM reminders WHERE medication_id = ? AND deleted_at IS NULL",
+    let tx = conn.transaction().await?;
+
+    let reminders = tx
+        .execute(
+            "UPDATE reminders SET is_active = 0, updated_at = ?, version = version + 1
+             WHERE is_active = 1 AND deleted_at IS NULL
+               AND medication_id = (SELECT id FROM medications WHERE uuid = ?)",
+            params![now.clone(), medication_id],
+        )
+        .await?;
+    // A schedule has no inactive state, so it ends the way it is deleted
+    let schedules = tx
+        .execute(
+            "UPDATE phased_schedules SET deleted_at = ?, updated_at = ?, version = version + 1
+             WHERE deleted_at IS NULL
+               AND medication_id = (SELECT id FROM medications WHERE uuid = ?)",
+            params![now.clone(), now, medication_id],
         )
         .await?;
 
+    tx.commit().await?;
+
+    Ok(reminders + schedules > 0)
+}
+
+pub async fn get_reminders_for_medication(medication_id: &str) -> Result<Vec<Reminder>> {
+    let conn = get_reader().ok_or_else(|| anyhow::anyhow!("No database connection"))?;
+    let mut conn = conn.lock().await;
+
+    let stmt = conn
+        .prepare_cached(concat!(
+            reminder_select!(),
+            " WHERE m.uuid = ? AND r.deleted_at IS NULL"
+        ))
+        .await?;
+
     let mut rows = stmt.query(params![medication_id]).await?;
     let mut reminders = Vec::new();
 
     while let Some(row) = rows.next().await? {
-        reminders.push(Reminder {
-            id: Some(row.get(0)?),
-            medication_id: row.get(1)?,
-            time: row.get(2)?,
-            days: row.get(3)?,
-            is_active: row.get::<Option<i64>>(4)?.map(|v| v != 0).unwrap_or(true),
-            created_at: row.get::<Option<String>>(5)?.unwrap_or_default(),
-            updated_at: row.get::<Option<String>>(6)?.unwrap_or_default(),
-            deleted_at: row.get::<Option<String>>(7)?,
-        });
+        reminders.push(reminder_from_row(&row)?);
     }
 
     Ok(reminders)
 }
 
+/// Active reminders of the active profile's medications
 pub async fn get_active_reminders() -> Result<Vec<Reminder>> {
-    let conn = get_connection().ok_or_else(|| anyhow::anyhow!("No database connection"))?;
-    let conn = conn.lock().await;
+    let conn = get_reader().ok_or_else(|| anyhow::anyhow!("No database connection"))?;
+    let mut conn = conn.lock().await;
+
+    let profile = resolve_profile(&conn, None).await?;
 
     let stmt = conn
-        .prepare(
-            "SELECT id, medication_id, time, days, is_active, created_at, updated_at, deleted_at
-             FROM reminders WHERE is_active = 1 AND deleted_at IS NULL",
-        )
+        .prepare_cached(concat!(
+            reminder_select!(),
+            " WHERE m.profile_id = ? AND r.is_active = 1 AND r.deleted_at IS NULL"
+        ))
         .await?;
 
-    let mut rows = stmt.query(()).await?;
+    let mut rows = stmt.query(params![profile]).await?;
     let mut reminders = Vec::new();
 
     while let Some(row) = rows.next().await? {
-        reminders.push(Reminder {
-            id: Some(row.get(0)?),
-            medication_id: row.get(1)?,
-            time: row.get(2)?,
-            days: row.get(3)?,
-            is_active: row.get::<Option<i64>>(4)?.map(|v| v != 0).unwrap_or(true),
-            created_at: row.get::<Option<String>>(5)?.unwrap_or_default(),
-            updated_at: row.get::<Option<String>>(6)?.unwrap_or_default(),
-            deleted_at: row.get::<Option<String>>(7)?,
-        });
+        reminders.push(reminder_from_row(&row)?);
     }
 
     Ok(reminders)
-}
\ No newline at end of file
+}
+
+// ===== Phased Schedules =====
+
+/// Add a schedule to a live medication that has none
+pub async fn create_schedule(schedule: &PhasedSchedule) -> Result<String> {
+    let conn = get_connection().ok_or_else(|| anyhow::anyhow!("No database connection"))?;
+    let conn = conn.lock().await;
+
+    let tx = conn.transaction().await?;
+    let id = insert_schedule(&tx, schedule).await?;
+    tx.commit().await?;
+
+    Ok(id)
+}
+
+async fn insert_schedule(conn: &Connection, schedule: &PhasedSchedule) -> Result<String> {
+    let now = format_timestamp(Utc::now());
+    let uuid = schedule.id.clone().unwrap_or_else(new_record_id);
+
+    let mut rows = conn
+        .query(
+            "INSERT INTO phased_schedules (uuid, medication_id, start_date, created_at, updated_at)
+             SELECT ?, m.id, ?, ?, ? FROM medications m
+             WHERE m.uuid = ? AND m.deleted_at IS NULL
+               AND NOT EXISTS (SELECT 1 FROM phased_schedules s
+                               WHERE s.medication_id = m.id AND s.deleted_at IS NULL)
+             RETURNING id, uuid",
+            params![
+                uuid,
+                schedule.start_date.trim(),
+                now.clone(),
+                now,
+                schedule.medication_id.clone()
+            ],
+        )
+        .await?;
+
+    let Some(row) = rows.next().await? else {
+        drop(rows);
+        let live = row_exists(
+            conn,
+            "SELECT 1 FROM medications WHERE uuid = ? AND deleted_at IS NULL",
+            &schedule.medication_id,
+        )
+        .await?;
+        return Err(if live {
+            anyhow::anyhow!(
+                "Medication {} already has a schedule; update or delete it first",
+                schedule.medication_id
+            )
+        } else {
+            anyhow::anyhow!(
+                "Failed to create schedule: medication {} not found",
+                schedule.medication_id
+            )
+        });
+    };
+    let rowid: i64 = row.get(0)?;
+    let id: String = row.get(1)?;
+    drop(rows);
+
+    insert_phases(conn, rowid, &schedule.phases).await?;
+
+    Ok(id)
+}
+
+/// Replace a schedule's start date and phases if it is still at
+/// `schedule.version`, or fail with `PillMomError::ScheduleConflict`.
+/// Returns `false` if the schedule does not exist.
+pub async fn update_schedule(schedule: &PhasedSchedule) -> Result<bool> {
+    let id = schedule
+        .id
+        .clone()
+        .ok_or_else(|| anyhow::anyhow!("Schedule ID is required for update"))?;
+
+    let conn = get_connection().ok_or_else(|| anyhow::anyhow!("No database connection"))?;
+    let conn = conn.lock().await;
+
+    let tx = conn.transaction().await?;
+
+    let mut rows = tx
+        .query(
+            "UPDATE phased_schedules SET start_date = ?, updated_at = ?, version = version + 1
+             WHERE uuid = ? AND version = ? AND deleted_at IS NULL
+             RETURNING id",
+            params![
+                schedule.start_date.trim(),
+                format_timestamp(Utc::now()),
+                id.clone(),
+                schedule.version
+            ],
+        )
+        .await?;
+    let rowid = match rows.next().await? {
+        Some(row) => row.get::<i64>(0)?,
+        None => {
+            drop(rows);
+            let rows = tx
+                .query(
+                    concat!(
+                        schedule_select!(),
+                        " WHERE s.uuid = ? AND s.deleted_at IS NULL"
+                    ),
+                    params![id],
+                )
+                .await?;
+            return match read_schedules(&tx, rows).await?.into_iter().next() {
+                Some(current) => Err(PillMomError::ScheduleConflict { current }.into()),
+                None => Ok(false),
+            };
+        }
+    };
+    drop(rows);
+
+    tx.execute(
+        "DELETE FROM schedule_phases WHERE schedule_id = ?",
+        params![rowid],
+    )
+    .await?;
+    insert_phases(&tx, rowid, &schedule.phases).await?;
+
+    tx.commit().await?;
+
+    Ok(true)
+}
+
+pub async fn delete_schedule(id: &str) -> Result<bool> {
+    let now = format_timestamp(Utc::now());
+
+    let conn = get_connection().ok_or_else(|| anyhow::anyhow!("No database connection"))?;
+    let conn = conn.lock().await;
+
+    let deleted = conn
+        .execute(
+            "UPDATE phased_schedules SET deleted_at = ?, updated_at = ?
+             WHERE uuid = ? AND deleted_at IS NULL",
+            params![now.clone(), now, id],
+        )
+        .await?;
+
+    Ok(deleted > 0)
+}
+
+/// Live schedules of the active profile's live medications
+pub async fn get_schedules() -> Result<Vec<PhasedSchedule>> {
+    let conn = get_reader().ok_or_else(|| anyhow::anyhow!("No database connection"))?;
+    let conn = conn.lock().await;
+
+    let profile = resolve_profile(&conn, None).await?;
+    let rows = conn
+        .query(
+            concat!(
+                schedule_select!(),
+                " WHERE m.profile_id = ? AND s.deleted_at IS NULL AND m.deleted_at IS NULL
+                 ORDER BY s.id"
+            ),
+            params![profile],
+        )
+        .await?;
+
+    read_schedules(&conn, rows).await
+}
+
+/// The live schedule of a medication, if it has one
+pub async fn get_schedule_for_medication(medication_id: &str) -> Result<Option<PhasedSchedule>> {
+    let conn = get_reader().ok_or_else(|| anyhow::anyhow!("No database connection"))?;
+    let conn = conn.lock().await;
+
+    let rows = conn
+        .query(
+            concat!(
+                schedule_select!(),
+                " WHERE m.uuid = ? AND s.deleted_at IS NULL"
+            ),
+            params![medication_id],
+        )
+        .await?;
+
+    Ok(read_schedules(&conn, rows).await?.into_iter().next())
+}
+
+async fn insert_phases(conn: &Connection, schedule: i64, phases: &[SchedulePhase]) -> Result<()> {
+    for (position, phase) in phases.iter().enumerate() {
+        conn.execute(
+            "INSERT INTO schedule_phases
+                (schedule_id, position, duration_days, dose_quantity, dose_unit, times, days,
+                 every_n_days)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+            params![
+                schedule,
+                position as i64,
+                phase.duration_days,
+                phase.dose_quantity,
+                phase.dose_unit.trim(),
+                phase
+                    .times
+                    .iter()
+                    .map(|time| time.trim())
+                    .collect::<Vec<_>>()
+                    .join(","),
+                phase.days.trim(),
+                phase.every_n_days
+            ],
+        )
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Read schedules from `schedule_select!` rows, each with its phases
+async fn read_schedules(conn: &Connection, mut rows: libsql::Rows) -> Result<Vec<PhasedSchedule>> {
+    let mut schedules = Vec::new();
+    while let Some(row) = rows.next().await? {
+        schedules.push((row.get::<i64>(7)?, schedule_from_row(&row)?));
+    }
+    drop(rows);
+
+    for (rowid, schedule) in &mut schedules {
+        schedule.phases = read_phases(conn, *rowid).await?;
+    }
+
+    Ok(schedules.into_iter().map(|(_, schedule)| schedule).collect())
+}
+
+/// Phases of the schedule at `rowid`, in the order they run
+pub(crate) async fn read_phases(conn: &Connection, rowid: i64) -> Result<Vec<SchedulePhase>> {
+    let mut rows = conn
+        .query(
+            "SELECT duration_days, dose_quantity, dose_unit, times, days, every_n_days
+             FROM schedule_phases WHERE schedule_id = ? ORDER BY position",
+            params![rowid],
+        )
+        .await?;
+
+    let mut phases = Vec::new();
+    while let Some(row) = rows.next().await? {
+        phases.push(phase_from_row(&row)?);
+    }
+
+    Ok(phases)
+}
+
+// ===== Profiles =====
+
+pub async fn create_profile(profile: &Profile) -> Result<String> {
+    let name = profile.name.trim();
+    if name.is_empty() {
+        return Err(anyhow::anyhow!("Profile name is required"));
+    }
+
+    let conn = get_connection().ok_or_else(|| anyhow::anyhow!("No database connection"))?;
+    let conn = conn.lock().await;
+
+    let now = format_timestamp(Utc::now());
+    let uuid = profile.id.clone().unwrap_or_else(new_record_id);
+
+    let mut rows = conn
+        .query(
+            "INSERT INTO profiles (uuid, name, created_at, updated_at) VALUES (?, ?, ?, ?)
+             RETURNING uuid",
+            params![uuid, name, now.clone(), now],
+        )
+        .await?;
+
+    if let Some(row) = rows.next().await? {
+        let id: String = row.get(0)?;
+        Ok(id)
+    } else {
+        Err(anyhow::anyhow!("Failed to create profile"))
+    }
+}
+
+/// Live profiles, oldest first
+pub async fn get_profiles() -> Result<Vec<Profile>> {
+    let conn = get_reader().ok_or_else(|| anyhow::anyhow!("No database connection"))?;
+    let conn = conn.lock().await;
+
+    let mut rows = conn
+        .query(
+            concat!(profile_select!(), " WHERE p.deleted_at IS NULL ORDER BY p.id"),
+            (),
+        )
+        .await?;
+
+    let mut profiles = Vec::new();
+    while let Some(row) = rows.next().await? {
+        profiles.push(profile_from_row(&row)?);
+    }
+
+    Ok(profiles)
+}
+
+/// The live profile `id`, or the active profile
+pub async fn get_profile(id: Option<&str>) -> Result<Profile> {
+    let conn = get_reader().ok_or_else(|| anyhow::anyhow!("No database connection"))?;
+    let conn = conn.lock().await;
+
+    let profile = resolve_profile(&conn, id).await?;
+    let mut rows = conn
+        .query(concat!(profile_select!(), " WHERE p.id = ?"), params![profile])
+        .await?;
+
+    match rows.next().await? {
+        Some(row) => profile_from_row(&row),
+        None => Err(anyhow::anyhow!("Profile not found")),
+    }
+}
+
+/// Rename a profile. Returns `false` if the profile does not exist.
+pub async fn update_profile(profile: &Profile) -> Result<bool> {
+    let id = profile
+        .id
+        .clone()
+        .ok_or_else(|| anyhow::anyhow!("Profile ID is required for update"))?;
+    let name = profile.name.trim();
+    if name.is_empty() {
+        return Err(anyhow::anyhow!("Profile name is required"));
+    }
+
+    let conn = get_connection().ok_or_else(|| anyhow::anyhow!("No database connection"))?;
+    let conn = conn.lock().await;
+
+    let updated = conn
+        .execute(
+            "UPDATE profiles SET name = ?, updated_at = ? WHERE uuid = ? AND deleted_at IS NULL",
+            params![name, format_timestamp(Utc::now()), id],
+        )
+        .await?;
+
+    Ok(updated > 0)
+}
+
+/// Soft delete a profile together with its medications and their
+/// reminders and schedules. The last live profile cannot be deleted.
+pub async fn delete_profile(id: &str) -> Result<bool> {
+    let now = format_timestamp(Utc::now());
+
+    let conn = get_connection().ok_or_else(|| anyhow::anyhow!("No database connection"))?;
+    let conn = conn.lock().await;
+
+    let tx = conn.transaction().await?;
+
+    let mut rows = tx
+        .query(
+            "SELECT COUNT(*) FROM profiles WHERE deleted_at IS NULL AND uuid != ?",
+            params![id],
+        )
+        .await?;
+    let others: i64 = match rows.next().await? {
+        Some(row) => row.get(0)?,
+        None => 0,
+    };
+    drop(rows);
+
+    if others == 0 {
+        return Err(anyhow::anyhow!("Cannot delete the only profile"));
+    }
+
+    let deleted = tx
+        .execute(
+            "UPDATE profiles SET deleted_at = ?, updated_at = ?
+             WHERE uuid = ? AND deleted_at IS NULL",
+            params![now.clone(), now.clone(), id],
+        )
+        .await?;
+
+    if deleted > 0 {
+        tx.execute(
+            "UPDATE reminders SET deleted_at = ?
+             WHERE deleted_at IS NULL AND medication_id IN (
+                 SELECT m.id FROM medications m JOIN profiles p ON p.id = m.profile_id
+                 WHERE p.uuid = ?)",
+            params![now.clone(), id],
+        )
+        .await?;
+        tx.execute(
+            "UPDATE phased_schedules SET deleted_at = ?
+             WHERE deleted_at IS NULL AND medication_id IN (
+                 SELECT m.id FROM medications m JOIN profiles p ON p.id = m.profile_id
+                 WHERE p.uuid = ?)",
+            params![now.clone(), id],
+        )
+        .await?;
+        tx.execute(
+            "UPDATE medications SET deleted_at = ?
+             WHERE deleted_at IS NULL
+               AND profile_id = (SELECT id FROM profiles WHERE uuid = ?)",
+            params![now, id],
+        )
+        .await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(deleted > 0)
+}
+
+/// Rowid of the live profile `id`; without one, of the session's active
+/// profile, or else the oldest live profile
+async fn resolve_profile(conn: &Connection, id: Option<&str>) -> Result<i64> {
+    let id = id.map(str::to_string).or_else(database::active_profile);
+
+    let mut rows = match &id {
+        Some(id) => {
+            conn.query(
+                "SELECT id FROM profiles WHERE uuid = ? AND deleted_at IS NULL",
+                params![id.as_str()],
+            )
+            .await?
+        }
+        None => {
+            conn.query(
+                "SELECT id FROM profiles WHERE deleted_at IS NULL ORDER BY id LIMIT 1",
+                (),
+            )
+            .await?
+        }
+    };
+
+    match rows.next().await? {
+        Some(row) => Ok(row.get(0)?),
+        None => Err(match id {
+            Some(id) => anyhow::anyhow!("Profile {} not found", id),
+            None => anyhow::anyhow!("No profile exists"),
+        }),
+    }
+}
+
+// ===== Dose Logs =====
+
+pub async fn record_dose(dose: &DoseLog) -> Result<String> {
+    let conn = get_connection().ok_or_else(|| anyhow::anyhow!("No database connection"))?;
+    let conn = conn.lock().await;
+
+    insert_dose_log(&conn, dose, true).await
+}
+
+/// Insert `dose`; with `live_only`, the medication must not be deleted.
+/// A reminder id must name a reminder of the same medication.
+async fn insert_dose_log(conn: &Connection, dose: &DoseLog, live_only: bool) -> Result<String> {
+    let uuid = dose.id.clone().unwrap_or_else(new_record_id);
+
+    let stmt = conn
+        .prepare(
+            "INSERT INTO dose_logs (uuid, medication_id, reminder_id, status, scheduled_for, logged_at)
+             SELECT ?, m.id, r.id, ?, ?, ? FROM medications m
+             LEFT JOIN reminders r ON r.uuid = ? AND r.medication_id = m.id
+             WHERE m.uuid = ? AND (? = 0 OR m.deleted_at IS NULL)
+               AND (? IS NULL OR r.id IS NOT NULL)
+             RETURNING uuid",
+        )
+        .await?;
+
+    let mut rows = stmt
+        .query(params![
+            uuid,
+            dose_status_name(dose.status),
+            dose.scheduled_for.map(format_timestamp),
+            format_timestamp(dose.logged_at),
+            dose.reminder_id.clone(),
+            dose.medication_id.clone(),
+            live_only as i64,
+            dose.reminder_id.clone()
+        ])
+        .await?;
+
+    if let Some(row) = rows.next().await? {
+        let id: String = row.get(0)?;
+        Ok(id)
+    } else {
+        Err(anyhow::anyhow!(
+            "Failed to record dose: medication {} not found{}",
+            dose.medication_id,
+            match &dose.reminder_id {
+                Some(reminder_id) => format!(" or reminder {} is not one of its reminders", reminder_id),
+                None => String::new(),
+            }
+        ))
+    }
+}
+
+/// Doses of the active profile logged in `[from, to)`, oldest first;
+/// either bound may be open. Doses of deleted medications are included.
+pub async fn get_dose_logs(
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+) -> Result<Vec<DoseLog>> {
+    let conn = get_reader().ok_or_else(|| anyhow::anyhow!("No database connection"))?;
+    let conn = conn.lock().await;
+
+    let profile = resolve_profile(&conn, None).await?;
+    query_dose_logs(&conn, from, to, Some(profile)).await
+}
+
+/// Doses of one medication logged at or after `since`, oldest first
+pub async fn get_medication_dose_logs(
+    medication_id: &str,
+    since: DateTime<Utc>,
+) -> Result<Vec<DoseLog>> {
+    let conn = get_reader().ok_or_else(|| anyhow::anyhow!("No database connection"))?;
+    let conn = conn.lock().await;
+
+    let mut rows = conn
+        .query(
+            concat!(
+                dose_log_select!(),
+                " WHERE m.uuid = ? AND d.logged_at >= ? ORDER BY d.logged_at, d.uuid"
+            ),
+            params![medication_id, format_timestamp(since)],
+        )
+        .await?;
+
+    let mut doses = Vec::new();
+    while let Some(row) = rows.next().await? {
+        doses.push(dose_log_from_row(&row)?);
+    }
+
+    Ok(doses)
+}
+
+/// Doses logged in `[from, to)` by medications of the profile with rowid
+/// `profile`, or of every profile
+async fn query_dose_logs(
+    conn: &Connection,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    profile: Option<i64>,
+) -> Result<Vec<DoseLog>> {
+    let from = from.map(format_timestamp);
+    let to = to.map(format_timestamp);
+
+    let mut rows = conn
+        .query(
+            concat!(
+                dose_log_select!(),
+                " WHERE (? IS NULL OR d.logged_at >= ?) AND (? IS NULL OR d.logged_at < ?)
+                   AND (? IS NULL OR m.profile_id = ?)
+                 ORDER BY d.logged_at, d.uuid"
+            ),
+            params![from.clone(), from, to.clone(), to, profile, profile],
+        )
+        .await?;
+
+    let mut doses = Vec::new();
+    while let Some(row) = rows.next().await? {
+        doses.push(dose_log_from_row(&row)?);
+    }
+
+    Ok(doses)
+}
+
+// ===== Export / Import =====
+
+/// Every profile, soft-deleted ones included, oldest first
+pub async fn export_profiles() -> Result<Vec<Profile>> {
+    let conn = get_reader().ok_or_else(|| anyhow::anyhow!("No database connection"))?;
+    let conn = conn.lock().await;
+
+    let mut rows = conn
+        .query(concat!(profile_select!(), " ORDER BY p.created_at, p.uuid"), ())
+        .await?;
+
+    let mut profiles = Vec::new();
+    while let Some(row) = rows.next().await? {
+        profiles.push(profile_from_row(&row)?);
+    }
+
+    Ok(profiles)
+}
+
+/// Dose history of every profile, oldest first
+pub async fn export_dose_logs() -> Result<Vec<DoseLog>> {
+    let conn = get_reader().ok_or_else(|| anyhow::anyhow!("No database connection"))?;
+    let conn = conn.lock().await;
+
+    query_dose_logs(&conn, None, None, None).await
+}
+
+/// Every schedule, soft-deleted ones included, oldest first
+pub async fn export_schedules() -> Result<Vec<PhasedSchedule>> {
+    let conn = get_reader().ok_or_else(|| anyhow::anyhow!("No database connection"))?;
+    let conn = conn.lock().await;
+
+    let rows = conn
+        .query(
+            concat!(schedule_select!(), " ORDER BY s.created_at, s.uuid"),
+            (),
+        )
+        .await?;
+
+    read_schedules(&conn, rows).await
+}
+
+/// Every medication of every profile with its reminders, soft-deleted
+/// records included, in an order that does not depend on the device the
+/// rows were written on
+pub async fn export_records() -> Result<Vec<Medication>> {
+    let conn = get_reader().ok_or_else(|| anyhow::anyhow!("No database connection"))?;
+    let conn = conn.lock().await;
+
+    let mut rows = conn
+        .query(
+            concat!(medication_select!(), " ORDER BY m.created_at, m.uuid"),
+            (),
+        )
+        .await?;
+
+    let mut medications = Vec::new();
+    while let Some(row) = rows.next().await? {
+        medications.push(medication_from_row(&row)?);
+    }
+
+    let mut rows = conn
+        .query(
+            concat!(reminder_select!(), " ORDER BY r.created_at, r.uuid"),
+            (),
+        )
+        .await?;
+
+    let mut reminders = Vec::new();
+    while let Some(row) = rows.next().await? {
+        reminders.push(reminder_from_row(&row)?);
+    }
+
+    attach_reminders(&mut medications, reminders);
+
+    Ok(medications)
+}
+
+/// Write imported profiles, medications, their reminders, schedules and
+/// dose history in one transaction. Every record must carry an id, and
+/// reminders must belong to the medication they are nested under.
+/// Medications without a profile go to the active profile. Doses already
+/// recorded are left as they are.
+pub async fn import_records(
+    profiles: &[Profile],
+    medications: &[Medication],
+    schedules: &[PhasedSchedule],
+    doses: &[DoseLog],
+    mode: ImportMode,
+) -> Result<ImportSummary> {
+    let conn = get_connection().ok_or_else(|| anyhow::anyhow!("No database connection"))?;
+    let conn = conn.lock().await;
+
+    let tx = conn.transaction().await?;
+    let mut summary = ImportSummary::default();
+
+    if mode == ImportMode::Replace {
+        tx.execute("DELETE FROM dose_logs", ()).await?;
+        tx.execute("DELETE FROM schedule_phases", ()).await?;
+        tx.execute("DELETE FROM phased_schedules", ()).await?;
+        tx.execute("DELETE FROM reminders", ()).await?;
+        tx.execute("DELETE FROM medications", ()).await?;
+
+        // An export with profiles brings its own people, so they replace
+        // ours rather than merging in beside them. One from before profiles
+        // lands in the active profile.
+        if !profiles.is_empty() {
+            tx.execute("DELETE FROM profiles", ()).await?;
+        }
+    }
+
+    for profile in profiles {
+        match import_profile(&tx, profile).await? {
+            Imported::Added => summary.profiles_added += 1,
+            Imported::Updated => summary.profiles_updated += 1,
+            Imported::Unchanged => summary.unchanged += 1,
+        }
+    }
+
+    for med in medications {
+        match import_medication(&tx, med).await? {
+            Imported::Added => summary.medications_added += 1,
+            Imported::Updated => summary.medications_updated += 1,
+            Imported::Unchanged => summary.unchanged += 1,
+        }
+
+        for reminder in &med.reminders {
+            match import_reminder(&tx, reminder).await? {
+                Imported::Added => summary.reminders_added += 1,
+                Imported::Updated => summary.reminders_updated += 1,
+                Imported::Unchanged => summary.unchanged += 1,
+            }
+        }
+    }
+
+    for schedule in schedules {
+        match import_schedule(&tx, schedule).await? {
+            Imported::Added => summary.schedules_added += 1,
+            Imported::Updated => summary.schedules_updated += 1,
+            Imported::Unchanged => summary.unchanged += 1,
+        }
+    }
+
+    for dose in doses {
+        let id = dose
+            .id
+            .as_deref()
+            .ok_or_else(|| anyhow::anyhow!("Imported dose has no ID"))?;
+
+        if row_exists(&tx, "SELECT 1 FROM dose_logs WHERE uuid = ?", id).await? {
+            summary.unchanged += 1;
+        } else {
+            insert_dose_log(&tx, dose, false).await?;
+            summary.doses_added += 1;
+        }
+    }
+
+    tx.commit().await?;
+
+    // The active profile may have gone with the profiles replaced
+    if mode == ImportMode::Replace && !profiles.is_empty() {
+        let kept = database::active_profile()
+            .is_none_or(|active| profiles.iter().any(|p| p.id.as_deref() == Some(&active)));
+        if !kept {
+            database::set_active_profile(None)?;
+        }
+    }
+
+    Ok(summary)
+}
+
+/// Whether `sql` finds a row for `id`, soft-deleted rows included
+async fn row_exists(conn: &Connection, sql: &str, id: &str) -> Result<bool> {
+    let mut rows = conn.query(sql, params![id]).await?;
+    Ok(rows.next().await?.is_some())
+}
+
+enum Imported {
+    Added,
+    Updated,
+    Unchanged,
+}
+
+/// Insert `profile`, or overwrite the stored copy if `profile` is newer
+async fn import_profile(conn: &Connection, profile: &Profile) -> Result<Imported> {
+    let id = profile
+        .id
+        .clone()
+        .ok_or_else(|| anyhow::anyhow!("Imported profile has no ID"))?;
+
+    if !row_exists(conn, "SELECT 1 FROM profiles WHERE uuid = ?", &id).await? {
+        conn.execute(
+            "INSERT INTO profiles (uuid, name, created_at, updated_at, deleted_at)
+             VALUES (?, ?, ?, ?, ?)",
+            params![
+                id,
+                profile.name.clone(),
+                format_timestamp(profile.created_at),
+                format_timestamp(profile.updated_at),
+                profile.deleted_at.map(format_timestamp)
+            ],
+        )
+        .await?;
+        return Ok(Imported::Added);
+    }
+
+    let updated = conn
+        .execute(
+            "UPDATE profiles SET name = ?, updated_at = ?, deleted_at = ?
+             WHERE uuid = ? AND updated_at < ?",
+            params![
+                profile.name.clone(),
+                format_timestamp(profile.updated_at),
+                profile.deleted_at.map(format_timestamp),
+                id,
+                format_timestamp(profile.updated_at)
+            ],
+        )
+        .await?;
+
+    Ok(if updated > 0 {
+        Imported::Updated
+    } else {
+        Imported::Unchanged
+    })
+}
+
+/// Insert `med`, or overwrite the stored copy if `med` is newer
+async fn import_medication(conn: &Connection, med: &Medication) -> Result<Imported> {
+    let id = med
+        .id
+        .clone()
+        .ok_or_else(|| anyhow::anyhow!("Imported medication has no ID"))?;
+
+    // Deleted profiles still hold their deleted medications
+    let profile = match med.profile_id.as_deref() {
+        Some(profile_id) => {
+            let mut rows = conn
+                .query("SELECT id FROM profiles WHERE uuid = ?", params![profile_id])
+                .await?;
+            match rows.next().await? {
+                Some(row) => row.get::<i64>(0)?,
+                None => {
+                    return Err(anyhow::anyhow!(
+                        "Imported medication {} refers to unknown profile {}",
+                        id,
+                        profile_id
+                    ))
+                }
+            }
+        }
+        None => resolve_profile(conn, None).await?,
+    };
+
+    if !row_exists(conn, "SELECT 1 FROM medications WHERE uuid = ?", &id).await? {
+        conn.execute(
+            "INSERT INTO medications
+                (uuid, profile_id, name, dosage, description, rxnorm_cui, ndc, atc_code, is_prn,
+                 min_hours_between_doses, max_daily_doses, course_start_date, course_end_date,
+                 course_total_doses, created_at, updated_at, deleted_at, version)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            params![
+                id,
+                profile,
+                med.name.clone(),
+                med.dosage.clone(),
+                med.description.clone(),
+                med.rxnorm_cui.clone(),
+                med.ndc.clone(),
+                med.atc_code.clone(),
+                med.is_prn as i64,
+                med.min_hours_between_doses,
+                med.max_daily_doses,
+                med.course_start_date.clone(),
+                med.course_end_date.clone(),
+                med.course_total_doses,
+                format_timestamp(med.created_at),
+                format_timestamp(med.updated_at),
+                med.deleted_at.map(format_timestamp),
+                med.version
+            ],
+        )
+        .await?;
+        return Ok(Imported::Added);
+    }
+
+    // Stored timestamps share one fixed-width format, so text comparison
+    // orders them chronologically
+    let updated = conn
+        .execute(
+            "UPDATE medications
+             SET profile_id = ?, name = ?, dosage = ?, description = ?, rxnorm_cui = ?, ndc = ?,
+                 atc_code = ?, is_prn = ?, min_hours_between_doses = ?, max_daily_doses = ?,
+                 course_start_date = ?, course_end_date = ?, course_total_doses = ?,
+                 updated_at = ?, deleted_at = ?, version = version + 1
+             WHERE uuid = ? AND updated_at < ?",
+            params![
+                profile,
+                med.name.clone(),
+                med.dosage.clone(),
+                med.description.clone(),
+                med.rxnorm_cui.clone(),
+                med.ndc.clone(),
+                med.atc_code.clone(),
+                med.is_prn as i64,
+                med.min_hours_between_doses,
+                med.max_daily_doses,
+                med.course_start_date.clone(),
+                med.course_end_date.clone(),
+                med.course_total_doses,
+                format_timestamp(med.updated_at),
+                med.deleted_at.map(format_timestamp),
+                id,
+                format_timestamp(med.updated_at)
+            ],
+        )
+        .await?;
+
+    Ok(if updated > 0 {
+        Imported::Updated
+    } else {
+        Imported::Unchanged
+    })
+}
+
+/// Insert `schedule`, or overwrite the stored copy and its phases if
+/// `schedule` is newer
+async fn import_schedule(conn: &Connection, schedule: &PhasedSchedule) -> Result<Imported> {
+    let id = schedule
+        .id
+        .clone()
+        .ok_or_else(|| anyhow::anyhow!("Imported schedule has no ID"))?;
+
+    let mut rows = if !row_exists(conn, "SELECT 1 FROM phased_schedules WHERE uuid = ?", &id).await? {
+        conn.query(
+            "INSERT INTO phased_schedules
+                (uuid, medication_id, start_date, created_at, updated_at, deleted_at, version)
+             SELECT ?, id, ?, ?, ?, ?, ? FROM medications WHERE uuid = ?
+             RETURNING id, 1",
+            params![
+                id.clone(),
+                schedule.start_date.clone(),
+                format_timestamp(schedule.created_at),
+                format_timestamp(schedule.updated_at),
+                schedule.deleted_at.map(format_timestamp),
+                schedule.version,
+                schedule.medication_id.clone()
+            ],
+        )
+        .await?
+    } else {
+        conn.query(
+            "UPDATE phased_schedules
+             SET start_date = ?, updated_at = ?, deleted_at = ?, version = version + 1
+             WHERE uuid = ? AND updated_at < ?
+             RETURNING id, 0",
+            params![
+                schedule.start_date.clone(),
+                format_timestamp(schedule.updated_at),
+                schedule.deleted_at.map(format_timestamp),
+                id.clone(),
+                format_timestamp(schedule.updated_at)
+            ],
+        )
+        .await?
+    };
+
+    let Some(row) = rows.next().await? else {
+        drop(rows);
+        if !row_exists(conn, "SELECT 1 FROM phased_schedules WHERE uuid = ?", &id).await? {
+            return Err(anyhow::anyhow!(
+                "Imported schedule {} refers to unknown medication {}",
+                id,
+                schedule.medication_id
+            ));
+        }
+        return Ok(Imported::Unchanged);
+    };
+    let rowid: i64 = row.get(0)?;
+    let added = row.get::<i64>(1)? == 1;
+    drop(rows);
+
+    conn.execute(
+        "DELETE FROM schedule_phases WHERE schedule_id = ?",
+        params![rowid],
+    )
+    .await?;
+    insert_phases(conn, rowid, &schedule.phases).await?;
+
+    Ok(if added {
+        Imported::Added
+    } else {
+        Imported::Updated
+    })
+}
+
+/// Insert `reminder`, or overwrite the stored copy if `reminder` is newer
+async fn import_reminder(conn: &Connection, reminder: &Reminder) -> Result<Imported> {
+    let id = reminder
+        .id
+        .clone()
+        .ok_or_else(|| anyhow::anyhow!("Imported reminder has no ID"))?;
+
+    if !row_exists(conn, "SELECT 1 FROM reminders WHERE uuid = ?", &id).await? {
+        let inserted = conn
+            .execute(
+                "INSERT INTO reminders
+                    (uuid, medication_id, time, days, is_active, created_at, updated_at,
+                     deleted_at, version)
+                 SELECT ?, id, ?, ?, ?, ?, ?, ?, ? FROM medications WHERE uuid = ?",
+                params![
+                    id.clone(),
+                    reminder.time.clone(),
+                    reminder.days.clone(),
+                    if reminder.is_active { 1 } else { 0 },
+                    format_timestamp(reminder.created_at),
+                    format_timestamp(reminder.updated_at),
+                    reminder.deleted_at.map(format_timestamp),
+                    reminder.version,
+                    reminder.medication_id.clone()
+                ],
+            )
+            .await?;
+
+        if inserted == 0 {
+            return Err(anyhow::anyhow!(
+                "Imported reminder {} refers to unknown medication {}",
+                id,
+                reminder.medication_id
+            ));
+        }
+        return Ok(Imported::Added);
+    }
+
+    let updated = conn
+        .execute(
+            "UPDATE reminders
+             SET time = ?, days = ?, is_active = ?, updated_at = ?, deleted_at = ?,
+                 version = version + 1
+             WHERE uuid = ? AND updated_at < ?",
+            params![
+                reminder.time.clone(),
+                reminder.days.clone(),
+                if reminder.is_active { 1 } else { 0 },
+                format_timestamp(reminder.updated_at),
+                reminder.deleted_at.map(format_timestamp),
+                id,
+                format_timestamp(reminder.updated_at)
+            ],
+        )
+        .await?;
+
+    Ok(if updated > 0 {
+        Imported::Updated
+    } else {
+        Imported::Unchanged
+    })
+}
+
+// ===== Row Mapping =====
+
+fn medication_from_row(row: &Row) -> Result<Medication> {
+    Ok(Medication {
+        id: Some(row.get(0)?),
+        name: row.get(1)?,
+        dosage: row.get::<Option<String>>(2)?.unwrap_or_default(),
+        description: row.get::<Option<String>>(3)?.unwrap_or_default(),
+        created_at: timestamp_column(row, 4)?.unwrap_or_default(),
+        updated_at: timestamp_column(row, 5)?.unwrap_or_default(),
+        deleted_at: timestamp_column(row, 6)?,
+        version: row.get(7)?,
+        profile_id: row.get(8)?,
+        rxnorm_cui: row.get(9)?,
+        ndc: row.get(10)?,
+        atc_code: row.get(11)?,
+        is_prn: row.get::<i64>(12)? != 0,
+        min_hours_between_doses: row.get(13)?,
+        max_daily_doses: row.get(14)?,
+        course_start_date: row.get(15)?,
+        course_end_date: row.get(16)?,
+        course_total_doses: row.get(17)?,
+        reminders: Vec::new(),
+    })
+}
+
+/// A schedule without its phases, which `read_schedules` loads
+fn schedule_from_row(row: &Row) -> Result<PhasedSchedule> {
+    Ok(PhasedSchedule {
+        id: Some(row.get(0)?),
+        medication_id: row.get(1)?,
+        start_date: row.get(2)?,
+        phases: Vec::new(),
+        created_at: timestamp_column(row, 3)?.unwrap_or_default(),
+        updated_at: timestamp_column(row, 4)?.unwrap_or_default(),
+        deleted_at: timestamp_column(row, 5)?,
+        version: row.get(6)?,
+    })
+}
+
+fn phase_from_row(row: &Row) -> Result<SchedulePhase> {
+    let times: String = row.get(3)?;
+    Ok(SchedulePhase {
+        duration_days: row.get(0)?,
+        dose_quantity: row.get(1)?,
+        dose_unit: row.get(2)?,
+        times: times
+            .split(',')
+            .filter(|time| !time.is_empty())
+            .map(str::to_string)
+            .collect(),
+        days: row.get(4)?,
+        every_n_days: row.get(5)?,
+    })
+}
+
+fn profile_from_row(row: &Row) -> Result<Profile> {
+    Ok(Profile {
+        id: Some(row.get(0)?),
+        name: row.get(1)?,
+        created_at: timestamp_column(row, 2)?.unwrap_or_default(),
+        updated_at: timestamp_column(row, 3)?.unwrap_or_default(),
+        deleted_at: timestamp_column(row, 4)?,
+    })
+}
+
+fn reminder_from_row(row: &Row) -> Result<Reminder> {
+    Ok(Reminder {
+        id: Some(row.get(0)?),
+        medication_id: row.get(1)?,
+        time: row.get(2)?,
+        days: row.get::<Option<String>>(3)?.unwrap_or_default(),
+        is_active: row.get::<Option<i64>>(4)?.map(|v| v != 0).unwrap_or(true),
+        created_at: timestamp_column(row, 5)?.unwrap_or_default(),
+        updated_at: timestamp_column(row, 6)?.unwrap_or_default(),
+        deleted_at: timestamp_column(row, 7)?,
+        version: row.get(8)?,
+    })
+}
+
+fn dose_log_from_row(row: &Row) -> Result<DoseLog> {
+    Ok(DoseLog {
+        id: Some(row.get(0)?),
+        medication_id: row.get(1)?,
+        reminder_id: row.get(2)?,
+        status: parse_dose_status(&row.get::<String>(3)?)?,
+        scheduled_for: timestamp_column(row, 4)?,
+        logged_at: timestamp_column(row, 5)?.unwrap_or_default(),
+    })
+}
+
+/// Name stored in `dose_logs.status`
+fn dose_status_name(status: DoseStatus) -> &'static str {
+    match status {
+        DoseStatus::Taken => "taken",
+        DoseStatus::Skipped => "skipped",
+        DoseStatus::Missed => "missed",
+    }
+}
+
+fn parse_dose_status(name: &str) -> Result<DoseStatus> {
+    match name {
+        "taken" => Ok(DoseStatus::Taken),
+        "skipped" => Ok(DoseStatus::Skipped),
+        "missed" => Ok(DoseStatus::Missed),
+        other => Err(anyhow::anyhow!("Unknown dose status '{}'", other)),
+    }
+}
+
+/// Read a nullable timestamp column written either by us or by a SQL default
+fn timestamp_column(row: &Row, idx: i32) -> Result<Option<DateTime<Utc>>> {
+    row.get::<Option<String>>(idx)?
+        .map(|value| parse_timestamp(&value))
+        .transpose()
+}
+
+/// Group reminders under their medications in one pass over each list
+fn attach_reminders(medications: &mut [Medication], reminders: Vec<Reminder>) {
+    let index: HashMap<String, usize> = medications
+        .iter()
+        .enumerate()
+        .filter_map(|(i, m)| m.id.clone().map(|id| (id, i)))
+        .collect();
+
+    for reminder in reminders {
+        if let Some(&i) = index.get(&reminder.medication_id) {
+            medications[i].reminders.push(reminder);
+        }
+    }
+}
+
+/// Escape LIKE wildcards so user input is matched literally
+fn escape_like(input: &str) -> String {
+    let mut escaped = String::with_capacity(input.len());
+    for c in input.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+// ===== Timestamps =====
+
+/// Format used for every stored timestamp: RFC 3339 in UTC with millisecond
+/// precision. The fixed width keeps lexical and chronological order equal,
+/// which the `ORDER BY created_at` queries rely on.
+pub(crate) fn format_timestamp(value: DateTime<Utc>) -> String {
+    value.to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+/// Parse a stored timestamp, accepting RFC 3339 as well as the
+/// `YYYY-MM-DD HH:MM:SS` format produced by SQLite's `datetime('now')`
+pub(crate) fn parse_timestamp(value: &str) -> Result<DateTime<Utc>> {
+    if let Ok(parsed) = DateTime::parse_from_rfc3339(value) {
+        return Ok(parsed.with_timezone(&Utc));
+    }
+
+    NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S%.f")
+        .map(|naive| naive.and_utc())
+        .map_err(|e| anyhow::anyhow!("Invalid timestamp '{}': {}", value, e))
+}
+
+// ===== Merging =====
+
+/// Three-way merge of one field: keep whichever side changed it, or fail
+/// when both changed it to different values
+fn merge_field<T: PartialEq + Clone>(base: &T, local: &T, remote: &T) -> Option<T> {
+    if local == base {
+        Some(remote.clone())
+    } else if remote == base || remote == local {
+        Some(local.clone())
+    } else {
+        None
+    }
+}
+
+fn merge_medication(base: &Medication, local: &Medication, remote: &Medication) -> Option<Medication> {
+    Some(Medication {
+        name: merge_field(&base.name, &local.name, &remote.name)?,
+        dosage: merge_field(&base.dosage, &local.dosage, &remote.dosage)?,
+        description: merge_field(&base.description, &local.description, &remote.description)?,
+        rxnorm_cui: merge_field(&base.rxnorm_cui, &local.rxnorm_cui, &remote.rxnorm_cui)?,
+        ndc: merge_field(&base.ndc, &local.ndc, &remote.ndc)?,
+        atc_code: merge_field(&base.atc_code, &local.atc_code, &remote.atc_code)?,
+        is_prn: merge_field(&base.is_prn, &local.is_prn, &remote.is_prn)?,
+        min_hours_between_doses: merge_field(
+            &base.min_hours_between_doses,
+            &local.min_hours_between_doses,
+            &remote.min_hours_between_doses,
+        )?,
+        max_daily_doses: merge_field(
+            &base.max_daily_doses,
+            &local.max_daily_doses,
+            &remote.max_daily_doses,
+        )?,
+        course_start_date: merge_field(
+            &base.course_start_date,
+            &local.course_start_date,
+            &remote.course_start_date,
+        )?,
+        course_end_date: merge_field(
+            &base.course_end_date,
+            &local.course_end_date,
+            &remote.course_end_date,
+        )?,
+        course_total_doses: merge_field(
+            &base.course_total_doses,
+            &local.course_total_doses,
+            &remote.course_total_doses,
+        )?,
+        ..remote.clone()
+    })
+}
+
+fn merge_reminder(base: &Reminder, local: &Reminder, remote: &Reminder) -> Option<Reminder> {
+    Some(Reminder {
+        time: merge_field(&base.time, &local.time, &remote.time)?,
+        days: merge_field(&base.days, &local.days, &remote.days)?,
+        is_active: merge_field(&base.is_active, &local.is_active, &remote.is_active)?,
+        ..remote.clone()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merge_field_keeps_the_side_that_changed() {
+        assert_eq!(merge_field(&1, &1, &2), Some(2));
+        assert_eq!(merge_field(&1, &2, &1), Some(2));
+        assert_eq!(merge_field(&1, &1, &1), Some(1));
+    }
+
+    #[test]
+    fn merge_field_accepts_the_same_change_on_both_sides() {
+        assert_eq!(merge_field(&"a", &"b", &"b"), Some("b"));
+    }
+
+    #[test]
+    fn merge_field_conflicts_when_both_sides_differ() {
+        assert_eq!(merge_field(&"a", &"b", &"c"), None);
+        assert_eq!(merge_field(&None, &Some(4), &Some(6)), None);
+    }
+
+    #[test]
+    fn merge_medication_combines_changes_to_different_fields() {
+        let base = Medication {
+            name: "Ibuprofen".to_string(),
+            dosage: "200mg".to_string(),
+            version: 1,
+            ..Default::default()
+        };
+        let local = Medication {
+            dosage: "400mg".to_string(),
+            ..base.clone()
+        };
+        let remote = Medication {
+            name: "Ibuprofen (Advil)".to_string(),
+            version: 2,
+            ..base.clone()
+        };
+
+        let merged = merge_medication(&base, &local, &remote).unwrap();
+        assert_eq!(merged.name, "Ibuprofen (Advil)");
+        assert_eq!(merged.dosage, "400mg");
+        assert_eq!(merged.version, 2);
+
+        let clashing = Medication {
+            dosage: "600mg".to_string(),
+            ..remote
+        };
+        assert!(merge_medication(&base, &local, &clashing).is_none());
+    }
+}
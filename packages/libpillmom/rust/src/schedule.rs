@@ -0,0 +1,603 @@
+use crate::models::{Medication, PhasedSchedule, Reminder, SchedulePhase};
+use anyhow::Result;
+use chrono::{
+    DateTime, Datelike, Duration, FixedOffset, NaiveDate, NaiveTime, TimeZone, Utc, Weekday,
+};
+
+// Interpretation of the free-text `time` and `days` fields of a reminder,
+// shared by the exporters that need a structured schedule, and of phased
+// schedules, which take over from the reminders on the days they cover.
+
+/// Largest UTC offset in use, in minutes
+const MAX_UTC_OFFSET_MINUTES: i64 = 14 * 60;
+
+/// Longest a single phase may last, so phase arithmetic stays in range
+const MAX_PHASE_DAYS: i64 = 3660;
+
+/// Most phases a schedule may have
+const MAX_PHASES: usize = 100;
+
+/// Days a reminder fires on
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DaySet {
+    EveryDay,
+    /// Distinct weekdays, Monday first
+    Only(Vec<Weekday>),
+}
+
+impl DaySet {
+    pub fn includes(&self, day: Weekday) -> bool {
+        match self {
+            DaySet::EveryDay => true,
+            DaySet::Only(weekdays) => weekdays.contains(&day),
+        }
+    }
+}
+
+/// Parse a reminder's `days`: "Daily" (or empty), "Weekdays", "Weekends", or
+/// a comma/space separated list of day names such as "Mon,Wed,Fri".
+/// Returns `None` if any part is not recognised.
+pub fn parse_days(days: &str) -> Option<DaySet> {
+    let normalized = days.trim().to_ascii_lowercase();
+
+    match normalized.as_str() {
+        "" | "daily" | "everyday" | "every day" => return Some(DaySet::EveryDay),
+        "weekdays" => {
+            return Some(DaySet::Only(vec![
+                Weekday::Mon,
+                Weekday::Tue,
+                Weekday::Wed,
+                Weekday::Thu,
+                Weekday::Fri,
+            ]))
+        }
+        "weekends" => return Some(DaySet::Only(vec![Weekday::Sat, Weekday::Sun])),
+        _ => {}
+    }
+
+    let mut weekdays = Vec::new();
+    for token in normalized
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|token| !token.is_empty())
+    {
+        // chrono accepts "mon" and "monday"; abbreviations like "tues" are
+        // matched on their first three letters
+        let day = token
+            .parse::<Weekday>()
+            .ok()
+            .or_else(|| token.get(..3)?.parse().ok())?;
+
+        if !weekdays.contains(&day) {
+            weekdays.push(day);
+        }
+    }
+
+    match weekdays.len() {
+        0 => return None,
+        7 => return Some(DaySet::EveryDay),
+        _ => {}
+    }
+
+    weekdays.sort_by_key(|day| day.num_days_from_monday());
+    Some(DaySet::Only(weekdays))
+}
+
+/// Parse a reminder's `time`, written as "HH:MM" or "HH:MM:SS"
+pub fn parse_time(time: &str) -> Option<NaiveTime> {
+    let time = time.trim();
+    NaiveTime::parse_from_str(time, "%H:%M")
+        .or_else(|_| NaiveTime::parse_from_str(time, "%H:%M:%S"))
+        .ok()
+}
+
+/// Parse a date written as "YYYY-MM-DD"
+pub fn parse_date(date: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d").ok()
+}
+
+pub fn format_date(date: NaiveDate) -> String {
+    date.format("%Y-%m-%d").to_string()
+}
+
+/// Check a schedule can be followed: a readable start date, and phases
+/// that each last at least a day and have a positive dose, readable times
+/// and days, and an interval of at least one day
+pub fn check_schedule(schedule: &PhasedSchedule) -> Result<()> {
+    if parse_date(&schedule.start_date).is_none() {
+        return Err(anyhow::anyhow!(
+            "Schedule start date '{}' is not YYYY-MM-DD",
+            schedule.start_date
+        ));
+    }
+    if schedule.phases.is_empty() {
+        return Err(anyhow::anyhow!("Schedule has no phases"));
+    }
+    if schedule.phases.len() > MAX_PHASES {
+        return Err(anyhow::anyhow!(
+            "Schedule has {} phases; at most {} are allowed",
+            schedule.phases.len(),
+            MAX_PHASES
+        ));
+    }
+
+    for (index, phase) in schedule.phases.iter().enumerate() {
+        let number = index + 1;
+        if !(1..=MAX_PHASE_DAYS).contains(&phase.duration_days) {
+            return Err(anyhow::anyhow!(
+                "Phase {} must last between 1 and {} days",
+                number,
+                MAX_PHASE_DAYS
+            ));
+        }
+        if !phase.dose_quantity.is_finite() || phase.dose_quantity <= 0.0 {
+            return Err(anyhow::anyhow!("Phase {} needs a positive dose", number));
+        }
+        if phase.times.is_empty() {
+            return Err(anyhow::anyhow!("Phase {} has no times", number));
+        }
+        if let Some(time) = phase.times.iter().find(|time| parse_time(time).is_none()) {
+            return Err(anyhow::anyhow!(
+                "Phase {} time '{}' is not HH:MM",
+                number,
+                time
+            ));
+        }
+        if parse_days(&phase.days).is_none() {
+            return Err(anyhow::anyhow!(
+                "Phase {} days '{}' are not recognised",
+                number,
+                phase.days
+            ));
+        }
+        if phase.every_n_days < 1 {
+            return Err(anyhow::anyhow!(
+                "Phase {} must repeat every 1 or more days",
+                number
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// The phase of `schedule` in effect on `date`: its index and its first
+/// and last days
+pub fn phase_on(
+    schedule: &PhasedSchedule,
+    date: NaiveDate,
+) -> Option<(usize, NaiveDate, NaiveDate)> {
+    let mut start = parse_date(&schedule.start_date)?;
+    if date < start {
+        return None;
+    }
+
+    for (index, phase) in schedule.phases.iter().enumerate() {
+        let days = phase.duration_days.clamp(1, MAX_PHASE_DAYS);
+        let end = start.checked_add_signed(Duration::days(days - 1))?;
+        if date <= end {
+            return Some((index, start, end));
+        }
+        start = end.succ_opt()?;
+    }
+
+    None
+}
+
+/// A dose due at a time of day
+pub struct DueDose<'a> {
+    pub time: NaiveTime,
+    pub source: DoseSource<'a>,
+}
+
+pub enum DoseSource<'a> {
+    Reminder(&'a Reminder),
+    Phase {
+        schedule: &'a PhasedSchedule,
+        index: usize,
+        phase: &'a SchedulePhase,
+    },
+}
+
+/// Doses of `med` due on `date`, earliest first: from the phase of
+/// `schedule` in effect that day, or otherwise from the medication's active
+/// reminders. Days outside the medication's course, and unreadable times
+/// and days, give no doses. `date` is local and a reminder's creation time
+/// is not, so reminders are not limited to the days since they were
+/// created here; `doses_between` does that.
+pub fn doses_on<'a>(
+    med: &'a Medication,
+    schedule: Option<&'a PhasedSchedule>,
+    date: NaiveDate,
+) -> Vec<DueDose<'a>> {
+    let mut doses = Vec::new();
+    if !in_course(med, date) {
+        return doses;
+    }
+
+    if let Some((schedule, (index, start, _))) =
+        schedule.and_then(|schedule| Some((schedule, phase_on(schedule, date)?)))
+    {
+        let phase = &schedule.phases[index];
+        let on_interval = (date - start).num_days() % phase.every_n_days.max(1) == 0;
+        let on_day = parse_days(&phase.days).is_some_and(|days| days.includes(date.weekday()));
+
+        if on_interval && on_day {
+            for time in phase.times.iter().filter_map(|time| parse_time(time)) {
+                doses.push(DueDose {
+                    time,
+                    source: DoseSource::Phase {
+                        schedule,
+                        index,
+                        phase,
+                    },
+                });
+            }
+        }
+    } else {
+        for reminder in med
+            .reminders
+            .iter()
+            .filter(|reminder| reminder.is_active && reminder.deleted_at.is_none())
+        {
+            let (Some(time), Some(days)) = (parse_time(&reminder.time), parse_days(&reminder.days))
+            else {
+                continue;
+            };
+            if days.includes(date.weekday()) {
+                doses.push(DueDose {
+                    time,
+                    source: DoseSource::Reminder(reminder),
+                });
+            }
+        }
+    }
+
+    doses.sort_by_key(|dose| dose.time);
+    doses
+}
+
+/// The local time zone at `minutes` from UTC
+pub fn utc_offset(minutes: i64) -> Result<FixedOffset> {
+    if minutes.abs() > MAX_UTC_OFFSET_MINUTES {
+        return Err(anyhow::anyhow!(
+            "UTC offset must be within {} minutes, got {}",
+            MAX_UTC_OFFSET_MINUTES,
+            minutes
+        ));
+    }
+    FixedOffset::east_opt(minutes as i32 * 60)
+        .ok_or_else(|| anyhow::anyhow!("UTC offset {} is out of range", minutes))
+}
+
+/// The start of local day `date` at `offset`
+pub fn local_midnight(date: NaiveDate, offset: FixedOffset) -> DateTime<Utc> {
+    offset
+        .from_local_datetime(&date.and_time(NaiveTime::MIN))
+        .single()
+        .map(|at| at.with_timezone(&Utc))
+        .unwrap_or_else(|| date.and_time(NaiveTime::MIN).and_utc())
+}
+
+/// Doses of `med` due from `from` to `to` inclusive, as `doses_on` gives
+/// them, with their times read as local times at `offset`. Reminder doses
+/// due before the reminder was created are left out. Earliest first.
+pub fn doses_between<'a>(
+    med: &'a Medication,
+    schedule: Option<&'a PhasedSchedule>,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    offset: FixedOffset,
+) -> Vec<(DateTime<Utc>, DueDose<'a>)> {
+    let first_day = from.with_timezone(&offset).date_naive();
+    let last_day = to.with_timezone(&offset).date_naive();
+
+    let mut doses = Vec::new();
+    for date in first_day.iter_days().take_while(|date| *date <= last_day) {
+        for dose in doses_on(med, schedule, date) {
+            let Some(at) = offset
+                .from_local_datetime(&date.and_time(dose.time))
+                .single()
+                .map(|at| at.with_timezone(&Utc))
+            else {
+                continue;
+            };
+            let created_at = match dose.source {
+                DoseSource::Reminder(reminder) => Some(reminder.created_at),
+                DoseSource::Phase { .. } => None,
+            };
+            if at >= from && at <= to && created_at.is_none_or(|created_at| at >= created_at) {
+                doses.push((at, dose));
+            }
+        }
+    }
+    doses
+}
+
+/// Whether `date` falls within the medication's course dates, if it has
+/// any. Unreadable dates do not limit the course.
+pub fn in_course(med: &Medication, date: NaiveDate) -> bool {
+    let after_start = med
+        .course_start_date
+        .as_deref()
+        .and_then(parse_date)
+        .is_none_or(|start| date >= start);
+    let before_end = med
+        .course_end_date
+        .as_deref()
+        .and_then(parse_date)
+        .is_none_or(|end| date <= end);
+    after_start && before_end
+}
+
+/// A phase's dose as text, e.g. "2.5 mg"
+pub fn describe_dose(phase: &SchedulePhase) -> String {
+    format!("{} {}", phase.dose_quantity, phase.dose_unit.trim())
+        .trim_end()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_days_named_sets() {
+        assert_eq!(parse_days(""), Some(DaySet::EveryDay));
+        assert_eq!(parse_days(" Daily "), Some(DaySet::EveryDay));
+        assert_eq!(parse_days("every day"), Some(DaySet::EveryDay));
+        assert_eq!(
+            parse_days("Weekends"),
+            Some(DaySet::Only(vec![Weekday::Sat, Weekday::Sun]))
+        );
+        assert_eq!(
+            parse_days("weekdays").map(|days| days.includes(Weekday::Fri)),
+            Some(true)
+        );
+    }
+
+    #[test]
+    fn parse_days_lists() {
+        // Sorted Monday first, repeats dropped, long names and
+        // abbreviations accepted
+        assert_eq!(
+            parse_days("Fri, monday,Wed tues wed"),
+            Some(DaySet::Only(vec![
+                Weekday::Mon,
+                Weekday::Tue,
+                Weekday::Wed,
+                Weekday::Fri
+            ]))
+        );
+        assert_eq!(
+            parse_days("Mon,Tue,Wed,Thu,Fri,Sat,Sun"),
+            Some(DaySet::EveryDay)
+        );
+    }
+
+    #[test]
+    fn parse_days_rejects_unknown_parts() {
+        assert_eq!(parse_days("Mon, someday"), None);
+        assert_eq!(parse_days("xy"), None);
+        assert_eq!(parse_days(" , "), None);
+    }
+
+    #[test]
+    fn parse_time_formats() {
+        assert_eq!(parse_time("08:30"), NaiveTime::from_hms_opt(8, 30, 0));
+        assert_eq!(parse_time(" 21:05:09 "), NaiveTime::from_hms_opt(21, 5, 9));
+        assert_eq!(parse_time("8am"), None);
+        assert_eq!(parse_time("25:00"), None);
+    }
+
+    fn date(value: &str) -> NaiveDate {
+        parse_date(value).unwrap()
+    }
+
+    fn phase(duration_days: i64, times: &[&str], days: &str, every_n_days: i64) -> SchedulePhase {
+        SchedulePhase {
+            duration_days,
+            dose_quantity: 1.0,
+            dose_unit: "tablets".to_string(),
+            times: times.iter().map(|time| time.to_string()).collect(),
+            days: days.to_string(),
+            every_n_days,
+        }
+    }
+
+    fn taper() -> PhasedSchedule {
+        // Mon 4 Mar to Wed 6 Mar twice a day, then every other day to
+        // Wed 13 Mar, then weekdays only to Fri 15 Mar
+        PhasedSchedule {
+            start_date: "2024-03-04".to_string(),
+            phases: vec![
+                phase(3, &["20:00", "08:00"], "Daily", 1),
+                phase(7, &["08:00"], "Daily", 2),
+                phase(2, &["09:00"], "Weekdays", 1),
+            ],
+            ..Default::default()
+        }
+    }
+
+    fn medication(reminders: &[&str]) -> Medication {
+        let created_at = DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        Medication {
+            name: "Prednisolone".to_string(),
+            created_at,
+            reminders: reminders
+                .iter()
+                .map(|time| Reminder {
+                    time: time.to_string(),
+                    days: "Daily".to_string(),
+                    created_at,
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    fn times(doses: &[DueDose]) -> Vec<String> {
+        doses
+            .iter()
+            .map(|dose| dose.time.format("%H:%M").to_string())
+            .collect()
+    }
+
+    #[test]
+    fn phase_on_finds_phase_bounds() {
+        let schedule = taper();
+        assert_eq!(phase_on(&schedule, date("2024-03-03")), None);
+        assert_eq!(
+            phase_on(&schedule, date("2024-03-04")),
+            Some((0, date("2024-03-04"), date("2024-03-06")))
+        );
+        assert_eq!(
+            phase_on(&schedule, date("2024-03-06")),
+            Some((0, date("2024-03-04"), date("2024-03-06")))
+        );
+        assert_eq!(
+            phase_on(&schedule, date("2024-03-07")),
+            Some((1, date("2024-03-07"), date("2024-03-13")))
+        );
+        assert_eq!(
+            phase_on(&schedule, date("2024-03-15")),
+            Some((2, date("2024-03-14"), date("2024-03-15")))
+        );
+        assert_eq!(phase_on(&schedule, date("2024-03-16")), None);
+    }
+
+    #[test]
+    fn phase_on_needs_a_readable_start() {
+        let schedule = PhasedSchedule {
+            start_date: "4 March".to_string(),
+            ..taper()
+        };
+        assert_eq!(phase_on(&schedule, date("2024-03-04")), None);
+    }
+
+    #[test]
+    fn doses_on_follows_phase_interval_and_days() {
+        let med = medication(&["12:00"]);
+        let schedule = taper();
+        let on = |day: &str| times(&doses_on(&med, Some(&schedule), date(day)));
+
+        // Sorted by time, and the phase replaces the reminders
+        assert_eq!(on("2024-03-05"), ["08:00", "20:00"]);
+        // Every other day from the start of the phase, not of the schedule
+        assert_eq!(on("2024-03-07"), ["08:00"]);
+        assert!(on("2024-03-08").is_empty());
+        assert_eq!(on("2024-03-13"), ["08:00"]);
+        // Weekdays only in the last phase
+        assert_eq!(on("2024-03-14"), ["09:00"]);
+        // Before and after the schedule, the reminders apply
+        assert_eq!(on("2024-03-03"), ["12:00"]);
+        assert_eq!(on("2024-03-16"), ["12:00"]);
+    }
+
+    #[test]
+    fn doses_on_reports_phase_source() {
+        let med = medication(&[]);
+        let schedule = taper();
+        let doses = doses_on(&med, Some(&schedule), date("2024-03-14"));
+        assert!(matches!(
+            doses[0].source,
+            DoseSource::Phase { index: 2, phase, .. } if phase.times == ["09:00"]
+        ));
+    }
+
+    #[test]
+    fn doses_on_stays_within_course() {
+        let med = Medication {
+            course_start_date: Some("2024-03-05".to_string()),
+            course_end_date: Some("2024-03-07".to_string()),
+            ..medication(&["08:00"])
+        };
+        let schedule = taper();
+        for day in ["2024-03-04", "2024-03-08"] {
+            assert!(doses_on(&med, None, date(day)).is_empty());
+            assert!(doses_on(&med, Some(&schedule), date(day)).is_empty());
+        }
+        assert_eq!(times(&doses_on(&med, None, date("2024-03-05"))), ["08:00"]);
+        assert_eq!(
+            times(&doses_on(&med, Some(&schedule), date("2024-03-07"))),
+            ["08:00"]
+        );
+    }
+
+    #[test]
+    fn doses_on_skips_inactive_and_unreadable_reminders() {
+        let mut med = medication(&["08:00", "12:00", "noon", "20:00"]);
+        med.reminders[1].is_active = false;
+        med.reminders[3].deleted_at = Some(Utc::now());
+        assert_eq!(times(&doses_on(&med, None, date("2024-03-04"))), ["08:00"]);
+    }
+
+    fn at(value: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(value)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    fn due_times(doses: &[(DateTime<Utc>, DueDose)]) -> Vec<DateTime<Utc>> {
+        doses.iter().map(|(at, _)| *at).collect()
+    }
+
+    #[test]
+    fn local_midnight_uses_the_offset() {
+        let new_york = utc_offset(-300).unwrap();
+        assert_eq!(
+            local_midnight(date("2024-03-03"), new_york),
+            at("2024-03-03T05:00:00Z")
+        );
+        let auckland = utc_offset(780).unwrap();
+        assert_eq!(
+            local_midnight(date("2024-03-03"), auckland),
+            at("2024-03-02T11:00:00Z")
+        );
+    }
+
+    #[test]
+    fn doses_between_reads_local_times() {
+        let med = medication(&["08:00"]);
+        let doses = doses_between(
+            &med,
+            None,
+            at("2024-03-04T00:00:00Z"),
+            at("2024-03-05T07:00:00Z"),
+            utc_offset(60).unwrap(),
+        );
+        assert_eq!(
+            due_times(&doses),
+            [at("2024-03-04T07:00:00Z"), at("2024-03-05T07:00:00Z")]
+        );
+    }
+
+    #[test]
+    fn doses_between_starts_when_reminder_was_created() {
+        // Created at 00:30 local time on the 5th, still the 4th in UTC
+        let mut med = medication(&["08:00"]);
+        med.reminders[0].created_at = at("2024-03-04T23:30:00Z");
+        let doses = doses_between(
+            &med,
+            None,
+            at("2024-03-04T00:00:00Z"),
+            at("2024-03-06T00:00:00Z"),
+            utc_offset(60).unwrap(),
+        );
+        assert_eq!(due_times(&doses), [at("2024-03-05T07:00:00Z")]);
+
+        // Created at 21:00 local time on the 4th, already the 5th in UTC
+        let mut med = medication(&["22:00"]);
+        med.reminders[0].created_at = at("2024-03-05T02:00:00Z");
+        let doses = doses_between(
+            &med,
+            None,
+            at("2024-03-04T00:00:00Z"),
+            at("2024-03-05T12:00:00Z"),
+            utc_offset(-300).unwrap(),
+        );
+        assert_eq!(due_times(&doses), [at("2024-03-05T03:00:00Z")]);
+    }
+}